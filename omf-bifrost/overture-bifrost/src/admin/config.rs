@@ -44,6 +44,16 @@ impl AccessMode {
 pub struct AdminConfig {
     pub allow_intersection_names: HashMap<String, bool>,
     pub admin_access: HashMap<String, HashMap<HighwayType, Vec<AccessMode>>>,
+    /// `drive_on_right` for admins that neither have a driving side nor inherit one from a parent
+    #[serde(default = "default_drive_on_right_fallback")]
+    pub drive_on_right_fallback: bool,
+    /// Also ingest maritime (`is_land = false`) division areas such as territorial waters
+    #[serde(default)]
+    pub include_maritime: bool,
+}
+
+fn default_drive_on_right_fallback() -> bool {
+    true
 }
 
 impl Default for AdminConfig {
@@ -418,6 +428,8 @@ impl Default for AdminConfig {
         Self {
             allow_intersection_names,
             admin_access,
+            drive_on_right_fallback: default_drive_on_right_fallback(),
+            include_maritime: false,
         }
     }
 }
@@ -505,6 +517,15 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_admin_config_without_fallback_defaults_to_right() {
+        let config: AdminConfig =
+            serde_json::from_str(r#"{"allow_intersection_names": {}, "admin_access": {}}"#)
+                .unwrap();
+        assert!(config.drive_on_right_fallback);
+        assert!(!config.include_maritime);
+    }
+
     #[test]
     fn test_admin_config_load_invalid_file_not_found() {
         let path = "/unlikely/path/that/does/not/exist/config.json";
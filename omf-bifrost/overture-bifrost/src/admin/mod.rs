@@ -1,16 +1,83 @@
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, bail};
 use duckdb::Connection as DuckConnection;
-use log::info;
-use rusqlite::{Connection, params};
+use log::{info, warn};
+use rusqlite::{Connection, OpenFlags, params};
+use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
 
+use crate::utils::interrupt::{check_interrupted, is_interrupted_error};
+use crate::utils::logging::{log_sql, log_sql_params, log_sql_with_params};
+
 mod config;
 
 pub use config::{
     AccessMode, AdminConfig, HighwayType, load_admin_config, save_default_admin_config,
 };
 
+/// SQLite pragma set used while building the admin database.
+///
+/// `Fast` turns off fsync and keeps the journal in memory, which makes the bulk import much
+/// quicker but can leave a corrupt database if the process or machine crashes mid-build.
+/// `Safe` uses a write-ahead log with `synchronous = NORMAL`, which is slower but keeps the
+/// database consistent across crashes; prefer it for production builds.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum DurabilityProfile {
+    #[default]
+    Fast,
+    Safe,
+}
+
+impl DurabilityProfile {
+    fn pragma_sql(self) -> &'static str {
+        match self {
+            DurabilityProfile::Fast => {
+                "PRAGMA synchronous = OFF;
+             PRAGMA journal_mode = MEMORY;
+             PRAGMA temp_store = MEMORY;"
+            }
+            DurabilityProfile::Safe => {
+                "PRAGMA journal_mode = WAL;
+             PRAGMA synchronous = NORMAL;
+             PRAGMA temp_store = MEMORY;"
+            }
+        }
+    }
+}
+
+fn apply_durability_profile(sqlite_con: &Connection, durability: DurabilityProfile) -> Result<()> {
+    info!("Using {:?} SQLite durability profile", durability);
+    execute_sqlite_batch(sqlite_con, durability.pragma_sql())?;
+    Ok(())
+}
+
+/// Which admins one `build_admins_from_geo_parquet` run adds to the database.
+///
+/// The default builds every level into a fresh database. Passes restricted to some levels keep
+/// the division ids in the `admins` table, so that a later `append` pass can link its admins to
+/// parents added earlier, e.g. level 2 first and level 4 in a second run.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct AdminPass {
+    /// Admin levels to include, all of `ADMIN_LEVEL_SUBTYPES` when empty
+    pub levels: Vec<i64>,
+    /// Add to the database of an earlier pass instead of replacing it
+    pub append: bool,
+}
+
+impl AdminPass {
+    /// Whether later passes may append to the result, which needs the division id columns
+    fn keeps_division_ids(&self) -> bool {
+        self.append || !self.levels.is_empty()
+    }
+}
+
+/// Division area subtypes imported for each admin level
+const ADMIN_LEVEL_SUBTYPES: &[(i64, &[&str])] =
+    &[(2, &["country", "dependency"]), (4, &["region"])];
+
+/// Rows inserted between progress reports and Ctrl-C checks
+const INTERRUPT_CHECK_ROWS: u64 = 1000;
+
 const CREATE_ADMINS_SQL: &str = "CREATE TABLE admins (
                 admin_level INTEGER NOT NULL,
                 iso_code TEXT,
@@ -35,6 +102,20 @@ const CREATE_ADMIN_INTERSECTION_INDEX_SQL: &str =
     "CREATE INDEX IdxAllowIntersectionNames ON admins ('allow_intersection_names');";
 const CREATE_SPATIAL_INDEX_SQL: &str = "SELECT CreateSpatialIndex('admins', 'geom');";
 
+const INSERT_ADMIN_SQL: &str = "INSERT INTO admins (
+                admin_level, iso_code, parent_admin, name, name_en,
+                drive_on_right, allow_intersection_names, default_language,
+                supported_languages, geom, division_id, parent_division_id
+            ) VALUES (?, ?, NULL, ?, ?, ?, ?, ?, ?, CastToMulti(GeomFromText(?, 4326)), ?, ?)";
+
+const DROP_TEMP_COLUMNS_SQL: &str = "ALTER TABLE admins DROP COLUMN division_id;
+         ALTER TABLE admins DROP COLUMN parent_division_id;";
+
+const INSERT_ADMIN_ACCESS_SQL: &str = "INSERT INTO admin_access (
+            admin_id, iso_code, trunk, trunk_link, track, footway, pedestrian, bridleway, cycleway, path, motorroad
+        ) SELECT rowid, iso_code, ?, ?, ?, ?, ?, ?, ?, ?, ?
+        FROM admins WHERE admin_level = 2 and iso_code = ?;";
+
 const CREATE_ADMIN_ACCESS_SQL: &str = "CREATE TABLE admin_access (
             admin_id INTEGER NOT NULL,
             iso_code TEXT,
@@ -82,19 +163,84 @@ fn get_allow_intersection_names(country: &str, admin_config: &AdminConfig) -> i6
         .unwrap_or(0)
 }
 
+/// Fails for levels without division subtypes, which the level filter would silently skip
+fn check_admin_levels(levels: &[i64]) -> Result<()> {
+    for level in levels {
+        if !ADMIN_LEVEL_SUBTYPES.iter().any(|(known, _)| known == level) {
+            bail!("Unsupported admin level {}, expected 2 or 4", level);
+        }
+    }
+    Ok(())
+}
+
+/// WHERE conditions selecting the division areas that become admins of `levels` (all when empty)
+fn admin_area_filter(admin_config: &AdminConfig, levels: &[i64]) -> String {
+    let subtypes: Vec<String> = ADMIN_LEVEL_SUBTYPES
+        .iter()
+        .filter(|(level, _)| levels.is_empty() || levels.contains(level))
+        .flat_map(|(_, subtypes)| subtypes.iter().map(|subtype| format!("'{}'", subtype)))
+        .collect();
+    let mut conditions = vec![
+        "area.geometry IS NOT NULL".to_string(),
+        format!("area.subtype IN ({})", subtypes.join(",")),
+    ];
+    if !admin_config.include_maritime {
+        conditions.insert(0, "area.is_land = TRUE".to_string());
+    }
+    conditions.join("\n            AND ")
+}
+
+/// Remove the database left behind by an interrupted build, other failures keep it for inspection
+fn remove_partial_output(path: &Path, err: &anyhow::Error) {
+    if !is_interrupted_error(err) {
+        return;
+    }
+    info!("Removing partial SQLite file at {}", path.display());
+    if let Err(remove_err) = fs::remove_file(path) {
+        warn!("Failed to remove {}: {}", path.display(), remove_err);
+    }
+}
+
+/// Run `insert` inside a transaction, rolling it back when it fails or is interrupted
+fn in_transaction<T>(sqlite_con: &Connection, insert: impl FnOnce() -> Result<T>) -> Result<T> {
+    execute_sqlite_batch(sqlite_con, "BEGIN;")?;
+    match insert() {
+        Ok(value) => {
+            execute_sqlite_batch(sqlite_con, "COMMIT;")?;
+            Ok(value)
+        }
+        Err(err) => {
+            warn!("Rolling back admin import: {}", err);
+            execute_sqlite_batch(sqlite_con, "ROLLBACK;")?;
+            Err(err)
+        }
+    }
+}
+
+/// Log and run a batch of SQLite statements
+fn execute_sqlite_batch(sqlite_con: &Connection, sql: &str) -> Result<()> {
+    log_sql("SQLite", sql);
+    sqlite_con.execute_batch(sql)?;
+    Ok(())
+}
+
 /// Creates and populates the Valhalla-compatible 'admins' table in SQLite, ingesting and transforming data from the Overture DuckDB source tables.
+/// An `append` pass adds the admins of its levels to the existing table instead.
 fn build_admins_table(
     duck_con: &DuckConnection,
     sqlite_con: &Connection,
     geoparquet_division_path: &str,
     geoparquet_area_path: &str,
     admin_config: &AdminConfig,
+    pass: &AdminPass,
 ) -> Result<()> {
-    info!("Creating admins table");
-    sqlite_con.execute_batch(CREATE_ADMINS_SQL)?;
+    if !pass.append {
+        info!("Creating admins table");
+        execute_sqlite_batch(sqlite_con, CREATE_ADMINS_SQL)?;
 
-    info!("Adding geometry column to admins table");
-    sqlite_con.execute_batch(ADD_ADMINS_GEOM_SQL)?;
+        info!("Adding geometry column to admins table");
+        execute_sqlite_batch(sqlite_con, ADD_ADMINS_GEOM_SQL)?;
+    }
 
     info!("Querying and joining division and area tables in DuckDB");
     let select_query = format!(
@@ -121,85 +267,145 @@ fn build_admins_table(
             ST_AsText(area.geometry) as wkt
         FROM read_parquet('{}') as area
         JOIN divs ON area.division_id = divs.div_id
-        WHERE area.is_land = TRUE
-            AND area.geometry IS NOT NULL
-            AND area.subtype IN ('country','dependency','region')",
-        geoparquet_division_path, geoparquet_area_path
+        WHERE {}",
+        geoparquet_division_path,
+        geoparquet_area_path,
+        admin_area_filter(admin_config, &pass.levels)
     );
 
+    log_sql("DuckDB", &select_query);
     let mut stmt = duck_con.prepare(&select_query)?;
     let mut rows = stmt.query([])?;
 
     info!("Processing admin records");
-    sqlite_con.execute_batch("BEGIN;")?;
-    let mut admin_count = 0u64;
-    while let Some(row) = rows.next()? {
-        let division_id: String = row.get(0)?;
-        let parent_division_id: Option<String> = row.get(1)?;
-        let admin_level: Option<i64> = row.get(2)?;
-        let country: String = row.get(3)?;
-        let region: Option<String> = row.get(4)?;
-        let name: Option<String> = row.get(5)?;
-        let name_en: Option<String> = row.get(6)?;
-        let driving_side: Option<String> = row.get(7)?;
-        let geom_wkt: String = row.get(8)?;
-
-        let admin_level = match admin_level {
-            Some(lvl) => lvl,
-            _ => continue,
-        };
+    log_sql("SQLite", INSERT_ADMIN_SQL);
+    let admin_count = in_transaction(sqlite_con, || {
+        let mut admin_count = 0u64;
+        while let Some(row) = rows.next()? {
+            let division_id: String = row.get(0)?;
+            let parent_division_id: Option<String> = row.get(1)?;
+            let admin_level: Option<i64> = row.get(2)?;
+            let country: String = row.get(3)?;
+            let region: Option<String> = row.get(4)?;
+            let name: Option<String> = row.get(5)?;
+            let name_en: Option<String> = row.get(6)?;
+            let driving_side: Option<String> = row.get(7)?;
+            let geom_wkt: String = row.get(8)?;
 
-        let iso_code = get_iso_code(admin_level, &country, &region);
-        let drive_on_right = get_drive_on_right(&driving_side);
-        let allow_intersection_names = get_allow_intersection_names(&country, admin_config);
+            let admin_level = match admin_level {
+                Some(lvl) => lvl,
+                _ => continue,
+            };
 
-        sqlite_con.execute(
-            "INSERT INTO admins (
-                admin_level, iso_code, parent_admin, name, name_en,
-                drive_on_right, allow_intersection_names, default_language,
-                supported_languages, geom, division_id, parent_division_id
-            ) VALUES (?, ?, NULL, ?, ?, ?, ?, ?, ?, CastToMulti(GeomFromText(?, 4326)), ?, ?)",
-            params![
-                admin_level,
-                iso_code,
-                name.unwrap_or_default(),
-                name_en.unwrap_or_default(),
-                drive_on_right,
-                allow_intersection_names,
-                Option::<String>::None,
-                Option::<String>::None,
-                geom_wkt,
-                division_id,
-                parent_division_id
-            ],
-        )?;
+            let iso_code = get_iso_code(admin_level, &country, &region);
+            let drive_on_right = get_drive_on_right(&driving_side);
+            let allow_intersection_names = get_allow_intersection_names(&country, admin_config);
 
-        admin_count += 1;
-        if admin_count % 1000 == 0 {
-            info!("{} admins processed so far...", admin_count);
+            log_sql_params(
+                "SQLite",
+                &[
+                    &admin_level,
+                    &iso_code,
+                    &name,
+                    &name_en,
+                    &drive_on_right,
+                    &allow_intersection_names,
+                    &geom_wkt,
+                    &division_id,
+                    &parent_division_id,
+                ],
+            );
+            sqlite_con.execute(
+                INSERT_ADMIN_SQL,
+                params![
+                    admin_level,
+                    iso_code,
+                    name.unwrap_or_default(),
+                    name_en.unwrap_or_default(),
+                    drive_on_right,
+                    allow_intersection_names,
+                    Option::<String>::None,
+                    Option::<String>::None,
+                    geom_wkt,
+                    division_id,
+                    parent_division_id
+                ],
+            )?;
+
+            admin_count += 1;
+            if admin_count.is_multiple_of(INTERRUPT_CHECK_ROWS) {
+                info!("{} admins processed so far...", admin_count);
+                check_interrupted()?;
+            }
         }
-    }
+        Ok(admin_count)
+    })?;
     info!("Finished inserting admin rows: {} total", admin_count);
-    sqlite_con.execute_batch("COMMIT;")?;
 
+    link_parent_admins(sqlite_con)?;
+
+    if pass.keeps_division_ids() {
+        info!("Keeping division ids for later passes");
+    } else {
+        info!("Dropping temporary columns");
+        execute_sqlite_batch(sqlite_con, DROP_TEMP_COLUMNS_SQL)?;
+    }
+
+    update_drive_on_right(
+        sqlite_con,
+        admin_config.drive_on_right_fallback,
+        pass.keeps_division_ids(),
+    )?;
+
+    // The indexes of the first pass are updated by the appended rows
+    if pass.append {
+        return Ok(());
+    }
+    create_admin_indexes(sqlite_con)
+}
+
+/// Fails unless the `admins` table of an earlier pass still has the division ids to link with
+fn check_appendable(sqlite_con: &Connection, sqlite_path: &str) -> Result<()> {
+    let sql = "SELECT COUNT(*) FROM pragma_table_info('admins') WHERE name = 'division_id'";
+    log_sql("SQLite", sql);
+    let columns: i64 = sqlite_con.query_row(sql, [], |row| row.get(0))?;
+    if columns == 0 {
+        bail!(
+            "Cannot append to {}: it was built without a level filter, so its admins cannot be linked",
+            sqlite_path
+        );
+    }
+    Ok(())
+}
+
+/// Links admins to their parent by division id, including admins of earlier passes whose
+/// parent level was missing then
+fn link_parent_admins(sqlite_con: &Connection) -> Result<()> {
     info!("Assigning parent_admin values");
-    sqlite_con.execute_batch(
+    execute_sqlite_batch(
+        sqlite_con,
         "UPDATE admins AS child
         SET parent_admin = (
             SELECT parent.rowid FROM admins AS parent
             WHERE parent.division_id = child.parent_division_id
         )
-        WHERE child.parent_division_id IS NOT NULL;",
-    )?;
-
-    info!("Dropping temporary columns");
-    sqlite_con.execute_batch(
-        "ALTER TABLE admins DROP COLUMN division_id;
-         ALTER TABLE admins DROP COLUMN parent_division_id;",
-    )?;
+        WHERE child.parent_division_id IS NOT NULL AND child.parent_admin IS NULL;",
+    )
+}
 
+/// Fills in missing `drive_on_right` values, first from the parent admin and then from
+/// `fallback` for admins that still have none. Returns the number of rows using the fallback.
+///
+/// With `awaiting_parents` the division ids are kept for a later pass, and admins whose parent
+/// is not in the table yet keep no value, so that they inherit from the parent once it is added.
+fn update_drive_on_right(
+    sqlite_con: &Connection,
+    fallback: bool,
+    awaiting_parents: bool,
+) -> Result<usize> {
     info!("Updating drive_on_right");
-    sqlite_con.execute_batch(
+    execute_sqlite_batch(
+        sqlite_con,
         "UPDATE admins
         SET drive_on_right = (
           SELECT parent.drive_on_right
@@ -209,31 +415,48 @@ fn build_admins_table(
         WHERE drive_on_right IS NULL;",
     )?;
 
+    let fallback_sql = if awaiting_parents {
+        "UPDATE admins SET drive_on_right = ?
+        WHERE drive_on_right IS NULL AND (parent_division_id IS NULL OR parent_admin IS NOT NULL);"
+    } else {
+        "UPDATE admins SET drive_on_right = ? WHERE drive_on_right IS NULL;"
+    };
+    log_sql_with_params("SQLite", fallback_sql, &[&fallback]);
+    let fallback_count = sqlite_con.execute(fallback_sql, params![fallback as i64])?;
+    if fallback_count > 0 {
+        warn!(
+            "{} admins have no driving side, using fallback drive_on_right = {}",
+            fallback_count, fallback as i64
+        );
+    }
+    Ok(fallback_count)
+}
+
+/// Creates the spatial and attribute indexes on a populated `admins` table.
+fn create_admin_indexes(sqlite_con: &Connection) -> Result<()> {
     info!("Creating spatial index");
-    sqlite_con.execute_batch(CREATE_SPATIAL_INDEX_SQL)?;
+    execute_sqlite_batch(sqlite_con, CREATE_SPATIAL_INDEX_SQL)?;
 
     info!("Creating level index");
-    sqlite_con.execute_batch(CREATE_ADMIN_INDEX_SQL)?;
+    execute_sqlite_batch(sqlite_con, CREATE_ADMIN_INDEX_SQL)?;
 
     info!("Creating Drive On Right index");
-    sqlite_con.execute_batch(CREATE_ADMIN_DRIVE_INDEX_SQL)?;
+    execute_sqlite_batch(sqlite_con, CREATE_ADMIN_DRIVE_INDEX_SQL)?;
 
     info!("Creating allow intersection names index");
-    sqlite_con.execute_batch(CREATE_ADMIN_INTERSECTION_INDEX_SQL)?;
+    execute_sqlite_batch(sqlite_con, CREATE_ADMIN_INTERSECTION_INDEX_SQL)?;
     Ok(())
 }
 
 /// Populates the `admin_access` table with per-country access rules from the admin config.
+/// The table is recreated on every pass, so countries appended later get their rules too.
 fn build_admin_access_table(sqlite_con: &Connection, admin_config: &AdminConfig) -> Result<()> {
     info!("Creating admin_access table");
-    sqlite_con.execute_batch(CREATE_ADMIN_ACCESS_SQL)?;
+    execute_sqlite_batch(sqlite_con, "DROP TABLE IF EXISTS admin_access;")?;
+    execute_sqlite_batch(sqlite_con, CREATE_ADMIN_ACCESS_SQL)?;
 
-    let mut stmt = sqlite_con.prepare(
-        "INSERT INTO admin_access (
-            admin_id, iso_code, trunk, trunk_link, track, footway, pedestrian, bridleway, cycleway, path, motorroad
-        ) SELECT rowid, iso_code, ?, ?, ?, ?, ?, ?, ?, ?, ?
-        FROM admins WHERE admin_level = 2 and iso_code = ?;"
-    )?;
+    log_sql("SQLite", INSERT_ADMIN_ACCESS_SQL);
+    let mut stmt = sqlite_con.prepare(INSERT_ADMIN_ACCESS_SQL)?;
     for (country_code, access_rules) in &admin_config.admin_access {
         let trunk = access_rules
             .get(&HighwayType::Trunk)
@@ -275,6 +498,21 @@ fn build_admin_access_table(sqlite_con: &Connection, admin_config: &AdminConfig)
             &motorroad,
             country_code as &dyn rusqlite::ToSql,
         ];
+        log_sql_params(
+            "SQLite",
+            &[
+                &trunk,
+                &trunk_link,
+                &track,
+                &footway,
+                &pedestrian,
+                &bridleway,
+                &cycleway,
+                &path,
+                &motorroad,
+                country_code,
+            ],
+        );
         let updated = stmt.execute(rusqlite::params_from_iter(params))?;
         if updated == 0 {
             log::warn!(
@@ -287,53 +525,287 @@ fn build_admin_access_table(sqlite_con: &Connection, admin_config: &AdminConfig)
     Ok(())
 }
 
+/// Loads the SpatiaLite extension.
+fn load_spatialite(sqlite_con: &Connection) -> Result<()> {
+    unsafe {
+        sqlite_con.load_extension_enable()?;
+        sqlite_con.load_extension("mod_spatialite", None::<&str>)
+            .context("Failed to load mod_spatialite extension. Make sure SpatiaLite is installed and 'mod_spatialite' is available in your library path.")?;
+        sqlite_con.load_extension_disable()?;
+    }
+    Ok(())
+}
+
+/// Loads the SpatiaLite extension and initializes the spatial metadata tables.
+fn init_spatialite(sqlite_con: &Connection) -> Result<()> {
+    load_spatialite(sqlite_con)?;
+    execute_sqlite_batch(sqlite_con, "SELECT InitSpatialMetaData(1);")?;
+    Ok(())
+}
+
+/// Key identifying an admin across inputs: its level, merged parent rowid and iso_code. Region
+/// iso_codes only hold the part after the country (`WA` for both US-WA and AU-WA), so the
+/// parent tells regions of different countries apart.
+type MergeKey = (i64, Option<i64>, String);
+
+/// Copies the `admins` rows of one source database into the merged database.
+/// Returns a map from source rowids to merged rowids. Admins whose [`MergeKey`] was already
+/// merged from an earlier input are not copied again but map to the existing row, so children
+/// still link to it; those rowids are also recorded in `duplicates`.
+fn merge_admins(
+    source: &Connection,
+    sqlite_con: &Connection,
+    merged_iso_codes: &mut HashMap<MergeKey, i64>,
+    duplicates: &mut Vec<i64>,
+) -> Result<HashMap<i64, i64>> {
+    // Parents have a lower admin level, so they are always inserted before their children
+    let select_query = "SELECT rowid, admin_level, iso_code, parent_admin, name, name_en,
+            drive_on_right, allow_intersection_names, default_language, supported_languages, geom
+        FROM admins ORDER BY admin_level, rowid";
+    let insert_query = "INSERT INTO admins (
+                admin_level, iso_code, parent_admin, name, name_en, drive_on_right,
+                allow_intersection_names, default_language, supported_languages, geom
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)";
+
+    log_sql("SQLite", select_query);
+    let mut select = source.prepare(select_query)?;
+    let mut rows = select.query([])?;
+    log_sql("SQLite", insert_query);
+    let mut insert = sqlite_con.prepare(insert_query)?;
+
+    let mut rowids = HashMap::new();
+    while let Some(row) = rows.next()? {
+        let rowid: i64 = row.get(0)?;
+        let admin_level: i64 = row.get(1)?;
+        let iso_code: Option<String> = row.get(2)?;
+        let parent_admin: Option<i64> = row.get(3)?;
+        let name: String = row.get(4)?;
+        let parent_admin = parent_admin.and_then(|parent| rowids.get(&parent).copied());
+
+        let key = iso_code
+            .clone()
+            .map(|iso_code| (admin_level, parent_admin, iso_code));
+        let existing = key.as_ref().and_then(|key| merged_iso_codes.get(key));
+        if let Some(&existing) = existing {
+            let iso_code = iso_code.as_deref().unwrap_or_default();
+            warn!(
+                "Admin '{}' (level {}, iso_code {}) already merged from an earlier input, skipping",
+                name, admin_level, iso_code
+            );
+            rowids.insert(rowid, existing);
+            duplicates.push(rowid);
+            continue;
+        }
+
+        let name_en: Option<String> = row.get(5)?;
+        let drive_on_right: Option<i64> = row.get(6)?;
+        let allow_intersection_names: Option<i64> = row.get(7)?;
+        let default_language: Option<String> = row.get(8)?;
+        let supported_languages: Option<String> = row.get(9)?;
+        let geom: Option<Vec<u8>> = row.get(10)?;
+
+        log_sql_params(
+            "SQLite",
+            &[
+                &admin_level,
+                &iso_code,
+                &parent_admin,
+                &name,
+                &name_en,
+                &drive_on_right,
+            ],
+        );
+        insert.execute(params![
+            admin_level,
+            iso_code,
+            parent_admin,
+            name,
+            name_en,
+            drive_on_right,
+            allow_intersection_names,
+            default_language,
+            supported_languages,
+            geom
+        ])?;
+
+        let merged_rowid = sqlite_con.last_insert_rowid();
+        if let Some(key) = key {
+            merged_iso_codes.insert(key, merged_rowid);
+        }
+        rowids.insert(rowid, merged_rowid);
+    }
+
+    Ok(rowids)
+}
+
+/// Copies the `admin_access` rows of one source database, pointing them at the merged admin rowids.
+/// Rules of admins skipped as duplicates are dropped, the earlier input's rules win.
+fn merge_admin_access(
+    source: &Connection,
+    sqlite_con: &Connection,
+    rowids: &HashMap<i64, i64>,
+    duplicates: &[i64],
+) -> Result<()> {
+    let select_query = "SELECT admin_id, iso_code, trunk, trunk_link, track, footway, pedestrian,
+            bridleway, cycleway, path, motorroad
+        FROM admin_access";
+    let insert_query = "INSERT INTO admin_access (
+            admin_id, iso_code, trunk, trunk_link, track, footway, pedestrian, bridleway, cycleway, path, motorroad
+        ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)";
+
+    log_sql("SQLite", select_query);
+    let mut select = source.prepare(select_query)?;
+    let mut rows = select.query([])?;
+    log_sql("SQLite", insert_query);
+    let mut insert = sqlite_con.prepare(insert_query)?;
+
+    while let Some(row) = rows.next()? {
+        let admin_id: i64 = row.get(0)?;
+        if duplicates.contains(&admin_id) {
+            continue;
+        }
+        let Some(&merged_admin_id) = rowids.get(&admin_id) else {
+            warn!(
+                "admin_access row references missing admin {}, skipping",
+                admin_id
+            );
+            continue;
+        };
+
+        let mut values: Vec<rusqlite::types::Value> = vec![merged_admin_id.into()];
+        for column in 1..11 {
+            values.push(row.get(column)?);
+        }
+        insert.execute(rusqlite::params_from_iter(values))?;
+    }
+
+    Ok(())
+}
+
+/// Merge several admin databases built by `build_admins_from_geo_parquet` into one.
+/// Rowids are reassigned in input order, with `parent_admin` and `admin_access.admin_id`
+/// remapped accordingly. An admin whose level, parent and iso_code already came from an earlier
+/// input (e.g. a country present in two regional extracts) is kept only once.
+/// The spatial and attribute indexes are rebuilt on the merged result.
+pub fn merge_admin_dbs(inputs: &[&Path], output: &Path) -> Result<()> {
+    info!(
+        "Merging {} admin databases into {}",
+        inputs.len(),
+        output.display()
+    );
+
+    if output.exists() {
+        info!("Removing existing SQLite file at {}", output.display());
+        fs::remove_file(output)?;
+    }
+    let sqlite_con = Connection::open(output)?;
+    apply_durability_profile(&sqlite_con, DurabilityProfile::Fast)?;
+    init_spatialite(&sqlite_con)?;
+
+    execute_sqlite_batch(&sqlite_con, CREATE_ADMINS_SQL)?;
+    execute_sqlite_batch(&sqlite_con, ADD_ADMINS_GEOM_SQL)?;
+    execute_sqlite_batch(&sqlite_con, DROP_TEMP_COLUMNS_SQL)?;
+    execute_sqlite_batch(&sqlite_con, CREATE_ADMIN_ACCESS_SQL)?;
+
+    let mut merged_iso_codes = HashMap::new();
+    let merged = in_transaction(&sqlite_con, || {
+        for input in inputs {
+            check_interrupted()?;
+            info!("Merging admins from {}", input.display());
+            let source = Connection::open_with_flags(input, OpenFlags::SQLITE_OPEN_READ_ONLY)
+                .with_context(|| format!("Failed to open admin database {}", input.display()))?;
+
+            let mut duplicates = Vec::new();
+            let rowids =
+                merge_admins(&source, &sqlite_con, &mut merged_iso_codes, &mut duplicates)?;
+            merge_admin_access(&source, &sqlite_con, &rowids, &duplicates)?;
+            info!(
+                "Merged {} admins from {} ({} duplicates skipped)",
+                rowids.len() - duplicates.len(),
+                input.display(),
+                duplicates.len()
+            );
+        }
+        Ok(())
+    });
+    if let Err(err) = merged {
+        drop(sqlite_con);
+        remove_partial_output(output, &err);
+        return Err(err);
+    }
+
+    create_admin_indexes(&sqlite_con)?;
+
+    info!("Admin merge completed and DB ready at {}", output.display());
+    Ok(())
+}
+
 /// Build the admin and access SQLite database from Overture DuckDB-derived geo-parquet division and area files, using a given AdminConfig.
 /// The Spatialite extension must be available in the environment for spatial support.
+/// `durability` selects the SQLite pragmas used during the import, see `DurabilityProfile`.
+/// `pass` restricts the run to some admin levels and appends to an earlier run, see `AdminPass`.
 pub fn build_admins_from_geo_parquet(
     geoparquet_division_path: &str,
     geoparquet_area_path: &str,
     sqlite_path: &str,
     admin_config: &AdminConfig,
+    durability: DurabilityProfile,
+    pass: &AdminPass,
 ) -> Result<()> {
     info!(
         "Preparing to build admins from {} and {} into {}",
         geoparquet_division_path, geoparquet_area_path, sqlite_path
     );
+    check_admin_levels(&pass.levels)?;
+    if !pass.levels.is_empty() {
+        info!("Including admin levels {:?}", pass.levels);
+    }
 
-    if Path::new(sqlite_path).exists() {
+    if pass.append {
+        if !Path::new(sqlite_path).exists() {
+            bail!(
+                "Cannot append to {}: no admin database from an earlier pass",
+                sqlite_path
+            );
+        }
+        info!("Appending to existing SQLite file at {}", sqlite_path);
+    } else if Path::new(sqlite_path).exists() {
         info!("Removing existing SQLite file at {}", sqlite_path);
         fs::remove_file(sqlite_path)?;
     }
     let sqlite_con = Connection::open(sqlite_path)?;
 
-    // Set SQLite pragmas for faster bulk import (less durability during import but higher performance)
-    sqlite_con.execute_batch(
-        "PRAGMA synchronous = OFF;
-             PRAGMA journal_mode = MEMORY;
-             PRAGMA temp_store = MEMORY;",
-    )?;
-
-    unsafe {
-        sqlite_con.load_extension_enable()?;
-        sqlite_con.load_extension("mod_spatialite", None::<&str>)
-            .context("Failed to load mod_spatialite extension. Make sure SpatiaLite is installed and 'mod_spatialite' is available in your library path.")?;
-        sqlite_con.load_extension_disable()?;
+    apply_durability_profile(&sqlite_con, durability)?;
+    if pass.append {
+        load_spatialite(&sqlite_con)?;
+        check_appendable(&sqlite_con, sqlite_path)?;
+    } else {
+        init_spatialite(&sqlite_con)?;
     }
-    sqlite_con.execute_batch("SELECT InitSpatialMetaData(1);")?;
 
     let duck_con = DuckConnection::open_in_memory()?;
     info!("Installing and loading DuckDB spatial extension");
     duck_con.execute("INSTALL spatial", [])?;
     duck_con.execute("LOAD spatial", [])?;
 
-    build_admins_table(
+    let built = build_admins_table(
         &duck_con,
         &sqlite_con,
         geoparquet_division_path,
         geoparquet_area_path,
         admin_config,
-    )?;
-    build_admin_access_table(&sqlite_con, admin_config)?;
+        pass,
+    )
+    .and_then(|_| check_interrupted())
+    .and_then(|_| build_admin_access_table(&sqlite_con, admin_config));
+    if let Err(err) = built {
+        drop(sqlite_con);
+        // The admins of earlier passes stay, the interrupted insert was rolled back
+        if !pass.append {
+            remove_partial_output(Path::new(sqlite_path), &err);
+        }
+        return Err(err);
+    }
 
     info!("Admin building completed and DB ready at {}", sqlite_path);
 
@@ -378,4 +850,224 @@ mod tests {
         assert_eq!(get_allow_intersection_names("GB", &ac), 0);
         assert_eq!(get_allow_intersection_names("XX", &ac), 0);
     }
+
+    #[test]
+    fn test_admin_area_filter_maritime() {
+        let mut ac = AdminConfig::default();
+        assert!(admin_area_filter(&ac, &[]).starts_with("area.is_land = TRUE"));
+
+        ac.include_maritime = true;
+        let filter = admin_area_filter(&ac, &[]);
+        assert!(!filter.contains("is_land"));
+        assert!(filter.contains("area.subtype IN ('country','dependency','region')"));
+    }
+
+    #[test]
+    fn test_admin_area_filter_levels() {
+        let ac = AdminConfig::default();
+        assert!(admin_area_filter(&ac, &[2]).contains("area.subtype IN ('country','dependency')"));
+        assert!(admin_area_filter(&ac, &[4]).contains("area.subtype IN ('region')"));
+        assert!(
+            admin_area_filter(&ac, &[4, 2])
+                .contains("area.subtype IN ('country','dependency','region')")
+        );
+
+        assert!(check_admin_levels(&[2, 4]).is_ok());
+        assert!(check_admin_levels(&[6]).is_err());
+    }
+
+    fn admin_db(rows: &str) -> Connection {
+        let sqlite_con = Connection::open_in_memory().unwrap();
+        sqlite_con.execute_batch(CREATE_ADMINS_SQL).unwrap();
+        // Stands in for the SpatiaLite geometry column
+        sqlite_con
+            .execute_batch("ALTER TABLE admins ADD COLUMN geom BLOB;")
+            .unwrap();
+        sqlite_con.execute_batch(rows).unwrap();
+        sqlite_con
+    }
+
+    #[test]
+    fn test_merge_keeps_regions_sharing_a_code_in_different_countries() {
+        let us = admin_db(
+            "INSERT INTO admins (rowid, admin_level, iso_code, parent_admin, name)
+             VALUES (1, 2, 'US', NULL, 'United States'),
+                    (2, 4, 'WA', 1, 'Washington');",
+        );
+        let au = admin_db(
+            "INSERT INTO admins (rowid, admin_level, iso_code, parent_admin, name)
+             VALUES (1, 2, 'AU', NULL, 'Australia'),
+                    (2, 4, 'WA', 1, 'Western Australia');",
+        );
+        // The US again, as in an overlapping extract
+        let us_again = admin_db(
+            "INSERT INTO admins (rowid, admin_level, iso_code, parent_admin, name)
+             VALUES (1, 2, 'US', NULL, 'United States'),
+                    (2, 4, 'WA', 1, 'Washington');",
+        );
+        let merged = admin_db("");
+
+        let mut merged_iso_codes = HashMap::new();
+        let mut duplicates = Vec::new();
+        for source in [&us, &au, &us_again] {
+            merge_admins(source, &merged, &mut merged_iso_codes, &mut duplicates).unwrap();
+        }
+        assert_eq!(duplicates, vec![1, 2]);
+
+        let admins: Vec<(i64, String, Option<i64>, String)> = merged
+            .prepare("SELECT rowid, iso_code, parent_admin, name FROM admins ORDER BY rowid")
+            .unwrap()
+            .query_map([], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+            })
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(
+            admins,
+            vec![
+                (1, "US".to_string(), None, "United States".to_string()),
+                (2, "WA".to_string(), Some(1), "Washington".to_string()),
+                (3, "AU".to_string(), None, "Australia".to_string()),
+                (
+                    4,
+                    "WA".to_string(),
+                    Some(3),
+                    "Western Australia".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_interrupt_rolls_back_admin_transaction() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+
+        use crate::utils::interrupt::check_interrupt_flag;
+
+        let interrupt = AtomicBool::new(false);
+        let sqlite_con = Connection::open_in_memory().unwrap();
+        sqlite_con.execute_batch(CREATE_ADMINS_SQL).unwrap();
+
+        let result = in_transaction(&sqlite_con, || {
+            for admin_count in 1..=5 * INTERRUPT_CHECK_ROWS {
+                sqlite_con.execute(
+                    "INSERT INTO admins (admin_level, name) VALUES (2, ?)",
+                    params![format!("admin {admin_count}")],
+                )?;
+                // Ctrl-C arrives in the middle of the first batch
+                if admin_count == 10 {
+                    interrupt.store(true, Ordering::SeqCst);
+                }
+                if admin_count.is_multiple_of(INTERRUPT_CHECK_ROWS) {
+                    check_interrupt_flag(&interrupt)?;
+                }
+            }
+            Ok(())
+        });
+
+        let err = result.unwrap_err();
+        assert!(is_interrupted_error(&err));
+        let count: i64 = sqlite_con
+            .query_row("SELECT COUNT(*) FROM admins", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 0);
+        assert!(sqlite_con.is_autocommit(), "transaction should be closed");
+    }
+
+    #[test]
+    fn test_safe_durability_profile_pragmas() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let sqlite_con = Connection::open(tmp_dir.path().join("admin.sqlite")).unwrap();
+
+        apply_durability_profile(&sqlite_con, DurabilityProfile::Safe).unwrap();
+
+        let journal_mode: String = sqlite_con
+            .query_row("PRAGMA journal_mode", [], |row| row.get(0))
+            .unwrap();
+        let synchronous: i64 = sqlite_con
+            .query_row("PRAGMA synchronous", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(journal_mode, "wal");
+        assert_eq!(synchronous, 1); // NORMAL
+    }
+
+    #[test]
+    fn test_fast_durability_profile_pragmas() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let sqlite_con = Connection::open(tmp_dir.path().join("admin.sqlite")).unwrap();
+
+        apply_durability_profile(&sqlite_con, DurabilityProfile::Fast).unwrap();
+
+        let journal_mode: String = sqlite_con
+            .query_row("PRAGMA journal_mode", [], |row| row.get(0))
+            .unwrap();
+        let synchronous: i64 = sqlite_con
+            .query_row("PRAGMA synchronous", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(journal_mode, "memory");
+        assert_eq!(synchronous, 0); // OFF
+    }
+
+    #[test]
+    fn test_orphan_admin_uses_drive_on_right_fallback() {
+        let sqlite_con = Connection::open_in_memory().unwrap();
+        sqlite_con.execute_batch(CREATE_ADMINS_SQL).unwrap();
+        sqlite_con
+            .execute_batch(
+                "INSERT INTO admins (rowid, admin_level, iso_code, parent_admin, name, drive_on_right)
+                 VALUES (1, 2, 'US', NULL, 'United States', 1),
+                        (2, 4, 'WA', 1, 'Washington', NULL),
+                        (3, 4, 'XX', NULL, 'Orphan', NULL);",
+            )
+            .unwrap();
+
+        let fallback_count = update_drive_on_right(&sqlite_con, false, false).unwrap();
+        assert_eq!(fallback_count, 1);
+
+        let drive_on_right: Vec<i64> = sqlite_con
+            .prepare("SELECT drive_on_right FROM admins ORDER BY rowid")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        // The region inherits from its parent, the orphan gets the configured fallback
+        assert_eq!(drive_on_right, vec![1, 1, 0]);
+    }
+
+    #[test]
+    fn test_region_pass_before_country_pass_inherits_drive_on_right() {
+        // A `--levels 4` pass whose regions have no driving side of their own
+        let sqlite_con = admin_db(
+            "INSERT INTO admins (rowid, admin_level, iso_code, parent_admin, name, drive_on_right,
+                                 division_id, parent_division_id)
+             VALUES (1, 4, 'KNT', NULL, 'Kent', NULL, 'kent', 'gb');",
+        );
+        link_parent_admins(&sqlite_con).unwrap();
+        let fallback_count = update_drive_on_right(&sqlite_con, true, true).unwrap();
+        assert_eq!(fallback_count, 0);
+
+        // A later `--levels 2 --append` pass adds the left-driving country
+        sqlite_con
+            .execute_batch(
+                "INSERT INTO admins (rowid, admin_level, iso_code, parent_admin, name, drive_on_right,
+                                     division_id, parent_division_id)
+                 VALUES (2, 2, 'GB', NULL, 'United Kingdom', 0, 'gb', NULL);",
+            )
+            .unwrap();
+        link_parent_admins(&sqlite_con).unwrap();
+        let fallback_count = update_drive_on_right(&sqlite_con, true, true).unwrap();
+        assert_eq!(fallback_count, 0);
+
+        let (parent_admin, drive_on_right): (i64, i64) = sqlite_con
+            .query_row(
+                "SELECT parent_admin, drive_on_right FROM admins WHERE rowid = 1",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(parent_admin, 2);
+        assert_eq!(drive_on_right, 0);
+    }
 }
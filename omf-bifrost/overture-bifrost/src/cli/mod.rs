@@ -1,11 +1,25 @@
-use std::path::PathBuf;
 use anyhow::Result;
-use clap::{Parser, Subcommand};
-use log::{debug, info};
+use clap::{Command, CommandFactory, Parser, Subcommand};
+use clap_complete::{Shell, generate};
+use log::{debug, error, info};
 use std::fs;
+use std::io::Write;
 use std::path::Path;
+use std::path::PathBuf;
+
+use overture_valhalla_writer::clip::ClipBox;
+use overture_valhalla_writer::profile::StageTimings;
+use overture_valhalla_writer::verify::verify_bin_files;
+use overture_valhalla_writer::writer::{
+    ConnectorlessPolicy, ConvertOptions, convert_overture_to_valhalla_with_timings,
+};
 
-use overture_valhalla_writer::writer::convert_overture_to_valhalla;
+use crate::admin::{AdminPass, DurabilityProfile};
+use crate::utils::download::{BoundingBox, default_release_version};
+use crate::utils::download_error::DownloadError;
+use crate::utils::manifest::write_conversion_manifest;
+use crate::utils::package::{PackageExtras, package_output};
+use crate::utils::selftest::run_selftest;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
@@ -14,6 +28,14 @@ pub struct Cli {
     #[arg(short, long, action = clap::ArgAction::Count)]
     pub verbose: u8,
 
+    /// Redact file paths, URLs and credentials from SQL written to the debug/trace log
+    #[arg(long, global = true)]
+    pub redact_sql: bool,
+
+    /// Print how long each stage of the command took (import, mapping, export, downloads...)
+    #[arg(long, global = true)]
+    pub profile: bool,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -61,9 +83,123 @@ enum Commands {
         output_dir: PathBuf,
 
         /// Number of parallel threads to use during conversion
+        /// Files with several row groups are read one row group per thread at a time
         /// Defaults to available CPU cores if not specified
         #[arg(short, long)]
         threads: Option<usize>,
+
+        /// Write empty output files instead of failing when no segment is routable
+        /// Valhalla will still refuse to build tiles from them
+        #[arg(long)]
+        allow_empty: bool,
+
+        /// Skip segments and connectors with implausible coordinates
+        /// Catches points at (0, 0), swapped lat/lon and large jumps between vertices
+        #[arg(long)]
+        strict_geometry: bool,
+
+        /// Round segment vertices and connector coordinates to this many decimals before matching
+        /// Helps when parquet float encoding makes them drift apart; output keeps full precision
+        #[arg(long, value_name = "DECIMALS")]
+        match_precision: Option<u32>,
+
+        /// Degrees a connector may lie from the vertex at its `at` position and still match by id (default 0.0001)
+        /// Connectors further away are matched by coordinate instead
+        #[arg(long, value_name = "DEGREES", help_heading = "Advanced")]
+        connector_snap_tolerance: Option<f64>,
+
+        /// Segment rows parsed per worker task in files with a single row group (default 1024)
+        /// Larger chunks lower scheduling overhead, smaller ones balance uneven rows better
+        #[arg(long, value_name = "ROWS", value_parser = clap::value_parser!(u64).range(1..), help_heading = "Advanced")]
+        chunk_size: Option<u64>,
+
+        /// Convert only this fraction of the segments, e.g. 0.1 for a 10% sample
+        /// Segments are picked by a seeded hash of their id, so the sample is reproducible
+        #[arg(long, value_name = "RATE", value_parser = parse_sample_rate)]
+        sample_rate: Option<f64>,
+
+        /// Seed selecting the `--sample-rate` sample
+        #[arg(long, default_value_t = 0, requires = "sample_rate")]
+        seed: u64,
+
+        /// Convert only the segments passing through this box, given as `xmin,ymin,xmax,ymax`
+        /// Rows with an Overture `bbox` column are rejected on it before their geometry is read
+        #[arg(long, value_name = "XMIN,YMIN,XMAX,YMAX", value_parser = parse_clip_bbox)]
+        clip_bbox: Option<ClipBox>,
+
+        /// JSON file with a saved bounding box `{"xmin", "ymin", "xmax", "ymax"}` to clip to
+        /// Takes the place of `--clip-bbox`, so the same file can drive `download` and `convert`
+        #[arg(long, value_name = "PATH", conflicts_with = "clip_bbox")]
+        bbox_file: Option<String>,
+
+        /// Write every road a second time with its nodes reversed (default true)
+        /// Transitional compatibility for current Valhalla builds, will be removed once roads are written once
+        #[arg(long, value_name = "BOOL", action = clap::ArgAction::Set, help_heading = "Advanced")]
+        emit_reverse_ways: Option<bool>,
+
+        /// Shorthand for `--emit-reverse-ways false`
+        #[arg(long, conflicts_with = "emit_reverse_ways", help_heading = "Advanced")]
+        no_reverse: bool,
+
+        /// Count segment references to missing connectors before converting and stop if there are any
+        #[arg(long)]
+        precheck: bool,
+
+        /// Also write auto_, pedestrian_ and bicycle_ prefixed ways.bin and way_nodes.bin
+        /// Each pair holds only the roads open to that travel mode
+        #[arg(long)]
+        split_by_mode: bool,
+
+        /// Also write sources.json, tracing every way and connector node back to its Overture
+        /// feature and the datasets (`sources`) it was derived from
+        #[arg(long)]
+        preserve_sources: bool,
+
+        /// What to do with segments that have no connectors: `skip` them, `synthesize` nodes
+        /// for their vertices and endpoints, or stop with an `error`
+        #[arg(long, value_name = "POLICY", default_value = "synthesize")]
+        connectorless_policy: ConnectorlessPolicy,
+
+        /// Also write the mapping summary (segments per road class, surface and denied access mode) as JSON
+        #[arg(long, value_name = "PATH")]
+        metrics_json: Option<PathBuf>,
+    },
+    /// Sanity-check the ways.bin and way_nodes.bin files written by `convert`
+    #[command(alias = "verify-tiles")]
+    VerifyBin {
+        /// Directory containing the binary files to check
+        #[arg(short, long)]
+        output_dir: PathBuf,
+    },
+    /// Download a tiny area, convert it and check the output, reporting PASS/FAIL per stage
+    Selftest {
+        /// Use the bundled sample instead of downloading
+        /// Without it the bundled sample is used only when the download cannot reach the network
+        #[arg(long)]
+        offline: bool,
+
+        /// Overture Maps release version to download
+        /// Defaults to the OVERTURE_RELEASE environment variable, then the built-in release
+        #[arg(short, long, default_value_t = default_release_version())]
+        release_version: String,
+    },
+    /// Bundle the files written by `convert` into a `.tar.zst` archive with a manifest
+    Package {
+        /// Directory containing the converted files
+        #[arg(short, long)]
+        output_dir: PathBuf,
+
+        /// Path of the `.tar.zst` archive to write
+        #[arg(short, long)]
+        archive: PathBuf,
+
+        /// Admin database written by `build-admins`, packaged as `admin.sqlite`
+        #[arg(long, value_name = "PATH")]
+        admin_db: Option<PathBuf>,
+
+        /// Valhalla config, packaged as `valhalla.json`
+        #[arg(long, value_name = "PATH")]
+        valhalla_config: Option<PathBuf>,
     },
     /// Build administrative data from Overture Maps data
     BuildAdmins {
@@ -85,6 +221,21 @@ enum Commands {
         /// Contains settings for administrative hierarchy and boundary processing
         #[arg(short, long)]
         config: Option<String>,
+
+        /// SQLite durability profile for the import: `fast` skips fsync and may corrupt the
+        /// database on a crash, `safe` uses a write-ahead log and is crash-safe but slower
+        #[arg(long, value_enum, default_value_t = DurabilityProfile::Fast)]
+        durability: DurabilityProfile,
+
+        /// Comma-separated admin levels to build in this run (2 for countries, 4 for regions);
+        /// all levels when omitted
+        #[arg(long, value_delimiter = ',')]
+        levels: Vec<i64>,
+
+        /// Add the admins to the database of an earlier `--levels` run instead of replacing it,
+        /// linking them to parents built before
+        #[arg(long)]
+        append: bool,
     },
     /// Generate the default admin config for customization
     GenerateAdminConfig {
@@ -103,7 +254,8 @@ enum Commands {
         output_file: String,
 
         /// Overture Maps release version
-        #[arg(short, long, default_value = "2025-05-21.0")]
+        /// Defaults to the OVERTURE_RELEASE environment variable, then the built-in release
+        #[arg(short, long, default_value_t = default_release_version())]
         release_version: String,
 
         /// Bounding box minimum longitude
@@ -121,7 +273,39 @@ enum Commands {
         /// Bounding box maximum latitude
         #[arg(long, default_value_t = 47.628727)]
         ymax: f64,
+
+        /// JSON file with a saved bounding box `{"xmin", "ymin", "xmax", "ymax"}`
+        /// Replaces the individual coordinate flags, which may not be given alongside it
+        #[arg(long, conflicts_with_all = ["xmin", "xmax", "ymin", "ymax"])]
+        bbox_file: Option<String>,
+
+        /// Expand the downloaded area by this many kilometers on every side
+        /// Keeps ways that leave the bounding box connected near its edges
+        #[arg(long, default_value_t = 0.0)]
+        buffer_km: f64,
+
+        /// Center of the area to download as `lat,lon`, used together with `--radius-km`
+        /// Replaces the individual coordinate flags and `--bbox-file`
+        #[arg(
+            long,
+            value_name = "LAT,LON",
+            value_parser = parse_center,
+            requires = "radius_km",
+            conflicts_with_all = ["xmin", "xmax", "ymin", "ymax", "bbox_file"]
+        )]
+        center: Option<(f64, f64)>,
+
+        /// Radius in kilometers around `--center` to download
+        #[arg(long, requires = "center")]
+        radius_km: Option<f64>,
+
+        /// Abort before downloading when the area holds more than this many features
+        /// Costs one count query up front; no cap when omitted
+        #[arg(long, value_name = "N")]
+        max_features: Option<u64>,
     },
+    /// List the available Overture Maps releases
+    ListReleases,
     /// Download sample Overture Maps administrative data
     DownloadAdmin {
         /// Directory where the downloaded admin data will be saved
@@ -137,7 +321,8 @@ enum Commands {
         output_division_areas_file: String,
 
         /// Overture Maps release version
-        #[arg(short, long, default_value = "2025-05-21.0")]
+        /// Defaults to the OVERTURE_RELEASE environment variable, then the built-in release
+        #[arg(short, long, default_value_t = default_release_version())]
         release_version: String,
 
         /// Bounding box minimum longitude
@@ -155,7 +340,108 @@ enum Commands {
         /// Bounding box maximum latitude
         #[arg(long, default_value_t = 47.628727)]
         ymax: f64,
+
+        /// JSON file with a saved bounding box `{"xmin", "ymin", "xmax", "ymax"}`
+        /// Replaces the individual coordinate flags, which may not be given alongside it
+        #[arg(long, conflicts_with_all = ["xmin", "xmax", "ymin", "ymax"])]
+        bbox_file: Option<String>,
     },
+    /// Print a shell completion script to stdout
+    /// Example: omf-bifrost completions bash > /etc/bash_completion.d/omf-bifrost
+    Completions {
+        /// Shell to generate the completions for
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+}
+
+/// Parse a `--sample-rate` fraction in (0, 1]
+fn parse_sample_rate(value: &str) -> std::result::Result<f64, String> {
+    let rate = value
+        .parse::<f64>()
+        .map_err(|e| format!("invalid sample rate '{}': {}", value, e))?;
+    if rate > 0.0 && rate <= 1.0 {
+        Ok(rate)
+    } else {
+        Err(format!("sample rate must be in (0, 1], got {}", rate))
+    }
+}
+
+/// Parse an `xmin,ymin,xmax,ymax` box for `--clip-bbox`
+fn parse_clip_bbox(value: &str) -> std::result::Result<ClipBox, String> {
+    let coordinates = value
+        .split(',')
+        .map(|coordinate| {
+            coordinate
+                .trim()
+                .parse::<f64>()
+                .map_err(|e| format!("invalid coordinate '{}': {}", coordinate, e))
+        })
+        .collect::<std::result::Result<Vec<f64>, String>>()?;
+    let [xmin, ymin, xmax, ymax] = coordinates[..] else {
+        return Err(format!("expected XMIN,YMIN,XMAX,YMAX but got '{}'", value));
+    };
+    if xmin > xmax || ymin > ymax {
+        return Err(format!(
+            "the minimum must not exceed the maximum in '{}'",
+            value
+        ));
+    }
+    Ok(ClipBox::new(xmin, ymin, xmax, ymax))
+}
+
+/// The clip box of `convert`: `--bbox-file` if given, otherwise `--clip-bbox`
+fn resolve_clip_box(
+    clip_bbox: Option<ClipBox>,
+    bbox_file: Option<&str>,
+) -> Result<Option<ClipBox>> {
+    let Some(path) = bbox_file else {
+        return Ok(clip_bbox);
+    };
+    info!("Clip bounding box file: {}", path);
+    let bbox = BoundingBox::from_json_file(path)?;
+    if bbox.xmin > bbox.xmax || bbox.ymin > bbox.ymax {
+        anyhow::bail!(
+            "The minimum must not exceed the maximum in the bounding box at '{}'",
+            path
+        );
+    }
+    Ok(Some(ClipBox::new(
+        bbox.xmin, bbox.ymin, bbox.xmax, bbox.ymax,
+    )))
+}
+
+/// Parse a `lat,lon` pair for `--center`
+fn parse_center(value: &str) -> std::result::Result<(f64, f64), String> {
+    let (lat, lon) = value
+        .split_once(',')
+        .ok_or_else(|| format!("expected LAT,LON but got '{}'", value))?;
+    let lat = lat
+        .trim()
+        .parse::<f64>()
+        .map_err(|e| format!("invalid latitude '{}': {}", lat, e))?;
+    let lon = lon
+        .trim()
+        .parse::<f64>()
+        .map_err(|e| format!("invalid longitude '{}': {}", lon, e))?;
+    Ok((lat, lon))
+}
+
+/// Resolve the bounding box from `--bbox-file` if given, otherwise from the coordinate flags
+fn resolve_bbox(
+    bbox_file: Option<&str>,
+    xmin: f64,
+    xmax: f64,
+    ymin: f64,
+    ymax: f64,
+) -> Result<BoundingBox> {
+    match bbox_file {
+        Some(path) => {
+            info!("Bounding box file: {}", path);
+            BoundingBox::from_json_file(path)
+        }
+        None => Ok(BoundingBox::new(xmin, ymin, xmax, ymax)),
+    }
 }
 
 /// Parse command line arguments
@@ -163,6 +449,29 @@ pub fn parse() -> Cli {
     Cli::parse()
 }
 
+/// The clap command definition behind `Cli`
+pub fn command() -> Command {
+    Cli::command()
+}
+
+/// Write the completion script of `shell` for all commands and flags to `out`
+pub fn write_completions(shell: Shell, out: &mut dyn Write) {
+    let mut command = command();
+    let name = command.get_name().to_string();
+    generate(shell, &mut command, name, out);
+}
+
+/// Error for the user: the download error followed by what to do about it, if anything
+fn with_download_hint(err: DownloadError) -> anyhow::Error {
+    match err.hint() {
+        Some(hint) => {
+            let message = format!("{}\n{}", err, hint);
+            anyhow::Error::new(err).context(message)
+        }
+        None => err.into(),
+    }
+}
+
 /// Run the command line interface with pre-parsed arguments
 pub fn run_with_args(cli: Cli) -> Result<()> {
     match cli.verbose {
@@ -171,6 +480,8 @@ pub fn run_with_args(cli: Cli) -> Result<()> {
         2 => debug!("Log level: DEBUG"),
         _ => debug!("Log level: TRACE"),
     }
+    crate::utils::logging::set_sql_redaction(cli.redact_sql);
+    let mut timings = StageTimings::new();
 
     match &cli.command {
         Commands::BuildTiles {
@@ -203,6 +514,22 @@ pub fn run_with_args(cli: Cli) -> Result<()> {
             input_dir,
             output_dir,
             threads,
+            allow_empty,
+            strict_geometry,
+            match_precision,
+            connector_snap_tolerance,
+            chunk_size,
+            sample_rate,
+            seed,
+            clip_bbox,
+            bbox_file,
+            emit_reverse_ways,
+            no_reverse,
+            precheck,
+            split_by_mode,
+            preserve_sources,
+            connectorless_policy,
+            metrics_json,
         } => {
             info!("Converting Overture Maps data to Valhalla binary format");
             info!("Input directory: {}", input_dir.display());
@@ -212,13 +539,48 @@ pub fn run_with_args(cli: Cli) -> Result<()> {
                 info!("Using {} threads", thread_count);
             }
 
-            convert_overture_to_valhalla(input_dir, output_dir)?;
+            let clip = resolve_clip_box(*clip_bbox, bbox_file.as_deref())?;
+            let options = ConvertOptions {
+                allow_empty: *allow_empty,
+                strict_geometry: *strict_geometry,
+                match_precision: *match_precision,
+                threads: *threads,
+                chunk_size: chunk_size.map(|rows| rows as usize),
+                sample_rate: *sample_rate,
+                seed: *seed,
+                connector_snap_tolerance: *connector_snap_tolerance,
+                emit_reverse_ways: if *no_reverse {
+                    Some(false)
+                } else {
+                    *emit_reverse_ways
+                },
+                precheck: *precheck,
+                split_by_mode: *split_by_mode,
+                preserve_sources: *preserve_sources,
+                connectorless_policy: *connectorless_policy,
+                clip,
+            };
+            let metrics = convert_overture_to_valhalla_with_timings(
+                input_dir,
+                output_dir,
+                &options,
+                &mut timings,
+            )?;
+            timings.time("manifest", || write_conversion_manifest(output_dir))?;
+            print!("{}", metrics.summary());
+            if let Some(path) = metrics_json {
+                metrics.write_json(path)?;
+                info!("Mapping metrics written to {}", path.display());
+            }
         }
         Commands::BuildAdmins {
             divisions,
             division_areas,
             output_dir,
             config,
+            durability,
+            levels,
+            append,
         } => {
             info!("Building administrative data from Overture Maps data");
             info!("Input: {}; {}", divisions, division_areas);
@@ -226,12 +588,20 @@ pub fn run_with_args(cli: Cli) -> Result<()> {
 
             let admin_config = crate::admin::load_admin_config(config.as_deref())?;
             let sqlite_path = format!("{}/admin.sqlite", output_dir);
-            crate::admin::build_admins_from_geo_parquet(
-                divisions,
-                division_areas,
-                &sqlite_path,
-                &admin_config,
-            )?;
+            let pass = AdminPass {
+                levels: levels.clone(),
+                append: *append,
+            };
+            timings.time("build-admins", || {
+                crate::admin::build_admins_from_geo_parquet(
+                    divisions,
+                    division_areas,
+                    &sqlite_path,
+                    &admin_config,
+                    *durability,
+                    &pass,
+                )
+            })?;
             info!("Admin building complete, db at {}", sqlite_path);
         }
         Commands::GenerateAdminConfig { output } => {
@@ -246,10 +616,40 @@ pub fn run_with_args(cli: Cli) -> Result<()> {
             xmax,
             ymin,
             ymax,
+            bbox_file,
+            buffer_km,
+            center,
+            radius_km,
+            max_features,
         } => {
+            let bbox = match (center, radius_km) {
+                (Some((lat, lon)), Some(radius_km)) => {
+                    info!("Center: {}, {}; radius: {} km", lat, lon, radius_km);
+                    BoundingBox::from_center(*lat, *lon, *radius_km)?
+                }
+                _ => resolve_bbox(bbox_file.as_deref(), *xmin, *xmax, *ymin, *ymax)?,
+            };
             info!("Downloading Overture Maps transportation data");
             info!("Release version: {}", release_version);
-            info!("Bounding box: ({}, {}) to ({}, {})", xmin, ymin, xmax, ymax);
+            info!(
+                "Bounding box: ({}, {}) to ({}, {})",
+                bbox.xmin, bbox.ymin, bbox.xmax, bbox.ymax
+            );
+
+            if *buffer_km < 0.0 {
+                anyhow::bail!("--buffer-km must not be negative, got {}", buffer_km);
+            }
+            let download_bbox = bbox.expanded_by_km(*buffer_km);
+            if *buffer_km > 0.0 {
+                info!(
+                    "Download bounding box (buffered by {} km): ({}, {}) to ({}, {})",
+                    buffer_km,
+                    download_bbox.xmin,
+                    download_bbox.ymin,
+                    download_bbox.xmax,
+                    download_bbox.ymax
+                );
+            }
             info!("Output path: {}/{}", output_dir, output_file);
 
             // Create output directory if it doesn't exist
@@ -259,17 +659,78 @@ pub fn run_with_args(cli: Cli) -> Result<()> {
             }
 
             // Use duckdb to download the data
-            crate::utils::download::download_overture_data(
-                release_version,
-                *xmin,
-                *xmax,
-                *ymin,
-                *ymax,
-                &output_path.to_string_lossy(),
-            )?;
+            timings.time("download", || {
+                crate::utils::download::download_overture_data(
+                    release_version,
+                    download_bbox.xmin,
+                    download_bbox.xmax,
+                    download_bbox.ymin,
+                    download_bbox.ymax,
+                    &output_path.to_string_lossy(),
+                    *max_features,
+                )
+                .map_err(with_download_hint)
+            })?;
 
             info!("Download complete! Data saved to {}", output_path.display());
         }
+        Commands::VerifyBin { output_dir } => {
+            info!("Verifying binary files in {}", output_dir.display());
+            let violations = verify_bin_files(output_dir)?;
+            if !violations.is_empty() {
+                for violation in &violations {
+                    error!("{}", violation);
+                }
+                anyhow::bail!(
+                    "{} failed verification ({} violations shown)",
+                    output_dir.display(),
+                    violations.len()
+                );
+            }
+            println!("{}: all checks passed", output_dir.display());
+        }
+        Commands::Selftest {
+            offline,
+            release_version,
+        } => {
+            let report = timings.time("selftest", || run_selftest(release_version, *offline))?;
+            print!("{}", report.summary());
+            if !report.passed() {
+                anyhow::bail!("self-test failed");
+            }
+        }
+        Commands::Package {
+            output_dir,
+            archive,
+            admin_db,
+            valhalla_config,
+        } => {
+            info!(
+                "Packaging {} into {}",
+                output_dir.display(),
+                archive.display()
+            );
+            let extras = PackageExtras {
+                admin_db: admin_db.as_deref(),
+                valhalla_config: valhalla_config.as_deref(),
+            };
+            let manifest = package_output(output_dir, archive, &extras)?;
+            println!(
+                "{}: packaged {} files",
+                archive.display(),
+                manifest.files.len()
+            );
+        }
+        Commands::ListReleases => {
+            let releases = crate::utils::download::list_overture_releases();
+            for release in releases {
+                if release == default_release_version() {
+                    println!("{} (default)", release);
+                } else {
+                    println!("{}", release);
+                }
+            }
+        }
         Commands::DownloadAdmin {
             output_dir,
             output_divisions_file,
@@ -279,10 +740,15 @@ pub fn run_with_args(cli: Cli) -> Result<()> {
             xmax,
             ymin,
             ymax,
+            bbox_file,
         } => {
+            let bbox = resolve_bbox(bbox_file.as_deref(), *xmin, *xmax, *ymin, *ymax)?;
             info!("Downloading Overture Maps admin divisions data");
             info!("Release version: {}", release_version);
-            info!("Bounding box: ({}, {}) to ({}, {})", xmin, ymin, xmax, ymax);
+            info!(
+                "Bounding box: ({}, {}) to ({}, {})",
+                bbox.xmin, bbox.ymin, bbox.xmax, bbox.ymax
+            );
             info!("Output path: {}", output_dir);
             info!("Output divisions file: {}", output_divisions_file);
             info!("Output division areas file: {}", output_division_areas_file);
@@ -294,15 +760,18 @@ pub fn run_with_args(cli: Cli) -> Result<()> {
                 if !Path::new(output_dir).exists() {
                     fs::create_dir_all(output_dir)?;
                 }
-                crate::utils::download::download_overture_admins(
-                    release_version,
-                    *xmin,
-                    *xmax,
-                    *ymin,
-                    *ymax,
-                    &output_divisions_path.to_string_lossy(),
-                    &output_division_areas_path.to_string_lossy(),
-                )?;
+                timings.time("download-admin", || {
+                    crate::utils::download::download_overture_admins(
+                        release_version,
+                        bbox.xmin,
+                        bbox.xmax,
+                        bbox.ymin,
+                        bbox.ymax,
+                        &output_divisions_path.to_string_lossy(),
+                        &output_division_areas_path.to_string_lossy(),
+                    )
+                    .map_err(with_download_hint)
+                })?;
                 info!(
                     "Admin downloads complete! Divisions: {}, Areas: {}",
                     output_divisions_path.display(),
@@ -310,7 +779,61 @@ pub fn run_with_args(cli: Cli) -> Result<()> {
                 );
             }
         }
+        Commands::Completions { shell } => {
+            write_completions(*shell, &mut std::io::stdout());
+        }
+    }
+
+    if cli.profile && !timings.is_empty() {
+        print!("{}", timings.summary());
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bash_completions() {
+        let mut out = Vec::new();
+        write_completions(Shell::Bash, &mut out);
+        let script = String::from_utf8(out).unwrap();
+        assert!(!script.is_empty());
+        assert!(script.contains("build-tiles"));
+        assert!(script.contains("download"));
+    }
+
+    #[test]
+    fn test_convert_bbox_file() {
+        let convert_args = |extra: &[&str]| {
+            let mut args = vec!["overture-bifrost", "convert", "-i", "in", "-o", "out"];
+            args.extend_from_slice(extra);
+            Cli::try_parse_from(args)
+        };
+        let cli = convert_args(&["--bbox-file", "bbox.json"]).unwrap();
+        let Commands::Convert {
+            bbox_file,
+            clip_bbox,
+            ..
+        } = cli.command
+        else {
+            panic!("expected the convert command");
+        };
+        assert_eq!(bbox_file.as_deref(), Some("bbox.json"));
+        assert!(clip_bbox.is_none());
+
+        let err =
+            convert_args(&["--bbox-file", "bbox.json", "--clip-bbox", "0,0,1,1"]).unwrap_err();
+        assert_eq!(err.kind(), clap::error::ErrorKind::ArgumentConflict);
+
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let path = file.path().to_str().unwrap();
+        BoundingBox::new(-122.4, 47.6, -122.3, 47.7)
+            .save_json_file(path)
+            .unwrap();
+        let clip = resolve_clip_box(None, Some(path)).unwrap();
+        assert_eq!(clip, Some(ClipBox::new(-122.4, 47.6, -122.3, 47.7)));
+    }
+}
@@ -1 +1,2 @@
-
+pub mod valhalla;
+pub mod valhalla_config;
@@ -0,0 +1,3 @@
+//! Valhalla way attributes of mapped Overture segments
+
+pub use overture_valhalla_writer::mapped::{MappedSegment, MappingContext, map_segment};
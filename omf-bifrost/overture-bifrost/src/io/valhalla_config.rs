@@ -0,0 +1,222 @@
+//! Typed subset of the Valhalla JSON configuration that bifrost sets
+
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// Default paths, matching `valhalla_build_config`
+pub const DEFAULT_TILE_DIR: &str = "/data/valhalla";
+pub const DEFAULT_ADMIN: &str = "/data/valhalla/admin.sqlite";
+pub const DEFAULT_TIMEZONE: &str = "/data/valhalla/tz_world.sqlite";
+
+/// Log output types Valhalla accepts
+const LOGGING_TYPES: &[&str] = &["std_out", "file"];
+
+/// Valhalla configuration. Sections and keys bifrost does not model are kept in `other`
+/// so a merged config round-trips them unchanged.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ValhallaConfig {
+    #[serde(default)]
+    pub mjolnir: MjolnirConfig,
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+/// The `mjolnir` section used by the tile builder
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MjolnirConfig {
+    pub tile_dir: String,
+    /// Admin database written by `build-admins`
+    pub admin: String,
+    pub timezone: String,
+    pub logging: LoggingConfig,
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+impl Default for MjolnirConfig {
+    fn default() -> Self {
+        Self {
+            tile_dir: DEFAULT_TILE_DIR.to_string(),
+            admin: DEFAULT_ADMIN.to_string(),
+            timezone: DEFAULT_TIMEZONE.to_string(),
+            logging: LoggingConfig::default(),
+            other: Map::new(),
+        }
+    }
+}
+
+/// Logging settings of a Valhalla section
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LoggingConfig {
+    /// `std_out` or `file`
+    #[serde(rename = "type")]
+    pub log_type: String,
+    pub color: bool,
+    /// Log file, only used with the `file` type
+    pub file_name: String,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            log_type: "std_out".to_string(),
+            color: true,
+            file_name: "path_to_some_file.log".to_string(),
+        }
+    }
+}
+
+/// Merge `overrides` into `base`: objects merge key by key, any other value replaces
+fn merge_values(base: &mut Value, overrides: &Value) {
+    match (base, overrides) {
+        (Value::Object(base), Value::Object(overrides)) => {
+            for (key, value) in overrides {
+                match base.get_mut(key) {
+                    Some(existing) => merge_values(existing, value),
+                    None => {
+                        base.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+        }
+        (base, overrides) => *base = overrides.clone(),
+    }
+}
+
+impl ValhallaConfig {
+    /// Default config with all paths under `tile_dir`
+    pub fn for_tile_dir(tile_dir: &Path) -> Self {
+        let mut config = Self::default();
+        config.mjolnir.tile_dir = tile_dir.display().to_string();
+        config.mjolnir.admin = tile_dir.join("admin.sqlite").display().to_string();
+        config.mjolnir.timezone = tile_dir.join("tz_world.sqlite").display().to_string();
+        config
+    }
+
+    pub fn from_json_str(json: &str) -> Result<Self> {
+        serde_json::from_str(json).context("Valhalla config is not valid JSON")
+    }
+
+    pub fn from_json_file(path: &Path) -> Result<Self> {
+        let json = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read Valhalla config '{}'", path.display()))?;
+        Self::from_json_str(&json)
+            .with_context(|| format!("Invalid Valhalla config '{}'", path.display()))
+    }
+
+    /// Apply JSON overrides such as `--inline-config '{"mjolnir":{"tile_dir":"/custom/path"}}'`
+    pub fn merge_json(&mut self, overrides: &str) -> Result<()> {
+        let overrides: Value =
+            serde_json::from_str(overrides).context("Config overrides are not valid JSON")?;
+        if !overrides.is_object() {
+            bail!("Config overrides must be a JSON object");
+        }
+        let mut merged = serde_json::to_value(&*self)?;
+        merge_values(&mut merged, &overrides);
+        *self = serde_json::from_value(merged).context("Config overrides have the wrong types")?;
+        Ok(())
+    }
+
+    /// Check the settings Valhalla would otherwise only reject while building
+    pub fn validate(&self) -> Result<()> {
+        let mjolnir = &self.mjolnir;
+        if mjolnir.tile_dir.is_empty() {
+            bail!("mjolnir.tile_dir must not be empty");
+        }
+        if !LOGGING_TYPES.contains(&mjolnir.logging.log_type.as_str()) {
+            bail!(
+                "mjolnir.logging.type must be one of {:?}, got '{}'",
+                LOGGING_TYPES,
+                mjolnir.logging.log_type
+            );
+        }
+        if mjolnir.logging.log_type == "file" && mjolnir.logging.file_name.is_empty() {
+            bail!("mjolnir.logging.file_name is required for file logging");
+        }
+        Ok(())
+    }
+
+    pub fn to_json_string(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Validate and write the config as the JSON file Valhalla reads
+    pub fn write(&self, path: &Path) -> Result<()> {
+        self.validate()?;
+        fs::write(path, self.to_json_string()?)
+            .with_context(|| format!("Failed to write Valhalla config '{}'", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_values() {
+        let config = ValhallaConfig::default();
+        assert_eq!(config.mjolnir.tile_dir, DEFAULT_TILE_DIR);
+        assert_eq!(config.mjolnir.admin, DEFAULT_ADMIN);
+        assert_eq!(config.mjolnir.timezone, DEFAULT_TIMEZONE);
+        assert_eq!(config.mjolnir.logging.log_type, "std_out");
+        assert!(config.mjolnir.logging.color);
+        config.validate().unwrap();
+
+        // Missing keys fall back to the defaults
+        let config = ValhallaConfig::from_json_str(r#"{"mjolnir":{"tile_dir":"/tiles"}}"#).unwrap();
+        assert_eq!(config.mjolnir.tile_dir, "/tiles");
+        assert_eq!(config.mjolnir.admin, DEFAULT_ADMIN);
+        assert_eq!(config.mjolnir.logging, LoggingConfig::default());
+    }
+
+    #[test]
+    fn test_serialized_key_names() {
+        let config = ValhallaConfig::for_tile_dir(Path::new("/tiles"));
+        let json: Value = serde_json::from_str(&config.to_json_string().unwrap()).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "mjolnir": {
+                    "tile_dir": "/tiles",
+                    "admin": "/tiles/admin.sqlite",
+                    "timezone": "/tiles/tz_world.sqlite",
+                    "logging": {
+                        "type": "std_out",
+                        "color": true,
+                        "file_name": "path_to_some_file.log"
+                    }
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_merge_keeps_unmodelled_keys() {
+        let mut config = ValhallaConfig::from_json_str(
+            r#"{"mjolnir":{"tile_dir":"/tiles","concurrency":4},"loki":{"actions":["route"]}}"#,
+        )
+        .unwrap();
+        config
+            .merge_json(r#"{"mjolnir":{"logging":{"type":"file","file_name":"/tmp/mjolnir.log"}}}"#)
+            .unwrap();
+        config.validate().unwrap();
+
+        assert_eq!(config.mjolnir.tile_dir, "/tiles");
+        assert_eq!(config.mjolnir.logging.log_type, "file");
+        assert!(config.mjolnir.logging.color);
+        assert_eq!(config.mjolnir.other["concurrency"], 4);
+        assert_eq!(config.other["loki"]["actions"][0], "route");
+
+        assert!(config.merge_json(r#"{"mjolnir":{"tile_dir":7}}"#).is_err());
+        config
+            .merge_json(r#"{"mjolnir":{"logging":{"type":"syslog"}}}"#)
+            .unwrap();
+        assert!(config.validate().is_err());
+    }
+}
@@ -1,6 +1,9 @@
 use anyhow::Result;
 use env_logger::Env;
 use log::{error, info};
+use overture_bifrost::utils::interrupt::{
+    INTERRUPTED_EXIT_CODE, install_interrupt_handler, is_interrupted_error,
+};
 use overture_bifrost::utils::logging::configure_logging;
 
 fn main() -> Result<()> {
@@ -12,6 +15,7 @@ fn main() -> Result<()> {
     env_logger::Builder::from_env(Env::default().default_filter_or(log_level.to_string())).init();
 
     info!("Starting overture-bifrost");
+    install_interrupt_handler()?;
 
     // Run the CLI with the already parsed arguments and match on the result
     match overture_bifrost::cli::run_with_args(cli) {
@@ -19,6 +23,10 @@ fn main() -> Result<()> {
             info!("Operation completed successfully");
             Ok(())
         }
+        Err(e) if is_interrupted_error(&e) => {
+            error!("Interrupted, partial output was removed");
+            std::process::exit(INTERRUPTED_EXIT_CODE);
+        }
         Err(e) => {
             error!("Error: {}", e);
             std::process::exit(1);
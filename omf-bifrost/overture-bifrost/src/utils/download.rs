@@ -1,6 +1,58 @@
-use anyhow::{Context, Result};
+use std::fs::{self, File};
+use std::path::Path;
+
+use anyhow::{Context, Result, bail};
 use duckdb::{Connection, params};
-use log::{debug, info};
+use log::{info, warn};
+use parquet::file::reader::{FileReader, SerializedFileReader};
+use serde::{Deserialize, Serialize};
+
+use crate::utils::download_error::{DownloadError, DownloadResult};
+use crate::utils::interrupt::{CancelOnInterrupt, Interrupted, is_interrupted};
+use crate::utils::logging::{log_sql, log_sql_with_params, param_for_log, sql_for_log};
+
+/// Approximate length of one degree of latitude (and of longitude at the equator)
+const KM_PER_DEGREE: f64 = 111.32;
+
+/// Release used when none is specified and `OVERTURE_RELEASE` is not set
+pub const DEFAULT_RELEASE_VERSION: &str = "2025-05-21.0";
+
+/// Environment variable overriding the default release at runtime
+pub const RELEASE_ENV_VAR: &str = "OVERTURE_RELEASE";
+
+/// Effective default release: `OVERTURE_RELEASE` when set and non-empty, else [`DEFAULT_RELEASE_VERSION`]
+pub fn default_release_version() -> String {
+    default_release_version_with(process_env)
+}
+
+/// Like [`default_release_version`], reading variables through `env` instead of the process
+/// environment
+pub fn default_release_version_with(env: impl Fn(&str) -> Option<String>) -> String {
+    release_version_from(env(RELEASE_ENV_VAR))
+}
+
+fn process_env(name: &str) -> Option<String> {
+    std::env::var(name).ok()
+}
+
+/// The release named by a value of `OVERTURE_RELEASE`, [`DEFAULT_RELEASE_VERSION`] when it is
+/// unset or blank
+pub fn release_version_from(env_value: Option<String>) -> String {
+    env_value
+        .map(|release| release.trim().to_string())
+        .filter(|release| !release.is_empty())
+        .unwrap_or_else(|| DEFAULT_RELEASE_VERSION.to_string())
+}
+
+/// Releases known at build time, newest first, used when the bucket cannot be listed
+pub const KNOWN_RELEASES: &[&str] = &[
+    "2025-05-21.0",
+    "2025-04-23.0",
+    "2025-03-19.1",
+    "2025-02-19.0",
+    "2025-01-22.0",
+    "2024-12-18.0",
+];
 
 pub struct OvertureMapsConfig {
     pub base_url: String,
@@ -9,14 +61,49 @@ pub struct OvertureMapsConfig {
 
 impl Default for OvertureMapsConfig {
     fn default() -> Self {
+        Self::default_with(process_env)
+    }
+}
+
+impl OvertureMapsConfig {
+    /// The default config, reading `OVERTURE_RELEASE` through `env` instead of the process
+    /// environment
+    pub fn default_with(env: impl Fn(&str) -> Option<String>) -> Self {
         Self {
             base_url: "s3://overturemaps-us-west-2/release".to_string(),
-            release_version: "2025-05-21.0".to_string(),
+            release_version: default_release_version_with(env),
         }
     }
+
+    /// HTTPS S3 listing of the release "directories" under `base_url`, if it is an S3 URL
+    pub fn release_listing_url(&self) -> Option<String> {
+        let path = self.base_url.strip_prefix("s3://")?;
+        let (bucket, prefix) = path.split_once('/').unwrap_or((path, ""));
+        let prefix = prefix.trim_end_matches('/');
+        Some(format!(
+            "https://{}.s3.amazonaws.com/?list-type=2&delimiter=%2F&prefix={}%2F",
+            bucket, prefix
+        ))
+    }
+}
+
+/// Extract release tags from an S3 `ListObjectsV2` response listing common prefixes
+pub fn parse_release_listing(xml: &str) -> Vec<String> {
+    let mut releases: Vec<String> = xml
+        .split("<Prefix>")
+        .skip(1)
+        .filter_map(|part| part.split_once("</Prefix>").map(|(prefix, _)| prefix))
+        .filter(|prefix| prefix.ends_with('/'))
+        .filter_map(|prefix| prefix.trim_end_matches('/').rsplit('/').next())
+        .filter(|tag| tag.chars().next().is_some_and(|c| c.is_ascii_digit()))
+        .map(str::to_string)
+        .collect();
+    releases.sort_by(|a, b| b.cmp(a));
+    releases.dedup();
+    releases
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct BoundingBox {
     pub xmin: f64,
     pub xmax: f64,
@@ -34,21 +121,148 @@ impl BoundingBox {
         }
     }
 
+    /// Box enclosing a circle of `radius_km` around a center point, with the longitude extent
+    /// corrected for the center latitude
+    pub fn from_center(lat: f64, lon: f64, radius_km: f64) -> Result<Self> {
+        if !(-90.0..=90.0).contains(&lat) || !(-180.0..=180.0).contains(&lon) {
+            bail!(
+                "Center ({}, {}) is not a valid lat,lon coordinate",
+                lat,
+                lon
+            );
+        }
+        if !radius_km.is_finite() || radius_km <= 0.0 {
+            bail!(
+                "Radius must be a positive number of kilometers, got {}",
+                radius_km
+            );
+        }
+        Ok(Self::new(lon, lat, lon, lat).expanded_by_km(radius_km))
+    }
+
+    /// Load a bounding box saved as JSON `{"xmin": .., "ymin": .., "xmax": .., "ymax": ..}`
+    pub fn from_json_file(path: &str) -> Result<Self> {
+        let s = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read bounding box file '{}'", path))?;
+        serde_json::from_str(&s)
+            .with_context(|| format!("Bounding box at '{}' is not valid JSON", path))
+    }
+
+    /// Save the bounding box as JSON so it can be reused with `--bbox-file`
+    pub fn save_json_file(&self, path: &str) -> Result<()> {
+        let text = serde_json::to_string_pretty(self)?;
+        fs::write(path, text)?;
+        Ok(())
+    }
+
+    /// Grow the box by `km` kilometers on every side. The longitude delta is corrected for
+    /// the latitude edge farthest from the equator, so the buffer is at least `km` everywhere.
+    /// Longitudes pushed past ±180° wrap around, turning the result into a box crossing the
+    /// antimeridian; a box grown to span every longitude becomes -180° to 180°.
+    pub fn expanded_by_km(&self, km: f64) -> Self {
+        let dlat = km / KM_PER_DEGREE;
+        let max_abs_lat = self.ymin.abs().max(self.ymax.abs()).min(89.0);
+        let dlon = km / (KM_PER_DEGREE * max_abs_lat.to_radians().cos());
+        let width = if self.crosses_antimeridian() {
+            self.xmax + 360.0 - self.xmin
+        } else {
+            self.xmax - self.xmin
+        };
+        let (xmin, xmax) = if width + 2.0 * dlon >= 360.0 {
+            (-180.0, 180.0)
+        } else {
+            (
+                wrap_longitude(self.xmin - dlon),
+                wrap_longitude(self.xmax + dlon),
+            )
+        };
+        Self {
+            xmin,
+            xmax,
+            ymin: (self.ymin - dlat).max(-90.0),
+            ymax: (self.ymax + dlat).min(90.0),
+        }
+    }
+
+    /// Whether the box wraps across the antimeridian, i.e. `xmin` lies east of `xmax`
+    pub fn crosses_antimeridian(&self) -> bool {
+        self.xmin > self.xmax
+    }
+
+    /// WKT for the box; boxes crossing the antimeridian are split at ±180° into a
+    /// MULTIPOLYGON, since a single ring from `xmin` to `xmax` would self-intersect
     pub fn to_wkt_polygon(&self) -> String {
+        if self.crosses_antimeridian() {
+            format!(
+                "MULTIPOLYGON((({})), (({})))",
+                wkt_ring(self.xmin, self.ymin, 180.0, self.ymax),
+                wkt_ring(-180.0, self.ymin, self.xmax, self.ymax)
+            )
+        } else {
+            format!(
+                "POLYGON(({}))",
+                wkt_ring(self.xmin, self.ymin, self.xmax, self.ymax)
+            )
+        }
+    }
+
+    /// SQL predicate on Overture's `bbox` column for features fully inside this box
+    fn contains_predicate(&self) -> String {
+        let x_predicate = if self.crosses_antimeridian() {
+            format!("(bbox.xmin >= {} OR bbox.xmax <= {})", self.xmin, self.xmax)
+        } else {
+            format!("bbox.xmin >= {} AND bbox.xmax <= {}", self.xmin, self.xmax)
+        };
         format!(
-            "POLYGON(({} {}, {} {}, {} {}, {} {}, {} {}))",
-            self.xmin,
-            self.ymin,
-            self.xmax,
-            self.ymin,
-            self.xmax,
-            self.ymax,
-            self.xmin,
-            self.ymax,
-            self.xmin,
-            self.ymin
+            "{} AND bbox.ymin >= {} AND bbox.ymax <= {}",
+            x_predicate, self.ymin, self.ymax
         )
     }
+
+    /// SQL predicate on Overture's `bbox` column for features intersecting this box
+    fn intersects_predicate(&self) -> String {
+        let x_predicate = if self.crosses_antimeridian() {
+            format!("(bbox.xmin <= {} OR bbox.xmax >= {})", self.xmax, self.xmin)
+        } else {
+            format!("bbox.xmin <= {} AND bbox.xmax >= {}", self.xmax, self.xmin)
+        };
+        format!(
+            "{} AND bbox.ymin <= {} AND bbox.ymax >= {}",
+            x_predicate, self.ymax, self.ymin
+        )
+    }
+}
+
+/// `lon` moved into [-180, 180] by a whole turn when it lies past the antimeridian
+fn wrap_longitude(lon: f64) -> f64 {
+    if lon > 180.0 {
+        lon - 360.0
+    } else if lon < -180.0 {
+        lon + 360.0
+    } else {
+        lon
+    }
+}
+
+/// Closed, counter-clockwise ring of coordinates for an axis-aligned rectangle
+fn wkt_ring(xmin: f64, ymin: f64, xmax: f64, ymax: f64) -> String {
+    format!(
+        "{} {}, {} {}, {} {}, {} {}, {} {}",
+        xmin, ymin, xmax, ymin, xmax, ymax, xmin, ymax, xmin, ymin
+    )
+}
+
+/// Whether `path` is on the local file system rather than a URL DuckDB fetches (s3://, https://)
+fn is_local_path(path: &str) -> bool {
+    !path.contains("://")
+}
+
+/// Row count stored in the footer of a local parquet file, without reading any row group
+pub fn parquet_metadata_row_count(path: &Path) -> Result<i64> {
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let reader = SerializedFileReader::new(file)
+        .with_context(|| format!("Failed to read the parquet footer of {}", path.display()))?;
+    Ok(reader.metadata().file_metadata().num_rows())
 }
 
 pub struct OvertureDuckDB {
@@ -56,39 +270,105 @@ pub struct OvertureDuckDB {
 }
 
 impl OvertureDuckDB {
-    pub fn new() -> Result<Self> {
-        let conn = Connection::open_in_memory().context("Failed to create DuckDB connection")?;
+    pub fn new() -> DownloadResult<Self> {
+        let conn = Connection::open_in_memory()
+            .map_err(|e| DownloadError::from_duckdb("Failed to create DuckDB connection", e))?;
 
         info!("Installing and loading DuckDB extensions");
 
-        conn.execute("INSTALL spatial", [])
-            .context("Failed to install spatial extension")?;
-        conn.execute("LOAD spatial", [])
-            .context("Failed to load spatial extension")?;
-        conn.execute("INSTALL httpfs", [])
-            .context("Failed to install httpfs extension")?;
-        conn.execute("LOAD httpfs", [])
-            .context("Failed to load httpfs extension")?;
+        for (statement, failure) in [
+            ("INSTALL spatial", "Failed to install spatial extension"),
+            ("LOAD spatial", "Failed to load spatial extension"),
+            ("INSTALL httpfs", "Failed to install httpfs extension"),
+            ("LOAD httpfs", "Failed to load httpfs extension"),
+        ] {
+            conn.execute(statement, [])
+                .map_err(|e| DownloadError::ExtensionLoad(format!("{}: {}", failure, e)))?;
+        }
 
         Ok(Self { conn })
     }
 
-    pub fn execute_query(&self, query: &str) -> Result<()> {
-        debug!("Executing DuckDB query:\n{}", query);
-        self.conn
-            .execute(query, [])
-            .with_context(|| format!("Failed to execute query: {}", query))?;
+    /// Run a statement, e.g. a download `COPY`. Ctrl-C cancels it with `DownloadError::Interrupted`.
+    pub fn execute_query(&self, query: &str) -> DownloadResult<()> {
+        if is_interrupted() {
+            return Err(Interrupted.into());
+        }
+        log_sql("DuckDB", query);
+        let _cancel = CancelOnInterrupt::new(&self.conn);
+        self.conn.execute(query, []).map_err(|e| {
+            if is_interrupted() {
+                return Interrupted.into();
+            }
+            DownloadError::from_duckdb(
+                &format!("Failed to execute query: {}", sql_for_log(query)),
+                e,
+            )
+        })?;
         Ok(())
     }
 
-    pub fn count_parquet_rows(&self, path: &str) -> Result<i64> {
+    pub fn read_text(&self, url: &str) -> DownloadResult<String> {
+        let query = "SELECT content FROM read_text(?)";
+        log_sql_with_params("DuckDB", query, &[&url]);
         let mut stmt = self
             .conn
-            .prepare("SELECT COUNT(1) FROM read_parquet(?)")
-            .context("Failed to prepare count query")?;
+            .prepare(query)
+            .map_err(|e| DownloadError::from_duckdb("Failed to prepare read_text query", e))?;
+        let content: String = stmt
+            .query_row(params![url], |row| row.get(0))
+            .map_err(|e| {
+                DownloadError::from_duckdb(&format!("Failed to read {}", param_for_log(url)), e)
+            })?;
+        Ok(content)
+    }
+
+    /// Rows in the parquet file(s) at `path`. A single local file is counted from its footer
+    /// metadata; globs, remote URLs and unreadable footers fall back to a DuckDB count.
+    pub fn count_parquet_rows(&self, path: &str) -> DownloadResult<i64> {
+        if is_local_path(path) && Path::new(path).is_file() {
+            match parquet_metadata_row_count(Path::new(path)) {
+                Ok(count) => return Ok(count),
+                Err(e) => warn!(
+                    "Could not read the row count of {} from its metadata, scanning instead: {:#}",
+                    param_for_log(path),
+                    e
+                ),
+            }
+        }
+        self.scan_parquet_rows(path)
+    }
+
+    /// Run a query returning a single count, e.g. one built by [`OvertureMapsQuery`]
+    pub fn query_count(&self, query: &str) -> DownloadResult<i64> {
+        log_sql("DuckDB", query);
+        let count: i64 = self
+            .conn
+            .query_row(query, [], |row| row.get(0))
+            .map_err(|e| {
+                DownloadError::from_duckdb(
+                    &format!("Failed to execute query: {}", sql_for_log(query)),
+                    e,
+                )
+            })?;
+        Ok(count)
+    }
+
+    fn scan_parquet_rows(&self, path: &str) -> DownloadResult<i64> {
+        let query = "SELECT COUNT(1) FROM read_parquet(?)";
+        log_sql_with_params("DuckDB", query, &[&path]);
+        let mut stmt = self
+            .conn
+            .prepare(query)
+            .map_err(|e| DownloadError::from_duckdb("Failed to prepare count query", e))?;
         let count: i64 = stmt
             .query_row(params![path], |row| row.get(0))
-            .with_context(|| format!("Failed to count rows in {}", path))?;
+            .map_err(|e| {
+                DownloadError::from_duckdb(
+                    &format!("Failed to count rows in {}", param_for_log(path)),
+                    e,
+                )
+            })?;
         Ok(count)
     }
 }
@@ -102,6 +382,22 @@ impl OvertureMapsQuery {
         Self { config }
     }
 
+    /// Number of transportation features `transportation_query` would download for `bbox`
+    pub fn transportation_count_query(&self, bbox: &BoundingBox) -> String {
+        format!(
+            r#"
+            SELECT COUNT(1)
+            FROM read_parquet('{base_url}/{version}/theme=transportation/type=*/*',
+                            filename=true, hive_partitioning=1)
+            WHERE
+                {bbox_predicate}
+            "#,
+            base_url = self.config.base_url,
+            version = self.config.release_version,
+            bbox_predicate = bbox.contains_predicate(),
+        )
+    }
+
     pub fn transportation_query(&self, bbox: &BoundingBox, output_path: &str) -> String {
         format!(
             r#"
@@ -110,18 +406,12 @@ impl OvertureMapsQuery {
                 FROM read_parquet('{base_url}/{version}/theme=transportation/type=*/*', 
                                 filename=true, hive_partitioning=1)
                 WHERE
-                    bbox.xmin >= {xmin}
-                    AND bbox.xmax <= {xmax}
-                    AND bbox.ymin >= {ymin}
-                    AND bbox.ymax <= {ymax}
+                    {bbox_predicate}
             ) TO '{output_path}' (FORMAT PARQUET);
             "#,
             base_url = self.config.base_url,
             version = self.config.release_version,
-            xmin = bbox.xmin,
-            xmax = bbox.xmax,
-            ymin = bbox.ymin,
-            ymax = bbox.ymax,
+            bbox_predicate = bbox.contains_predicate(),
             output_path = output_path
         )
     }
@@ -135,19 +425,13 @@ impl OvertureMapsQuery {
                 FROM read_parquet('{base_url}/{version}/theme=divisions/type=division_area/*', 
                                 filename=true, hive_partitioning=1)
                 WHERE
-                    bbox.xmin <= {xmax}
-                    AND bbox.xmax >= {xmin}
-                    AND bbox.ymin <= {ymax}
-                    AND bbox.ymax >= {ymin}
+                    {bbox_predicate}
                     AND ST_Intersects(geometry, ST_GeomFromText('{bbox_wkt}'))
             ) TO '{output_path}' (FORMAT PARQUET);
             "#,
             base_url = self.config.base_url,
             version = self.config.release_version,
-            xmax = bbox.xmax,
-            xmin = bbox.xmin,
-            ymax = bbox.ymax,
-            ymin = bbox.ymin,
+            bbox_predicate = bbox.intersects_predicate(),
             bbox_wkt = bbox_wkt,
             output_path = output_path
         )
@@ -174,7 +458,96 @@ impl OvertureMapsQuery {
     }
 }
 
+/// List the releases available in the Overture bucket, falling back to [`KNOWN_RELEASES`]
+/// when the bucket cannot be listed (no network, listing not permitted, ...)
+pub fn list_overture_releases() -> Vec<String> {
+    let config = OvertureMapsConfig::default();
+    let listed = config
+        .release_listing_url()
+        .context("Release base URL is not an S3 bucket")
+        .and_then(|url| {
+            info!("Listing Overture releases from {}", url);
+            Ok(OvertureDuckDB::new()?.read_text(&url)?)
+        })
+        .map(|xml| parse_release_listing(&xml));
+
+    match listed {
+        Ok(releases) if !releases.is_empty() => releases,
+        Ok(_) => {
+            warn!("Release listing was empty, using the bundled list of known releases");
+            KNOWN_RELEASES.iter().map(|r| r.to_string()).collect()
+        }
+        Err(e) => {
+            warn!(
+                "Could not list releases ({:#}), using the bundled list of known releases",
+                e
+            );
+            KNOWN_RELEASES.iter().map(|r| r.to_string()).collect()
+        }
+    }
+}
 
+/// After a step of a download, stop and delete the files written so far if Ctrl-C was pressed,
+/// which also cancels the step's query, see `OvertureDuckDB::execute_query`
+fn stop_if_interrupted(partial_outputs: &[&str]) -> DownloadResult<()> {
+    if !is_interrupted() {
+        return Ok(());
+    }
+    for path in partial_outputs {
+        if fs::metadata(path).is_ok() {
+            info!("Removing partial download {}", path);
+            if let Err(err) = fs::remove_file(path) {
+                warn!("Failed to remove {}: {}", path, err);
+            }
+        }
+    }
+    Err(Interrupted.into())
+}
+
+/// Fail before downloading anything when `bbox` holds more than `max_features` transportation
+/// features, so a mistyped bounding box does not start a huge download. `None` disables the cap.
+fn check_max_features(
+    db: &OvertureDuckDB,
+    query_builder: &OvertureMapsQuery,
+    bbox: &BoundingBox,
+    max_features: Option<u64>,
+) -> DownloadResult<()> {
+    let Some(max_features) = max_features else {
+        return Ok(());
+    };
+    info!("Counting transportation features in the bounding box...");
+    let count = db.query_count(&query_builder.transportation_count_query(bbox))?;
+    if count as u64 > max_features {
+        return Err(DownloadError::TooManyFeatures {
+            count,
+            max_features,
+        });
+    }
+    info!("{} transportation features to download", count);
+    Ok(())
+}
+
+fn download_transportation(
+    db: &OvertureDuckDB,
+    config: OvertureMapsConfig,
+    bbox: &BoundingBox,
+    output_path: &str,
+    max_features: Option<u64>,
+) -> DownloadResult<i64> {
+    let query_builder = OvertureMapsQuery::new(config);
+    check_max_features(db, &query_builder, bbox, max_features)?;
+
+    info!("Downloading transportation data...");
+    let query = query_builder.transportation_query(bbox, output_path);
+    let downloaded = db.execute_query(&query);
+    stop_if_interrupted(&[output_path])?;
+    downloaded?;
+
+    db.count_parquet_rows(output_path)
+}
+
+/// Download the transportation features inside the box to `output_path`, refusing to
+/// when there are more than `max_features` of them
 pub fn download_overture_data(
     release_version: &str,
     xmin: f64,
@@ -182,7 +555,8 @@ pub fn download_overture_data(
     ymin: f64,
     ymax: f64,
     output_path: &str,
-) -> Result<()> {
+    max_features: Option<u64>,
+) -> DownloadResult<()> {
     let config = OvertureMapsConfig {
         release_version: release_version.to_string(),
         ..Default::default()
@@ -190,13 +564,7 @@ pub fn download_overture_data(
 
     let bbox = BoundingBox::new(xmin, ymin, xmax, ymax);
     let db = OvertureDuckDB::new()?;
-    let query_builder = OvertureMapsQuery::new(config);
-
-    info!("Downloading transportation data...");
-    let query = query_builder.transportation_query(&bbox, output_path);
-    db.execute_query(&query)?;
-
-    let count = db.count_parquet_rows(output_path)?;
+    let count = download_transportation(&db, config, &bbox, output_path, max_features)?;
     info!(
         "Download complete! Found {} transportation features in the specified area.",
         count
@@ -213,7 +581,7 @@ pub fn download_overture_admins(
     ymax: f64,
     division_output_path: &str,
     area_output_path: &str,
-) -> Result<()> {
+) -> DownloadResult<()> {
     let config = OvertureMapsConfig {
         release_version: release_version.to_string(),
         ..Default::default()
@@ -226,7 +594,9 @@ pub fn download_overture_admins(
     // Download division areas first
     info!("Downloading division areas...");
     let areas_query = query_builder.division_areas_query(&bbox, area_output_path);
-    db.execute_query(&areas_query)?;
+    let downloaded = db.execute_query(&areas_query);
+    stop_if_interrupted(&[area_output_path])?;
+    downloaded?;
 
     let area_count = db.count_parquet_rows(area_output_path)?;
     info!("Found {} division area features", area_count);
@@ -234,7 +604,9 @@ pub fn download_overture_admins(
     // Download corresponding divisions
     info!("Downloading division metadata for matching areas...");
     let divisions_query = query_builder.divisions_query(area_output_path, division_output_path);
-    db.execute_query(&divisions_query)?;
+    let downloaded = db.execute_query(&divisions_query);
+    stop_if_interrupted(&[area_output_path, division_output_path])?;
+    downloaded?;
 
     let division_count = db.count_parquet_rows(division_output_path)?;
     info!("Found {} division features", division_count);
@@ -244,8 +616,85 @@ pub fn download_overture_admins(
 
 #[cfg(test)]
 mod tests {
+    use tempfile::NamedTempFile;
+
     use super::*;
 
+    #[test]
+    fn test_metadata_row_count_matches_duckdb() {
+        let path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../tests/data/wa-divisions.parquet"
+        );
+        let db = OvertureDuckDB {
+            conn: Connection::open_in_memory().unwrap(),
+        };
+
+        let metadata_count = parquet_metadata_row_count(Path::new(path)).unwrap();
+        assert!(metadata_count > 0);
+        assert_eq!(metadata_count, db.scan_parquet_rows(path).unwrap());
+        assert_eq!(db.count_parquet_rows(path).unwrap(), metadata_count);
+
+        assert!(is_local_path(path));
+        assert!(!is_local_path("s3://overturemaps-us-west-2/release"));
+    }
+
+    /// Release `test-release` under `base_url` with one segment file of `rows` features near Seattle
+    fn write_local_release(db: &OvertureDuckDB, base_url: &Path, rows: usize) {
+        let dir = base_url.join("test-release/theme=transportation/type=segment");
+        fs::create_dir_all(&dir).unwrap();
+        db.execute_query(&format!(
+            "COPY (SELECT 'segment-' || range AS id, \
+             {{'xmin': -122.34, 'xmax': -122.33, 'ymin': 47.61, 'ymax': 47.62}} AS bbox \
+             FROM range({})) TO '{}' (FORMAT PARQUET)",
+            rows,
+            dir.join("part-0.parquet").display()
+        ))
+        .unwrap();
+    }
+
+    #[test]
+    fn test_max_features_cap() {
+        let base_url = tempfile::tempdir().unwrap();
+        let db = OvertureDuckDB {
+            conn: Connection::open_in_memory().unwrap(),
+        };
+        write_local_release(&db, base_url.path(), 5);
+        let config = || OvertureMapsConfig {
+            base_url: base_url.path().display().to_string(),
+            release_version: "test-release".to_string(),
+        };
+        let bbox = BoundingBox::new(-122.4, 47.6, -122.3, 47.7);
+        let output = base_url.path().join("download.parquet");
+        let output_path = output.to_str().unwrap();
+
+        let err = download_transportation(&db, config(), &bbox, output_path, Some(4)).unwrap_err();
+        assert!(matches!(
+            err,
+            DownloadError::TooManyFeatures {
+                count: 5,
+                max_features: 4
+            }
+        ));
+        let message = err.to_string();
+        assert!(
+            message.contains("holds 5 transportation features"),
+            "{}",
+            message
+        );
+        assert!(message.contains("--max-features 4"), "{}", message);
+        assert!(!output.exists());
+
+        assert_eq!(
+            download_transportation(&db, config(), &bbox, output_path, Some(5)).unwrap(),
+            5
+        );
+        assert_eq!(
+            download_transportation(&db, config(), &bbox, output_path, None).unwrap(),
+            5
+        );
+    }
+
     #[test]
     fn test_bounding_box_wkt() {
         let bbox = BoundingBox::new(-122.4, 47.6, -122.3, 47.7);
@@ -255,10 +704,199 @@ mod tests {
         assert!(wkt.contains("47.6"));
     }
 
+    #[test]
+    fn test_bounding_box_wkt_antimeridian() {
+        let bbox = BoundingBox::new(170.0, -10.0, -170.0, 10.0);
+        assert!(bbox.crosses_antimeridian());
+        assert_eq!(
+            bbox.to_wkt_polygon(),
+            "MULTIPOLYGON(((170 -10, 180 -10, 180 10, 170 10, 170 -10)), \
+             ((-180 -10, -170 -10, -170 10, -180 10, -180 -10)))"
+        );
+
+        let bbox = BoundingBox::new(-122.4, 47.6, -122.3, 47.7);
+        assert!(!bbox.crosses_antimeridian());
+        assert!(bbox.to_wkt_polygon().starts_with("POLYGON(("));
+    }
+
+    #[test]
+    fn test_division_areas_query_antimeridian_prefilter() {
+        let query_builder = OvertureMapsQuery::new(OvertureMapsConfig::default());
+
+        let bbox = BoundingBox::new(170.0, -10.0, -170.0, 10.0);
+        let query = query_builder.division_areas_query(&bbox, "out.parquet");
+        assert!(query.contains("(bbox.xmin <= -170 OR bbox.xmax >= 170)"));
+        assert!(query.contains("MULTIPOLYGON"));
+
+        let bbox = BoundingBox::new(-122.4, 47.6, -122.3, 47.7);
+        let query = query_builder.division_areas_query(&bbox, "out.parquet");
+        assert!(query.contains("bbox.xmin <= -122.3 AND bbox.xmax >= -122.4"));
+    }
+
+    #[test]
+    fn test_transportation_query_antimeridian_prefilter() {
+        let query_builder = OvertureMapsQuery::new(OvertureMapsConfig::default());
+        let bbox = BoundingBox::new(170.0, -10.0, -170.0, 10.0);
+        let query = query_builder.transportation_query(&bbox, "out.parquet");
+        assert!(query.contains("(bbox.xmin >= 170 OR bbox.xmax <= -170)"));
+        assert!(query.contains("bbox.ymin >= -10"));
+    }
+
+    #[test]
+    fn test_bounding_box_expanded_by_km() {
+        let bbox = BoundingBox::new(10.0, 59.0, 11.0, 60.0);
+        let expanded = bbox.expanded_by_km(1.0);
+
+        let dlat = 1.0 / 111.32;
+        // At 60° a degree of longitude is half as long as at the equator
+        let dlon = 2.0 / 111.32;
+        assert!((bbox.ymin - expanded.ymin - dlat).abs() < 1e-9);
+        assert!((expanded.ymax - bbox.ymax - dlat).abs() < 1e-9);
+        assert!((bbox.xmin - expanded.xmin - dlon).abs() < 1e-9);
+        assert!((expanded.xmax - bbox.xmax - dlon).abs() < 1e-9);
+
+        assert_eq!(bbox.expanded_by_km(0.0), bbox);
+    }
+
+    #[test]
+    fn test_bounding_box_from_center() {
+        let bbox = BoundingBox::from_center(47.6062, -122.3321, 10.0).unwrap();
+        assert!((bbox.xmin - -122.465337).abs() < 1e-5);
+        assert!((bbox.xmax - -122.198863).abs() < 1e-5);
+        assert!((bbox.ymin - 47.516369).abs() < 1e-5);
+        assert!((bbox.ymax - 47.696031).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_bounding_box_from_center_invalid() {
+        assert!(BoundingBox::from_center(95.0, 0.0, 10.0).is_err());
+        assert!(BoundingBox::from_center(0.0, 200.0, 10.0).is_err());
+        assert!(BoundingBox::from_center(47.6, -122.3, 0.0).is_err());
+        assert!(BoundingBox::from_center(47.6, -122.3, -1.0).is_err());
+    }
+
+    #[test]
+    fn test_bounding_box_expanded_by_km_clamps() {
+        let bbox = BoundingBox::new(-180.0, 89.99, 180.0, 90.0);
+        let expanded = bbox.expanded_by_km(5.0);
+        assert_eq!(expanded.xmin, -180.0);
+        assert_eq!(expanded.xmax, 180.0);
+        assert_eq!(expanded.ymax, 90.0);
+    }
+
+    #[test]
+    fn test_bounding_box_expanded_by_km_wraps_antimeridian() {
+        // Fiji straddles the antimeridian; a buffer east of 179.9° continues at -180°
+        let bbox = BoundingBox::from_center(-17.8, 179.9, 20.0).unwrap();
+        assert!(bbox.crosses_antimeridian(), "{:?}", bbox);
+        assert!(bbox.xmin < 179.9 && bbox.xmin > 179.5);
+        assert!(bbox.xmax > -180.0 && bbox.xmax < -179.8);
+
+        // Growing an already crossing box keeps it crossing
+        let crossing = BoundingBox::new(179.0, -20.0, -179.0, -15.0).expanded_by_km(10.0);
+        assert!(crossing.xmin < 179.0 && crossing.xmax > -179.0);
+        assert!(crossing.crosses_antimeridian());
+        assert!(crossing.to_wkt_polygon().starts_with("MULTIPOLYGON"));
+    }
+
+    #[test]
+    fn test_bounding_box_file_roundtrip_into_query() {
+        let file = NamedTempFile::new().unwrap();
+        let path = file.path().to_str().unwrap();
+
+        let bbox = BoundingBox::new(-122.4, 47.6, -122.3, 47.7);
+        bbox.save_json_file(path).unwrap();
+        let loaded = BoundingBox::from_json_file(path).unwrap();
+        assert_eq!(loaded, bbox);
+
+        let query = OvertureMapsQuery::new(OvertureMapsConfig::default())
+            .transportation_query(&loaded, "out.parquet");
+        assert!(query.contains("bbox.xmin >= -122.4"));
+        assert!(query.contains("bbox.xmax <= -122.3"));
+        assert!(query.contains("bbox.ymin >= 47.6"));
+        assert!(query.contains("bbox.ymax <= 47.7"));
+    }
+
+    #[test]
+    fn test_bounding_box_file_missing_field() {
+        let file = NamedTempFile::new().unwrap();
+        fs::write(file.path(), r#"{"xmin": 1.0, "ymin": 2.0, "xmax": 3.0}"#).unwrap();
+        let err = BoundingBox::from_json_file(file.path().to_str().unwrap()).unwrap_err();
+        assert!(format!("{:?}", err).contains("is not valid JSON"));
+    }
+
+    #[test]
+    fn test_release_listing_url() {
+        let config = OvertureMapsConfig::default();
+        assert_eq!(
+            config.release_listing_url().as_deref(),
+            Some(
+                "https://overturemaps-us-west-2.s3.amazonaws.com/?list-type=2&delimiter=%2F&prefix=release%2F"
+            )
+        );
+
+        let config = OvertureMapsConfig {
+            base_url: "/local/release".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(config.release_listing_url(), None);
+    }
+
+    #[test]
+    fn test_parse_release_listing() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<ListBucketResult xmlns="http://s3.amazonaws.com/doc/2006-03-01/">
+  <Name>overturemaps-us-west-2</Name>
+  <Prefix>release/</Prefix>
+  <KeyCount>3</KeyCount>
+  <Delimiter>/</Delimiter>
+  <CommonPrefixes><Prefix>release/2025-04-23.0/</Prefix></CommonPrefixes>
+  <CommonPrefixes><Prefix>release/2025-05-21.0/</Prefix></CommonPrefixes>
+  <CommonPrefixes><Prefix>release/2025-03-19.1/</Prefix></CommonPrefixes>
+</ListBucketResult>"#;
+        assert_eq!(
+            parse_release_listing(xml),
+            vec!["2025-05-21.0", "2025-04-23.0", "2025-03-19.1"]
+        );
+        assert!(parse_release_listing("<Error>AccessDenied</Error>").is_empty());
+    }
+
+    #[test]
+    fn test_known_releases_include_default() {
+        assert!(KNOWN_RELEASES.contains(&DEFAULT_RELEASE_VERSION));
+    }
+
     #[test]
     fn test_overture_config_default() {
         let config = OvertureMapsConfig::default();
         assert!(config.base_url.contains("overturemaps"));
         assert!(!config.release_version.is_empty());
     }
+
+    #[test]
+    fn test_release_env_var_overrides_default() {
+        assert_eq!(release_version_from(None), DEFAULT_RELEASE_VERSION);
+        assert_eq!(
+            release_version_from(Some("2025-04-23.0".to_string())),
+            "2025-04-23.0"
+        );
+        assert_eq!(
+            release_version_from(Some(" 2025-04-23.0\n".to_string())),
+            "2025-04-23.0"
+        );
+        assert_eq!(
+            release_version_from(Some(" ".to_string())),
+            DEFAULT_RELEASE_VERSION
+        );
+
+        let env = |name: &str| (name == RELEASE_ENV_VAR).then(|| "2025-04-23.0".to_string());
+        assert_eq!(
+            OvertureMapsConfig::default_with(env).release_version,
+            "2025-04-23.0"
+        );
+        assert_eq!(
+            OvertureMapsConfig::default_with(|_| None).release_version,
+            DEFAULT_RELEASE_VERSION
+        );
+    }
 }
@@ -0,0 +1,197 @@
+//! Errors of the download functions, classified from DuckDB messages
+
+use std::io;
+
+use crate::utils::interrupt::Interrupted;
+
+pub type DownloadResult<T> = Result<T, DownloadError>;
+
+/// Why a download failed. DuckDB reports every failure as a message, so the variant is
+/// picked from the message text, see [`DownloadError::from_duckdb_message`].
+#[derive(Debug, thiserror::Error)]
+pub enum DownloadError {
+    /// The spatial or httpfs extension could not be installed or loaded
+    #[error("DuckDB extension error: {0}")]
+    ExtensionLoad(String),
+
+    /// The bucket could not be reached or rejected the request, e.g. missing credentials
+    #[error("network error: {0}")]
+    Network(String),
+
+    /// No files exist for the release under the base URL
+    #[error("release not found: {0}")]
+    NoSuchRelease(String),
+
+    /// A query that should return a row returned none
+    #[error("query returned no rows: {0}")]
+    EmptyResult(String),
+
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+
+    #[error(
+        "the bounding box holds {count} transportation features, more than --max-features \
+         {max_features}; narrow the area or raise the cap"
+    )]
+    TooManyFeatures { count: i64, max_features: u64 },
+
+    /// Any other failed query
+    #[error("query failed: {0}")]
+    Query(String),
+
+    #[error("download stopped")]
+    Interrupted(#[from] Interrupted),
+}
+
+/// Message fragments of DuckDB and its httpfs extension, matched lowercase
+const EXTENSION_PATTERNS: &[&str] = &["extension"];
+const NO_SUCH_RELEASE_PATTERNS: &[&str] = &[
+    "no files found that match the pattern",
+    "(http 404)",
+    "nosuchkey",
+];
+const NETWORK_PATTERNS: &[&str] = &[
+    "http",
+    "connection",
+    "could not resolve",
+    "timed out",
+    "timeout",
+    "credential",
+    "access denied",
+    "accessdenied",
+    "ssl",
+];
+const IO_PATTERNS: &[&str] = &[
+    "io error",
+    "cannot open file",
+    "permission denied",
+    "no space left",
+];
+
+impl DownloadError {
+    /// Classify a DuckDB error message
+    pub fn from_duckdb_message(message: &str) -> Self {
+        Self::classified(message, message.to_string())
+    }
+
+    /// Classify a DuckDB error, keeping `context` (which query or file failed) in the message
+    pub fn from_duckdb(context: &str, error: duckdb::Error) -> Self {
+        match error {
+            duckdb::Error::QueryReturnedNoRows => Self::EmptyResult(context.to_string()),
+            error => Self::classified(&error.to_string(), format!("{}: {}", context, error)),
+        }
+    }
+
+    /// Variant for the DuckDB `error` text, carrying `message`. The order matters: a failed
+    /// extension download is an extension error and a 404 a missing release, not network errors.
+    fn classified(error: &str, message: String) -> Self {
+        let error = error.to_lowercase();
+        let matches = |patterns: &[&str]| patterns.iter().any(|pattern| error.contains(pattern));
+        if matches(EXTENSION_PATTERNS) {
+            Self::ExtensionLoad(message)
+        } else if matches(NO_SUCH_RELEASE_PATTERNS) {
+            Self::NoSuchRelease(message)
+        } else if matches(NETWORK_PATTERNS) {
+            Self::Network(message)
+        } else if matches(IO_PATTERNS) {
+            Self::Io(io::Error::other(message))
+        } else {
+            Self::Query(message)
+        }
+    }
+
+    /// What the user can do about the error, shown by the CLI
+    pub fn hint(&self) -> Option<&'static str> {
+        match self {
+            Self::ExtensionLoad(_) => Some(
+                "DuckDB downloads its spatial and httpfs extensions on first use; check the network \
+                 connection or preinstall them",
+            ),
+            Self::Network(_) => {
+                Some("Check the network connection and, for a private bucket, the S3 credentials")
+            }
+            Self::NoSuchRelease(_) => {
+                Some("Run `omf-bifrost list-releases` to see the available releases")
+            }
+            Self::TooManyFeatures { .. } => {
+                Some("Pass a higher --max-features or leave it out to download anyway")
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_duckdb_messages() {
+        let cases = [
+            (
+                r#"IO Error: Failed to download extension "httpfs" at URL "http://extensions.duckdb.org/v1.3.0/linux_amd64/httpfs.duckdb_extension.gz""#,
+                "ExtensionLoad",
+            ),
+            (
+                r#"Catalog Error: Scalar Function with name "st_intersects" is not in the catalog, but it exists in the spatial extension."#,
+                "ExtensionLoad",
+            ),
+            (
+                r#"IO Error: No files found that match the pattern "s3://overturemaps-us-west-2/release/2099-01-01.0/theme=transportation/type=*/*""#,
+                "NoSuchRelease",
+            ),
+            (
+                "HTTP Error: HTTP GET error on 'https://example.com/release/x.parquet' (HTTP 404)",
+                "NoSuchRelease",
+            ),
+            (
+                "HTTP Error: HTTP GET error on 'https://overturemaps-us-west-2.s3.amazonaws.com/release' (HTTP 403)",
+                "Network",
+            ),
+            (
+                "IO Error: Could not establish connection error for HTTP HEAD to 'https://overturemaps-us-west-2.s3.amazonaws.com/'",
+                "Network",
+            ),
+            (
+                r#"IO Error: Cannot open file "/read-only/data.parquet": Permission denied"#,
+                "Io",
+            ),
+            ("Parser Error: syntax error at or near \"SELEC\"", "Query"),
+        ];
+        for (message, expected) in cases {
+            let variant = match DownloadError::from_duckdb_message(message) {
+                DownloadError::ExtensionLoad(_) => "ExtensionLoad",
+                DownloadError::Network(_) => "Network",
+                DownloadError::NoSuchRelease(_) => "NoSuchRelease",
+                DownloadError::EmptyResult(_) => "EmptyResult",
+                DownloadError::Io(_) => "Io",
+                DownloadError::Query(_) => "Query",
+                other => panic!("unexpected {:?}", other),
+            };
+            assert_eq!(variant, expected, "{}", message);
+        }
+    }
+
+    #[test]
+    fn test_context_is_not_classified() {
+        // The https URL in the failed query does not make this a network error
+        let context =
+            "Failed to execute query: SELECT * FROM read_parquet('https://example.com/*')";
+        let err = DownloadError::from_duckdb(
+            context,
+            duckdb::Error::InvalidParameterName("$bbox".to_string()),
+        );
+        assert!(matches!(err, DownloadError::Query(_)));
+        assert!(err.to_string().contains(context));
+        assert!(err.hint().is_none());
+
+        let err = DownloadError::from_duckdb(context, duckdb::Error::QueryReturnedNoRows);
+        assert!(matches!(err, DownloadError::EmptyResult(_)));
+        assert!(
+            DownloadError::NoSuchRelease(String::new())
+                .hint()
+                .unwrap()
+                .contains("list-releases")
+        );
+    }
+}
@@ -0,0 +1,148 @@
+// src/utils/interrupt.rs
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+
+use anyhow::Result;
+use duckdb::{Connection, InterruptHandle};
+
+/// Exit code of a process stopped by SIGINT
+pub const INTERRUPTED_EXIT_CODE: i32 = 130;
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// DuckDB connection whose running query the first Ctrl-C cancels, see `CancelOnInterrupt`
+static ACTIVE_QUERY: ActiveQuery = Mutex::new(None);
+
+/// Slot for the interrupt handle of the connection running a cancellable query
+pub type ActiveQuery = Mutex<Option<Arc<InterruptHandle>>>;
+
+/// Error returned by long running operations that stopped because of Ctrl-C
+#[derive(Debug, thiserror::Error)]
+#[error("interrupted by user")]
+pub struct Interrupted;
+
+/// Install a Ctrl-C handler that sets the interrupt flag and cancels the running DuckDB query.
+/// Ingestion loops stop at their next batch boundary and clean up; a second Ctrl-C exits
+/// immediately.
+pub fn install_interrupt_handler() -> Result<()> {
+    ctrlc::set_handler(|| {
+        if INTERRUPTED.swap(true, Ordering::SeqCst) {
+            std::process::exit(INTERRUPTED_EXIT_CODE);
+        }
+        eprintln!(
+            "Interrupt received, cancelling the running query or stopping after the current batch (press Ctrl-C again to abort)"
+        );
+        cancel_active_query();
+    })?;
+    Ok(())
+}
+
+/// Registers the DuckDB connection of a long running query, such as a download `COPY`, so that
+/// Ctrl-C cancels the query instead of waiting for it to finish. Unregisters when dropped.
+pub struct CancelOnInterrupt<'a> {
+    slot: &'a ActiveQuery,
+    handle: Arc<InterruptHandle>,
+}
+
+impl CancelOnInterrupt<'static> {
+    pub fn new(conn: &Connection) -> Self {
+        Self::register(&ACTIVE_QUERY, conn)
+    }
+}
+
+impl<'a> CancelOnInterrupt<'a> {
+    /// Register `conn` in `slot`. Tests use a slot of their own, as the process-wide one would
+    /// let them cancel the queries of every other test running alongside.
+    pub fn register(slot: &'a ActiveQuery, conn: &Connection) -> Self {
+        let handle = conn.interrupt_handle();
+        *slot.lock().unwrap_or_else(PoisonError::into_inner) = Some(handle.clone());
+        Self { slot, handle }
+    }
+}
+
+impl Drop for CancelOnInterrupt<'_> {
+    fn drop(&mut self) {
+        let mut active = self.slot.lock().unwrap_or_else(PoisonError::into_inner);
+        if active
+            .as_ref()
+            .is_some_and(|handle| Arc::ptr_eq(handle, &self.handle))
+        {
+            *active = None;
+        }
+    }
+}
+
+/// Cancel the query running on the connection registered by `CancelOnInterrupt`, if any
+fn cancel_active_query() {
+    cancel_query(&ACTIVE_QUERY);
+}
+
+/// Cancel the query running on the connection registered in `slot`, if any. The query fails
+/// with a DuckDB error.
+pub fn cancel_query(slot: &ActiveQuery) {
+    if let Some(handle) = slot.lock().unwrap_or_else(PoisonError::into_inner).as_ref() {
+        handle.interrupt();
+    }
+}
+
+pub fn is_interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+/// `Err(Interrupted)` once Ctrl-C was pressed
+pub fn check_interrupted() -> Result<()> {
+    check_interrupt_flag(&INTERRUPTED)
+}
+
+/// `Err(Interrupted)` once `flag` is set. Tests stop their loops with a flag of their own,
+/// as setting the process-wide Ctrl-C flag would stop every other test running alongside.
+pub fn check_interrupt_flag(flag: &AtomicBool) -> Result<()> {
+    if flag.load(Ordering::SeqCst) {
+        return Err(Interrupted.into());
+    }
+    Ok(())
+}
+
+/// Whether an error chain stems from an interrupt
+pub fn is_interrupted_error(err: &anyhow::Error) -> bool {
+    err.chain().any(|cause| cause.is::<Interrupted>())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn test_cancel_query_stops_the_registered_query() {
+        let slot = ActiveQuery::new(None);
+        let conn = Connection::open_in_memory().unwrap();
+        let finished = AtomicBool::new(false);
+
+        let result = thread::scope(|scope| {
+            let _cancel = CancelOnInterrupt::register(&slot, &conn);
+            // Cancelling before the query started has no effect, so keep cancelling until it fails
+            scope.spawn(|| {
+                while !finished.load(Ordering::SeqCst) {
+                    cancel_query(&slot);
+                    thread::sleep(Duration::from_millis(20));
+                }
+            });
+            let result = conn.query_row(
+                "SELECT sum(range % 7) FROM range(1000000000000)",
+                [],
+                |row| row.get::<_, i128>(0),
+            );
+            finished.store(true, Ordering::SeqCst);
+            result
+        });
+
+        assert!(result.is_err(), "query should have been cancelled");
+        assert!(
+            slot.lock().unwrap().is_none(),
+            "connection should be unregistered"
+        );
+    }
+}
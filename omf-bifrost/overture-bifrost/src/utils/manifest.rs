@@ -0,0 +1,127 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use log::info;
+use serde::{Deserialize, Serialize};
+
+use overture_valhalla_writer::valhalla_sys::{OsmWay, OsmWayNode};
+
+use crate::utils::download::BoundingBox;
+
+/// Name of the manifest `convert` writes next to the binary files
+pub const CONVERSION_MANIFEST_FILE: &str = "conversion_manifest.json";
+
+/// Contents of `conversion_manifest.json`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConversionManifest {
+    /// Version of overture-bifrost that wrote the output
+    pub version: String,
+    pub ways: usize,
+    pub way_nodes: usize,
+    /// Extent of all exported way nodes, `null` when no way was written
+    pub bbox: Option<BoundingBox>,
+}
+
+/// Smallest box holding all `way_nodes`, `None` when there are none
+pub fn way_nodes_bbox(way_nodes: &[OsmWayNode]) -> Option<BoundingBox> {
+    let mut coordinates = way_nodes.iter().map(OsmWayNode::lat_lon);
+    let (lat, lon) = coordinates.next()?;
+    let mut bbox = BoundingBox::new(lon, lat, lon, lat);
+    for (lat, lon) in coordinates {
+        bbox.xmin = bbox.xmin.min(lon);
+        bbox.xmax = bbox.xmax.max(lon);
+        bbox.ymin = bbox.ymin.min(lat);
+        bbox.ymax = bbox.ymax.max(lat);
+    }
+    Some(bbox)
+}
+
+fn read_bin<T>(path: &Path, from_bytes: fn(&[u8]) -> Option<Vec<T>>) -> Result<Vec<T>> {
+    let bytes = fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    from_bytes(&bytes).with_context(|| format!("{} has a truncated record", path.display()))
+}
+
+/// Summarize the `ways.bin` and `way_nodes.bin` in `output_dir`, including the extent they
+/// cover, into `conversion_manifest.json` there
+pub fn write_conversion_manifest(output_dir: &Path) -> Result<ConversionManifest> {
+    let ways = read_bin(&output_dir.join("ways.bin"), OsmWay::from_bytes)?;
+    let way_nodes = read_bin(&output_dir.join("way_nodes.bin"), OsmWayNode::from_bytes)?;
+
+    let manifest = ConversionManifest {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        ways: ways.len(),
+        way_nodes: way_nodes.len(),
+        bbox: way_nodes_bbox(&way_nodes),
+    };
+    match &manifest.bbox {
+        Some(bbox) => info!(
+            "Output covers ({}, {}) to ({}, {})",
+            bbox.xmin, bbox.ymin, bbox.xmax, bbox.ymax
+        ),
+        None => info!("Output has no way nodes, recording no bounding box"),
+    }
+
+    let path = output_dir.join(CONVERSION_MANIFEST_FILE);
+    fs::write(&path, serde_json::to_string_pretty(&manifest)?)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(manifest)
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    fn write_bins(dir: &Path, ways: &[OsmWay], way_nodes: &[OsmWayNode]) {
+        fs::write(dir.join("ways.bin"), OsmWay::slice_as_bytes(ways)).unwrap();
+        fs::write(
+            dir.join("way_nodes.bin"),
+            OsmWayNode::slice_as_bytes(way_nodes),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_manifest_bbox_matches_way_nodes() {
+        let dir = tempdir().unwrap();
+        let coordinates = [(-122.3, 47.6), (-122.31, 47.62), (-122.29, 47.61)];
+        let way_nodes: Vec<OsmWayNode> = coordinates
+            .iter()
+            .enumerate()
+            .map(|(index, &(lon, lat))| OsmWayNode::new(0, index as u32, index as u64, lon, lat, 1))
+            .collect();
+        write_bins(dir.path(), &[OsmWay::new(1, 0, 3, true, true)], &way_nodes);
+
+        let manifest = write_conversion_manifest(dir.path()).unwrap();
+        assert_eq!(manifest.ways, 1);
+        assert_eq!(manifest.way_nodes, 3);
+        let bbox = manifest.bbox.clone().unwrap();
+        for (value, expected) in [
+            (bbox.xmin, -122.31),
+            (bbox.xmax, -122.29),
+            (bbox.ymin, 47.6),
+            (bbox.ymax, 47.62),
+        ] {
+            assert!((value - expected).abs() < 1e-6, "{} != {}", value, expected);
+        }
+
+        let written = fs::read_to_string(dir.path().join(CONVERSION_MANIFEST_FILE)).unwrap();
+        assert_eq!(
+            serde_json::from_str::<ConversionManifest>(&written).unwrap(),
+            manifest
+        );
+    }
+
+    #[test]
+    fn test_manifest_without_way_nodes() {
+        let dir = tempdir().unwrap();
+        write_bins(dir.path(), &[], &[]);
+
+        let manifest = write_conversion_manifest(dir.path()).unwrap();
+        assert_eq!(manifest.bbox, None);
+        let written = fs::read_to_string(dir.path().join(CONVERSION_MANIFEST_FILE)).unwrap();
+        assert!(written.contains("\"bbox\": null"));
+    }
+}
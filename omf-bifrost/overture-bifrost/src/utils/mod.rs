@@ -1,2 +1,7 @@
 pub mod download;
+pub mod download_error;
+pub mod interrupt;
 pub mod logging;
+pub mod manifest;
+pub mod package;
+pub mod selftest;
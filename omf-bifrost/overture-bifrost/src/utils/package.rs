@@ -0,0 +1,273 @@
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+use log::{debug, info};
+use overture_valhalla_writer::restrictions::RESTRICTIONS_FILE;
+use overture_valhalla_writer::sources::SOURCES_FILE;
+use overture_valhalla_writer::strings::STRINGS_FILE;
+use serde::{Deserialize, Serialize};
+
+use crate::utils::manifest::CONVERSION_MANIFEST_FILE;
+
+/// Files a converted directory must contain to be packaged
+pub const REQUIRED_FILES: &[&str] = &["ways.bin", "way_nodes.bin"];
+
+/// Files `convert` writes only for some inputs or options, packaged when present: the way name
+/// table, turn restrictions, `--preserve-sources` provenance, the manifest and the
+/// `--split-by-mode` way files
+pub const OPTIONAL_FILES: &[&str] = &[
+    STRINGS_FILE,
+    RESTRICTIONS_FILE,
+    SOURCES_FILE,
+    CONVERSION_MANIFEST_FILE,
+    "auto_ways.bin",
+    "auto_way_nodes.bin",
+    "pedestrian_ways.bin",
+    "pedestrian_way_nodes.bin",
+    "bicycle_ways.bin",
+    "bicycle_way_nodes.bin",
+];
+
+/// Name of the admin database in the archive
+pub const ADMIN_DB_FILE: &str = "admin.sqlite";
+
+/// Name of the Valhalla config in the archive
+pub const VALHALLA_CONFIG_FILE: &str = "valhalla.json";
+
+/// Name of the manifest written at the start of the archive
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+/// zstd compression level used for the archive
+const ZSTD_LEVEL: i32 = 19;
+
+/// One packaged file as listed in the manifest
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PackageEntry {
+    pub name: String,
+    pub size: u64,
+}
+
+/// Contents of `manifest.json`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PackageManifest {
+    /// Version of overture-bifrost that wrote the package
+    pub version: String,
+    pub files: Vec<PackageEntry>,
+}
+
+/// Files packaged from outside the converted directory. `build-admins` writes the admin
+/// database wherever `--output-dir` points, so both are given as paths.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PackageExtras<'a> {
+    /// Admin database, packaged as [`ADMIN_DB_FILE`]
+    pub admin_db: Option<&'a Path>,
+    /// Valhalla config, packaged as [`VALHALLA_CONFIG_FILE`]
+    pub valhalla_config: Option<&'a Path>,
+}
+
+fn entry(path: &Path, name: &str) -> Result<PackageEntry> {
+    let size = fs::metadata(path)
+        .with_context(|| format!("Failed to read metadata of {}", path.display()))?
+        .len();
+    Ok(PackageEntry {
+        name: name.to_string(),
+        size,
+    })
+}
+
+/// Collect the files to package with their paths, failing if a required file or an extra is
+/// missing
+fn collect_entries(
+    output_dir: &Path,
+    extras: &PackageExtras,
+) -> Result<Vec<(PathBuf, PackageEntry)>> {
+    let mut entries = Vec::new();
+    for name in REQUIRED_FILES.iter().chain(OPTIONAL_FILES) {
+        let path = output_dir.join(name);
+        if !path.is_file() {
+            if REQUIRED_FILES.contains(name) {
+                bail!("{} is missing from {}", name, output_dir.display());
+            }
+            debug!("{} not found in {}, skipping", name, output_dir.display());
+            continue;
+        }
+        entries.push((path.clone(), entry(&path, name)?));
+    }
+    for (path, name) in [
+        (extras.admin_db, ADMIN_DB_FILE),
+        (extras.valhalla_config, VALHALLA_CONFIG_FILE),
+    ] {
+        let Some(path) = path else {
+            continue;
+        };
+        if !path.is_file() {
+            bail!("{} does not exist", path.display());
+        }
+        entries.push((path.to_path_buf(), entry(path, name)?));
+    }
+    Ok(entries)
+}
+
+fn write_archive(
+    archive: &Path,
+    manifest: &PackageManifest,
+    entries: &[(PathBuf, PackageEntry)],
+) -> Result<()> {
+    let file =
+        File::create(archive).with_context(|| format!("Failed to create {}", archive.display()))?;
+    let encoder = zstd::Encoder::new(BufWriter::new(file), ZSTD_LEVEL)?;
+    let mut builder = tar::Builder::new(encoder);
+
+    let manifest_json = serde_json::to_vec_pretty(manifest)?;
+    let mut header = tar::Header::new_gnu();
+    header.set_size(manifest_json.len() as u64);
+    header.set_mode(0o644);
+    header.set_cksum();
+    builder.append_data(&mut header, MANIFEST_FILE, manifest_json.as_slice())?;
+
+    for (path, entry) in entries {
+        builder
+            .append_path_with_name(path, &entry.name)
+            .with_context(|| format!("Failed to add {} to the archive", path.display()))?;
+    }
+
+    builder.into_inner()?.finish()?;
+    Ok(())
+}
+
+/// Bundle the converted files in `output_dir`, and the `extras` given, into a `.tar.zst`
+/// archive with a manifest. `ways.bin` and `way_nodes.bin` are required, the other files
+/// `convert` writes are included when present.
+pub fn package_output(
+    output_dir: &Path,
+    archive: &Path,
+    extras: &PackageExtras,
+) -> Result<PackageManifest> {
+    let entries = collect_entries(output_dir, extras)?;
+    let manifest = PackageManifest {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        files: entries.iter().map(|(_, entry)| entry.clone()).collect(),
+    };
+
+    if let Err(e) = write_archive(archive, &manifest, &entries) {
+        let _ = fs::remove_file(archive);
+        return Err(e);
+    }
+
+    info!(
+        "Packaged {} files from {} into {}",
+        manifest.files.len(),
+        output_dir.display(),
+        archive.display()
+    );
+    Ok(manifest)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use overture_valhalla_writer::writer::{ConvertOptions, convert_overture_to_valhalla};
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::utils::manifest::write_conversion_manifest;
+    use crate::utils::selftest::write_bundled_sample;
+
+    /// Names and contents of the files in `archive`
+    fn read_archive(archive: &Path) -> Vec<(String, Vec<u8>)> {
+        let decoder = zstd::Decoder::new(File::open(archive).unwrap()).unwrap();
+        let mut tar = tar::Archive::new(decoder);
+        tar.entries()
+            .unwrap()
+            .map(|entry| {
+                let mut entry = entry.unwrap();
+                let name = entry.path().unwrap().to_string_lossy().into_owned();
+                let mut contents = Vec::new();
+                entry.read_to_end(&mut contents).unwrap();
+                (name, contents)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_package_converted_directory() {
+        let dir = tempdir().unwrap();
+        let input_dir = dir.path().join("input");
+        let output_dir = dir.path().join("output");
+        fs::create_dir_all(&input_dir).unwrap();
+        fs::create_dir_all(&output_dir).unwrap();
+        write_bundled_sample(&input_dir.join("segment.parquet")).unwrap();
+        let options = ConvertOptions {
+            preserve_sources: true,
+            ..Default::default()
+        };
+        convert_overture_to_valhalla(&input_dir, &output_dir, &options).unwrap();
+        write_conversion_manifest(&output_dir).unwrap();
+
+        let admin_db = dir.path().join("admins").join("admin.sqlite");
+        fs::create_dir_all(admin_db.parent().unwrap()).unwrap();
+        fs::write(&admin_db, b"admins").unwrap();
+        let archive = dir.path().join("graph.tar.zst");
+
+        let extras = PackageExtras {
+            admin_db: Some(&admin_db),
+            valhalla_config: None,
+        };
+        let manifest = package_output(&output_dir, &archive, &extras).unwrap();
+
+        let files = read_archive(&archive);
+        let names: Vec<&str> = files.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "manifest.json",
+                "ways.bin",
+                "way_nodes.bin",
+                "strings.bin",
+                "sources.json",
+                "conversion_manifest.json",
+                "admin.sqlite",
+            ]
+        );
+        for (name, contents) in &files[1..] {
+            let source = if name == ADMIN_DB_FILE {
+                admin_db.clone()
+            } else {
+                output_dir.join(name)
+            };
+            assert_eq!(contents, &fs::read(source).unwrap(), "{}", name);
+        }
+        let packaged_manifest: PackageManifest = serde_json::from_slice(&files[0].1).unwrap();
+        assert_eq!(packaged_manifest, manifest);
+    }
+
+    #[test]
+    fn test_package_requires_ways() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("way_nodes.bin"), b"").unwrap();
+        let archive = dir.path().join("graph.tar.zst");
+
+        let err = package_output(dir.path(), &archive, &PackageExtras::default()).unwrap_err();
+        assert!(err.to_string().contains("ways.bin"), "{}", err);
+        assert!(!archive.exists());
+    }
+
+    #[test]
+    fn test_package_requires_given_extras() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("ways.bin"), b"").unwrap();
+        fs::write(dir.path().join("way_nodes.bin"), b"").unwrap();
+        let missing = dir.path().join("valhalla.json");
+        let extras = PackageExtras {
+            admin_db: None,
+            valhalla_config: Some(&missing),
+        };
+
+        let err =
+            package_output(dir.path(), &dir.path().join("graph.tar.zst"), &extras).unwrap_err();
+        assert!(err.to_string().contains("valhalla.json"), "{}", err);
+    }
+}
@@ -0,0 +1,325 @@
+//! End-to-end self-test: download a tiny area, convert it and check the output, falling back
+//! to a bundled sample when the network is unavailable
+
+use std::fmt;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use arrow::array::{
+    ArrayRef, BinaryArray, Float64Builder, ListBuilder, RecordBatch, StringArray, StringBuilder,
+    StructBuilder,
+};
+use arrow::datatypes::{DataType, Field, Fields};
+use log::{info, warn};
+use overture_valhalla_writer::verify::verify_bin_files;
+use overture_valhalla_writer::writer::{ConvertOptions, convert_overture_to_valhalla};
+use parquet::arrow::ArrowWriter;
+
+use crate::utils::download::{BoundingBox, download_overture_data};
+use crate::utils::download_error::DownloadError;
+use crate::utils::manifest::write_conversion_manifest;
+
+/// A few blocks of downtown Seattle, small enough to download in seconds
+pub const SELFTEST_BBOX: BoundingBox = BoundingBox {
+    xmin: -122.3405,
+    xmax: -122.3375,
+    ymin: 47.6085,
+    ymax: 47.6105,
+};
+
+/// Connectors of the bundled sample as `(id, lon, lat)`, all inside [`SELFTEST_BBOX`]
+const SAMPLE_CONNECTORS: &[(&str, f64, f64)] = &[
+    ("selftest-c1", -122.3400, 47.6090),
+    ("selftest-c2", -122.3390, 47.6090),
+    ("selftest-c3", -122.3390, 47.6100),
+    ("selftest-c4", -122.3380, 47.6100),
+];
+
+/// Segments of the bundled sample as `(id, class, connector indices)`, each a straight line
+/// between its two connectors
+const SAMPLE_SEGMENTS: &[(&str, &str, [usize; 2])] = &[
+    ("selftest-s1", "residential", [0, 1]),
+    ("selftest-s2", "secondary", [1, 2]),
+    ("selftest-s3", "footway", [2, 3]),
+];
+
+/// Outcome of one stage
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StageStatus {
+    Pass,
+    Fail,
+    /// Not run because an earlier stage failed
+    Skip,
+}
+
+impl fmt::Display for StageStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StageStatus::Pass => write!(f, "PASS"),
+            StageStatus::Fail => write!(f, "FAIL"),
+            StageStatus::Skip => write!(f, "SKIP"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StageResult {
+    pub stage: &'static str,
+    pub status: StageStatus,
+    pub detail: String,
+}
+
+/// Stages of a self-test run in the order they ran
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SelftestReport {
+    pub stages: Vec<StageResult>,
+}
+
+impl SelftestReport {
+    pub fn passed(&self) -> bool {
+        self.stages
+            .iter()
+            .all(|stage| stage.status == StageStatus::Pass)
+    }
+
+    /// One `STATUS stage: detail` line per stage
+    pub fn summary(&self) -> String {
+        self.stages
+            .iter()
+            .map(|stage| format!("{} {}: {}\n", stage.status, stage.stage, stage.detail))
+            .collect()
+    }
+
+    /// Record a stage; once one has failed, the remaining ones are recorded as skipped
+    fn run(&mut self, stage: &'static str, run: impl FnOnce() -> Result<String>) {
+        if self.stages.iter().any(|s| s.status != StageStatus::Pass) {
+            self.stages.push(StageResult {
+                stage,
+                status: StageStatus::Skip,
+                detail: "an earlier stage failed".to_string(),
+            });
+            return;
+        }
+        let (status, detail) = match run() {
+            Ok(detail) => (StageStatus::Pass, detail),
+            Err(err) => (StageStatus::Fail, format!("{:#}", err)),
+        };
+        info!("Self-test stage {}: {} ({})", stage, status, detail);
+        self.stages.push(StageResult {
+            stage,
+            status,
+            detail,
+        });
+    }
+}
+
+/// Little-endian WKB of a point or, with several coordinates, a LineString
+fn wkb(coordinates: &[(f64, f64)]) -> Vec<u8> {
+    let mut bytes = vec![1u8];
+    if let [(lon, lat)] = coordinates {
+        bytes.extend(1u32.to_le_bytes());
+        bytes.extend(lon.to_le_bytes());
+        bytes.extend(lat.to_le_bytes());
+    } else {
+        bytes.extend(2u32.to_le_bytes());
+        bytes.extend((coordinates.len() as u32).to_le_bytes());
+        for (lon, lat) in coordinates {
+            bytes.extend(lon.to_le_bytes());
+            bytes.extend(lat.to_le_bytes());
+        }
+    }
+    bytes
+}
+
+/// Write the bundled sample to `path` in the layout of a `download`: segment and connector
+/// rows in one file, told apart by their `type`
+pub fn write_bundled_sample(path: &Path) -> Result<()> {
+    let mut ids = Vec::new();
+    let mut types = Vec::new();
+    let mut subtypes = Vec::new();
+    let mut classes = Vec::new();
+    let mut geometries = Vec::new();
+    let connector_fields = Fields::from(vec![
+        Field::new("connector_id", DataType::Utf8, true),
+        Field::new("at", DataType::Float64, true),
+    ]);
+    let mut connectors = ListBuilder::new(StructBuilder::from_fields(connector_fields, 0));
+
+    for (id, class, ends) in SAMPLE_SEGMENTS {
+        ids.push(*id);
+        types.push("segment");
+        subtypes.push(Some("road"));
+        classes.push(Some(*class));
+        let coordinates = ends.map(|end| (SAMPLE_CONNECTORS[end].1, SAMPLE_CONNECTORS[end].2));
+        geometries.push(wkb(&coordinates));
+        for (end, at) in ends.iter().zip([0.0, 1.0]) {
+            let refs = connectors.values();
+            refs.field_builder::<StringBuilder>(0)
+                .expect("connector_id builder")
+                .append_value(SAMPLE_CONNECTORS[*end].0);
+            refs.field_builder::<Float64Builder>(1)
+                .expect("at builder")
+                .append_value(at);
+            refs.append(true);
+        }
+        connectors.append(true);
+    }
+    for (id, lon, lat) in SAMPLE_CONNECTORS {
+        ids.push(*id);
+        types.push("connector");
+        subtypes.push(None);
+        classes.push(None);
+        geometries.push(wkb(&[(*lon, *lat)]));
+        connectors.append(false);
+    }
+
+    let batch = RecordBatch::try_from_iter([
+        ("id", Arc::new(StringArray::from(ids)) as ArrayRef),
+        ("type", Arc::new(StringArray::from(types))),
+        ("subtype", Arc::new(StringArray::from(subtypes))),
+        ("class", Arc::new(StringArray::from(classes))),
+        (
+            "geometry",
+            Arc::new(BinaryArray::from_iter_values(geometries)),
+        ),
+        ("connectors", Arc::new(connectors.finish())),
+    ])?;
+    let file =
+        File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+    let mut writer = ArrowWriter::try_new(file, batch.schema(), None)?;
+    writer.write(&batch)?;
+    writer.close()?;
+    Ok(())
+}
+
+/// Whether a download error means the data could not be reached at all, rather than a
+/// problem the self-test should report
+fn is_unreachable(err: &DownloadError) -> bool {
+    matches!(
+        err,
+        DownloadError::Network(_) | DownloadError::ExtensionLoad(_)
+    )
+}
+
+/// Fetch the input to `segment_path`: [`SELFTEST_BBOX`] of `release_version`, or the bundled
+/// sample when `offline` or the download cannot reach the bucket
+fn fetch_input(segment_path: &Path, release_version: &str, offline: bool) -> Result<String> {
+    if !offline {
+        let bbox = &SELFTEST_BBOX;
+        match download_overture_data(
+            release_version,
+            bbox.xmin,
+            bbox.xmax,
+            bbox.ymin,
+            bbox.ymax,
+            &segment_path.to_string_lossy(),
+            None,
+        ) {
+            Ok(()) => return Ok(format!("downloaded release {}", release_version)),
+            Err(err) if is_unreachable(&err) => {
+                warn!("Download failed, using the bundled sample: {}", err);
+            }
+            Err(err) => return Err(err.into()),
+        }
+    }
+    write_bundled_sample(segment_path)?;
+    Ok("using the bundled sample".to_string())
+}
+
+/// Run every stage in `work_dir`, which must exist
+fn run_stages(work_dir: &Path, release_version: &str, offline: bool) -> SelftestReport {
+    let input_dir = work_dir.join("input");
+    let output_dir = work_dir.join("output");
+    let mut report = SelftestReport::default();
+
+    report.run("download", || {
+        fs::create_dir_all(&input_dir)?;
+        fetch_input(&input_dir.join("segment.parquet"), release_version, offline)
+    });
+    report.run("convert", || {
+        fs::create_dir_all(&output_dir)?;
+        let metrics =
+            convert_overture_to_valhalla(&input_dir, &output_dir, &ConvertOptions::default())?;
+        Ok(format!("mapped {} segments", metrics.segments))
+    });
+    report.run("verify-bin", || {
+        let violations = verify_bin_files(&output_dir)?;
+        if let Some(first) = violations.first() {
+            anyhow::bail!("{} violations, first: {}", violations.len(), first);
+        }
+        Ok("all checks passed".to_string())
+    });
+    report.run("validate", || {
+        let manifest = write_conversion_manifest(&output_dir)?;
+        let Some(bbox) = manifest.bbox else {
+            anyhow::bail!("no ways were written");
+        };
+        // Downloaded segments are kept whole, so they may reach past the box but must touch it
+        let touches = bbox.xmin <= SELFTEST_BBOX.xmax
+            && bbox.xmax >= SELFTEST_BBOX.xmin
+            && bbox.ymin <= SELFTEST_BBOX.ymax
+            && bbox.ymax >= SELFTEST_BBOX.ymin;
+        if !touches {
+            anyhow::bail!(
+                "output covers ({}, {}) to ({}, {}), away from the test area",
+                bbox.xmin,
+                bbox.ymin,
+                bbox.xmax,
+                bbox.ymax
+            );
+        }
+        Ok(format!(
+            "{} ways, {} way nodes",
+            manifest.ways, manifest.way_nodes
+        ))
+    });
+    report
+}
+
+/// Directory a self-test works in, unique to the process
+pub fn selftest_dir() -> PathBuf {
+    std::env::temp_dir().join(format!("omf-bifrost-selftest-{}", std::process::id()))
+}
+
+/// Download (or, `offline`, take the bundled sample), convert and check the output in a
+/// temporary directory, removed again afterwards
+pub fn run_selftest(release_version: &str, offline: bool) -> Result<SelftestReport> {
+    let work_dir = selftest_dir();
+    fs::create_dir_all(&work_dir)
+        .with_context(|| format!("Failed to create {}", work_dir.display()))?;
+    let report = run_stages(&work_dir, release_version, offline);
+    if let Err(err) = fs::remove_dir_all(&work_dir) {
+        warn!("Failed to remove {}: {}", work_dir.display(), err);
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::download::default_release_version;
+
+    #[test]
+    fn test_selftest_bundled_sample_passes() {
+        let report = run_selftest(&default_release_version(), true).unwrap();
+        let stages: Vec<_> = report.stages.iter().map(|stage| stage.stage).collect();
+        assert_eq!(stages, ["download", "convert", "verify-bin", "validate"]);
+        assert!(report.passed(), "{}", report.summary());
+        assert_eq!(report.stages[0].detail, "using the bundled sample");
+        assert!(!selftest_dir().exists());
+    }
+
+    #[test]
+    fn test_stages_after_failure_skipped() {
+        let mut report = SelftestReport::default();
+        report.run("first", || anyhow::bail!("broken"));
+        report.run("second", || Ok("unreachable".to_string()));
+        assert_eq!(
+            report.summary(),
+            "FAIL first: broken\nSKIP second: an earlier stage failed\n"
+        );
+        assert!(!report.passed());
+    }
+}
@@ -0,0 +1,15 @@
+//! Errors raised while writing tiles
+
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum TilesError {
+    #[error("invalid zoom range {min_zoom}..={max_zoom}, expected min <= max <= 22")]
+    InvalidZoomRange { min_zoom: u8, max_zoom: u8 },
+
+    #[error("SQLite error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
@@ -2,6 +2,11 @@
 //! 
 //! Map tile visualization for Overture Maps transportation data.
 //! 
-//! This crate will provide tile rendering capabilities in the future.
+//! Currently this exports converted segments to vector MBTiles, see [`mbtiles::export_mbtiles`].
 
-// Placeholder - to be implemented later
+pub mod error;
+pub mod mbtiles;
+mod mvt;
+
+pub use error::TilesError;
+pub use mbtiles::export_mbtiles;
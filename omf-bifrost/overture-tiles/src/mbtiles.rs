@@ -0,0 +1,245 @@
+//! Export of converted segments to vector MBTiles for inspection in a map viewer
+
+use std::collections::BTreeMap;
+use std::f64::consts::PI;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use log::info;
+use rusqlite::{Connection, params};
+
+use overture_valhalla_writer::writer::{Data, Point};
+
+use crate::error::TilesError;
+use crate::mvt::{EXTENT, LayerBuilder};
+
+/// Name of the single vector layer holding the segments
+pub const LAYER_NAME: &str = "transportation";
+
+/// Zoom levels written when none are given
+pub const DEFAULT_MIN_ZOOM: u8 = 12;
+pub const DEFAULT_MAX_ZOOM: u8 = 14;
+
+/// Latitude limit of the Web Mercator projection
+const MAX_LATITUDE: f64 = 85.051_128_78;
+
+/// Fractional Web Mercator tile coordinates of a point at `zoom`
+fn project(point: &Point, zoom: u8) -> (f64, f64) {
+    let tiles = f64::from(1u32 << zoom);
+    let lat = point.lat.clamp(-MAX_LATITUDE, MAX_LATITUDE).to_radians();
+    let x = (point.lon + 180.0) / 360.0 * tiles;
+    let y = (1.0 - (lat.tan() + 1.0 / lat.cos()).ln() / PI) / 2.0 * tiles;
+    let max = tiles - 1e-9;
+    (x.clamp(0.0, max), y.clamp(0.0, max))
+}
+
+/// Vector tiles (XYZ scheme) of all segments at `zoom`; segments go into every tile their
+/// bounding box touches, unclipped
+fn build_zoom(data: &Data, zoom: u8) -> BTreeMap<(u32, u32), LayerBuilder> {
+    let mut tiles: BTreeMap<(u32, u32), LayerBuilder> = BTreeMap::new();
+    for segment in &data.segments {
+        if segment.points.len() < 2 {
+            continue;
+        }
+        let projected: Vec<(f64, f64)> = segment.points.iter().map(|point| project(point, zoom)).collect();
+        let (mut min_x, mut min_y, mut max_x, mut max_y) = (f64::MAX, f64::MAX, f64::MIN, f64::MIN);
+        for &(x, y) in &projected {
+            min_x = min_x.min(x);
+            min_y = min_y.min(y);
+            max_x = max_x.max(x);
+            max_y = max_y.max(y);
+        }
+
+        let class = segment.road_class.as_deref().unwrap_or("unknown");
+        let tags = [("id", segment.id.as_str()), ("class", class)];
+        for tile_x in min_x.floor() as u32..=max_x.floor() as u32 {
+            for tile_y in min_y.floor() as u32..=max_y.floor() as u32 {
+                let local: Vec<(i64, i64)> = projected
+                    .iter()
+                    .map(|&(x, y)| {
+                        (
+                            ((x - f64::from(tile_x)) * f64::from(EXTENT)).round() as i64,
+                            ((y - f64::from(tile_y)) * f64::from(EXTENT)).round() as i64,
+                        )
+                    })
+                    .collect();
+                tiles
+                    .entry((tile_x, tile_y))
+                    .or_insert_with(|| LayerBuilder::new(LAYER_NAME))
+                    .add_line(&tags, &local);
+            }
+        }
+    }
+    tiles.retain(|_, layer| !layer.is_empty());
+    tiles
+}
+
+fn gzip(bytes: &[u8]) -> std::io::Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(bytes)?;
+    encoder.finish()
+}
+
+/// `west,south,east,north` of all segment vertices
+fn bounds(data: &Data) -> Option<String> {
+    let mut points = data.segments.iter().flat_map(|segment| &segment.points);
+    let first = points.next()?;
+    let (mut west, mut south, mut east, mut north) = (first.lon, first.lat, first.lon, first.lat);
+    for point in points {
+        west = west.min(point.lon);
+        south = south.min(point.lat);
+        east = east.max(point.lon);
+        north = north.max(point.lat);
+    }
+    Some(format!("{},{},{},{}", west, south, east, north))
+}
+
+fn write_metadata(conn: &Connection, data: &Data, min_zoom: u8, max_zoom: u8) -> Result<(), TilesError> {
+    let vector_layers = serde_json::json!({
+        "vector_layers": [{
+            "id": LAYER_NAME,
+            "fields": { "id": "String", "class": "String" },
+            "minzoom": min_zoom,
+            "maxzoom": max_zoom,
+        }]
+    });
+    let mut metadata = vec![
+        ("name", "Overture transportation".to_string()),
+        ("format", "pbf".to_string()),
+        ("type", "overlay".to_string()),
+        ("minzoom", min_zoom.to_string()),
+        ("maxzoom", max_zoom.to_string()),
+        ("json", vector_layers.to_string()),
+    ];
+    if let Some(bounds) = bounds(data) {
+        metadata.push(("bounds", bounds));
+    }
+
+    let mut insert = conn.prepare("INSERT INTO metadata (name, value) VALUES (?1, ?2)")?;
+    for (name, value) in metadata {
+        insert.execute(params![name, value])?;
+    }
+    Ok(())
+}
+
+/// Write the segments of `data` to an MBTiles file at `path` as a `transportation` vector layer
+/// of LineStrings tagged with their id and class, for every zoom level in `min_zoom..=max_zoom`.
+/// An existing file is replaced. Returns the number of tiles written.
+pub fn export_mbtiles(data: &Data, path: &Path, min_zoom: u8, max_zoom: u8) -> Result<usize, TilesError> {
+    if min_zoom > max_zoom || max_zoom > 22 {
+        return Err(TilesError::InvalidZoomRange { min_zoom, max_zoom });
+    }
+    if path.exists() {
+        fs::remove_file(path)?;
+    }
+
+    let mut conn = Connection::open(path)?;
+    conn.execute_batch(
+        "CREATE TABLE metadata (name TEXT, value TEXT);
+         CREATE TABLE tiles (zoom_level INTEGER, tile_column INTEGER, tile_row INTEGER, tile_data BLOB);
+         CREATE UNIQUE INDEX tile_index ON tiles (zoom_level, tile_column, tile_row);",
+    )?;
+
+    let transaction = conn.transaction()?;
+    write_metadata(&transaction, data, min_zoom, max_zoom)?;
+    let mut tile_count = 0;
+    {
+        let mut insert = transaction.prepare(
+            "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (?1, ?2, ?3, ?4)",
+        )?;
+        for zoom in min_zoom..=max_zoom {
+            let tiles = build_zoom(data, zoom);
+            info!("Writing {} tiles at zoom {}", tiles.len(), zoom);
+            for ((x, y), layer) in tiles {
+                // MBTiles rows use the TMS scheme with y growing northwards
+                let tms_y = (1u32 << zoom) - 1 - y;
+                insert.execute(params![zoom, x, tms_y, gzip(&layer.encode_tile())?])?;
+                tile_count += 1;
+            }
+        }
+    }
+    transaction.commit()?;
+
+    Ok(tile_count)
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use overture_valhalla_writer::writer::Segment;
+
+    use super::*;
+
+    fn segment(id: &str, class: &str, points: Vec<Point>) -> Segment {
+        Segment {
+            id: id.to_string(),
+            name: id.to_string(),
+            road_class: Some(class.to_string()),
+            subtype: Some("road".to_string()),
+            points,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_export_mbtiles() {
+        let data = Data {
+            segments: vec![
+                segment(
+                    "s1",
+                    "primary",
+                    vec![Point { lat: 47.6, lon: -122.3 }, Point { lat: 47.61, lon: -122.31 }],
+                ),
+                segment(
+                    "s2",
+                    "residential",
+                    vec![Point { lat: 47.61, lon: -122.31 }, Point { lat: 47.62, lon: -122.3 }],
+                ),
+            ],
+            connectors: Vec::new(),
+        };
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("segments.mbtiles");
+
+        let tile_count = export_mbtiles(&data, &path, DEFAULT_MIN_ZOOM, DEFAULT_MAX_ZOOM).unwrap();
+        assert!(tile_count >= 3);
+
+        let conn = Connection::open(&path).unwrap();
+        let rows: i64 = conn.query_row("SELECT COUNT(*) FROM tiles", [], |row| row.get(0)).unwrap();
+        assert_eq!(rows as usize, tile_count);
+        let format: String = conn
+            .query_row("SELECT value FROM metadata WHERE name = 'format'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(format, "pbf");
+        let zooms: Vec<u8> = conn
+            .prepare("SELECT DISTINCT zoom_level FROM tiles ORDER BY zoom_level")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(zooms, vec![12, 13, 14]);
+
+        // Seattle is tile 656/1430 at zoom 12, stored as TMS row 4095 - 1430
+        let tile: Vec<u8> = conn
+            .query_row(
+                "SELECT tile_data FROM tiles WHERE zoom_level = 12 AND tile_column = 656 AND tile_row = 2665",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(&tile[..2], &[0x1f, 0x8b]);
+    }
+
+    #[test]
+    fn test_export_mbtiles_rejects_bad_zoom_range() {
+        let data = Data { segments: Vec::new(), connectors: Vec::new() };
+        let dir = tempdir().unwrap();
+        let result = export_mbtiles(&data, &dir.path().join("empty.mbtiles"), 14, 12);
+        assert!(matches!(result, Err(TilesError::InvalidZoomRange { .. })));
+    }
+}
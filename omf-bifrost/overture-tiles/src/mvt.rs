@@ -0,0 +1,164 @@
+//! Minimal Mapbox Vector Tile (protobuf) encoder for single-layer LineString tiles
+
+use std::collections::HashMap;
+
+/// Tile coordinate extent used for all layers
+pub const EXTENT: u32 = 4096;
+
+const WIRE_VARINT: u64 = 0;
+const WIRE_LENGTH_DELIMITED: u64 = 2;
+
+const GEOM_TYPE_LINESTRING: u64 = 2;
+const CMD_MOVE_TO: u32 = 1;
+const CMD_LINE_TO: u32 = 2;
+
+fn put_varint(buf: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buf.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
+
+fn put_key(buf: &mut Vec<u8>, field: u64, wire_type: u64) {
+    put_varint(buf, (field << 3) | wire_type);
+}
+
+fn put_varint_field(buf: &mut Vec<u8>, field: u64, value: u64) {
+    put_key(buf, field, WIRE_VARINT);
+    put_varint(buf, value);
+}
+
+fn put_bytes_field(buf: &mut Vec<u8>, field: u64, bytes: &[u8]) {
+    put_key(buf, field, WIRE_LENGTH_DELIMITED);
+    put_varint(buf, bytes.len() as u64);
+    buf.extend_from_slice(bytes);
+}
+
+fn put_packed_field(buf: &mut Vec<u8>, field: u64, values: &[u32]) {
+    let mut packed = Vec::new();
+    for value in values {
+        put_varint(&mut packed, u64::from(*value));
+    }
+    put_bytes_field(buf, field, &packed);
+}
+
+fn zigzag(value: i64) -> u32 {
+    ((value << 1) ^ (value >> 63)) as u32
+}
+
+fn command(id: u32, count: u32) -> u32 {
+    (id & 0x7) | (count << 3)
+}
+
+/// Collects the features of one layer and encodes it as a complete tile
+#[derive(Debug)]
+pub struct LayerBuilder {
+    name: String,
+    keys: Vec<String>,
+    key_index: HashMap<String, u32>,
+    values: Vec<String>,
+    value_index: HashMap<String, u32>,
+    features: Vec<Vec<u8>>,
+}
+
+impl LayerBuilder {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            keys: Vec::new(),
+            key_index: HashMap::new(),
+            values: Vec::new(),
+            value_index: HashMap::new(),
+            features: Vec::new(),
+        }
+    }
+
+    fn intern(table: &mut Vec<String>, index: &mut HashMap<String, u32>, value: &str) -> u32 {
+        *index.entry(value.to_string()).or_insert_with(|| {
+            table.push(value.to_string());
+            table.len() as u32 - 1
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.features.is_empty()
+    }
+
+    /// Add a LineString in tile coordinates with string tags. Repeated points are dropped,
+    /// lines with fewer than two distinct points are skipped. Returns whether it was added.
+    pub fn add_line(&mut self, tags: &[(&str, &str)], points: &[(i64, i64)]) -> bool {
+        let mut points = points.to_vec();
+        points.dedup();
+        if points.len() < 2 {
+            return false;
+        }
+
+        let mut geometry = Vec::with_capacity(points.len() * 2 + 2);
+        let (mut x, mut y) = points[0];
+        geometry.push(command(CMD_MOVE_TO, 1));
+        geometry.push(zigzag(x));
+        geometry.push(zigzag(y));
+        geometry.push(command(CMD_LINE_TO, points.len() as u32 - 1));
+        for &(next_x, next_y) in &points[1..] {
+            geometry.push(zigzag(next_x - x));
+            geometry.push(zigzag(next_y - y));
+            (x, y) = (next_x, next_y);
+        }
+
+        let mut tag_indices = Vec::with_capacity(tags.len() * 2);
+        for (key, value) in tags {
+            tag_indices.push(Self::intern(&mut self.keys, &mut self.key_index, key));
+            tag_indices.push(Self::intern(&mut self.values, &mut self.value_index, value));
+        }
+
+        let mut feature = Vec::new();
+        put_varint_field(&mut feature, 1, self.features.len() as u64 + 1);
+        put_packed_field(&mut feature, 2, &tag_indices);
+        put_varint_field(&mut feature, 3, GEOM_TYPE_LINESTRING);
+        put_packed_field(&mut feature, 4, &geometry);
+        self.features.push(feature);
+        true
+    }
+
+    /// Encode a tile holding just this layer
+    pub fn encode_tile(&self) -> Vec<u8> {
+        let mut layer = Vec::new();
+        put_varint_field(&mut layer, 15, 2);
+        put_bytes_field(&mut layer, 1, self.name.as_bytes());
+        for feature in &self.features {
+            put_bytes_field(&mut layer, 2, feature);
+        }
+        for key in &self.keys {
+            put_bytes_field(&mut layer, 3, key.as_bytes());
+        }
+        for value in &self.values {
+            let mut encoded = Vec::new();
+            put_bytes_field(&mut encoded, 1, value.as_bytes());
+            put_bytes_field(&mut layer, 4, &encoded);
+        }
+        put_varint_field(&mut layer, 5, u64::from(EXTENT));
+
+        let mut tile = Vec::new();
+        put_bytes_field(&mut tile, 3, &layer);
+        tile
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_line_geometry() {
+        let mut layer = LayerBuilder::new("transportation");
+        assert!(!layer.add_line(&[("class", "primary")], &[(5, 5), (5, 5)]));
+        assert!(layer.add_line(&[("class", "primary")], &[(2, 2), (2, 10), (10, 10)]));
+
+        // Example from the vector tile specification, section 4.3.5
+        let mut expected = Vec::new();
+        put_packed_field(&mut expected, 4, &[9, 4, 4, 18, 0, 16, 16, 0]);
+        let tile = layer.encode_tile();
+        assert!(tile.windows(expected.len()).any(|window| window == expected.as_slice()));
+    }
+}
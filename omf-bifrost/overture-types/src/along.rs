@@ -0,0 +1,77 @@
+//! Positions along a segment, as used for connector references and linear referencing
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Relative position along a segment, from 0.0 at its start to 1.0 at its end
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Serialize, Deserialize)]
+#[serde(try_from = "f64", into = "f64")]
+pub struct AlongFraction(f64);
+
+/// Error for a position outside [0, 1]
+#[derive(Debug, Clone, Copy, PartialEq, Error)]
+#[error("position along segment must be within [0, 1], got {0}")]
+pub struct AlongFractionError(pub f64);
+
+impl AlongFraction {
+    /// Start of the segment
+    pub const START: AlongFraction = AlongFraction(0.0);
+
+    /// End of the segment
+    pub const END: AlongFraction = AlongFraction(1.0);
+
+    /// Create a position, failing for values outside [0, 1] (including NaN)
+    pub fn new(value: f64) -> Result<Self, AlongFractionError> {
+        if (0.0..=1.0).contains(&value) {
+            Ok(AlongFraction(value))
+        } else {
+            Err(AlongFractionError(value))
+        }
+    }
+
+    /// The position as a fraction of the segment length
+    pub fn value(self) -> f64 {
+        self.0
+    }
+}
+
+impl TryFrom<f64> for AlongFraction {
+    type Error = AlongFractionError;
+
+    fn try_from(value: f64) -> Result<Self, Self::Error> {
+        AlongFraction::new(value)
+    }
+}
+
+impl From<AlongFraction> for f64 {
+    fn from(fraction: AlongFraction) -> Self {
+        fraction.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_along_fraction_accepts_range() {
+        assert_eq!(AlongFraction::new(0.5).unwrap().value(), 0.5);
+        assert_eq!(AlongFraction::new(0.0).unwrap(), AlongFraction::START);
+        assert_eq!(AlongFraction::new(1.0).unwrap(), AlongFraction::END);
+    }
+
+    #[test]
+    fn test_along_fraction_rejects_out_of_range() {
+        assert_eq!(AlongFraction::new(-0.1), Err(AlongFractionError(-0.1)));
+        assert_eq!(AlongFraction::new(1.5), Err(AlongFractionError(1.5)));
+        assert!(AlongFraction::new(f64::NAN).is_err());
+    }
+
+    #[test]
+    fn test_along_fraction_serde_validates() {
+        let fraction: AlongFraction = serde_json::from_str("0.25").unwrap();
+        assert_eq!(fraction.value(), 0.25);
+        assert_eq!(serde_json::to_string(&fraction).unwrap(), "0.25");
+        assert!(serde_json::from_str::<AlongFraction>("2.0").is_err());
+    }
+}
@@ -9,7 +9,9 @@
 pub mod segment;
 pub mod connector;
 pub mod properties;
+pub mod along;
 
 pub use segment::Segment;
 pub use connector::Connector;
 pub use properties::*;
+pub use along::{AlongFraction, AlongFractionError};
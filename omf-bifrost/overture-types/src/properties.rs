@@ -2,6 +2,8 @@
 
 use serde::{Deserialize, Serialize};
 
+use crate::along::AlongFraction;
+
 /// Properties associated with a transportation segment
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SegmentProperties {
@@ -22,6 +24,12 @@ pub struct SegmentProperties {
     
     /// Speed limits
     pub speed_limits: Option<Vec<SpeedLimit>>,
+    
+    /// Turns that may not be taken from this segment
+    pub prohibited_transitions: Option<Vec<ProhibitedTransition>>,
+    
+    /// Datasets the segment was derived from
+    pub sources: Option<Vec<Source>>,
 }
 
 /// Properties associated with a transportation connector
@@ -32,6 +40,28 @@ pub struct ConnectorProperties {
     
     /// Connected segments
     pub connected_segments: Option<Vec<ConnectedSegment>>,
+    
+    /// Datasets the connector was derived from
+    pub sources: Option<Vec<Source>>,
+}
+
+/// Provenance of a feature or one of its properties
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Source {
+    /// JSON pointer to the property the source applies to, the whole feature when empty
+    pub property: Option<String>,
+    
+    /// Name of the source dataset (OpenStreetMap, TomTom, etc.)
+    pub dataset: String,
+    
+    /// Identifier of the feature in the source dataset, e.g. "w123" for an OSM way
+    pub record_id: Option<String>,
+    
+    /// When the source record was last updated
+    pub update_time: Option<String>,
+    
+    /// Confidence in the source data (0-1)
+    pub confidence: Option<f64>,
 }
 
 /// Road names in different languages
@@ -75,6 +105,16 @@ pub struct SpeedLimit {
     
     /// Minimum speed
     pub min_speed: Option<Speed>,
+    
+    /// When this speed limit applies
+    pub when: Option<SpeedLimitWhen>,
+}
+
+/// When a speed limit applies
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpeedLimitWhen {
+    /// Direction of travel relative to the segment geometry (forward, backward)
+    pub heading: Option<String>,
 }
 
 /// Speed value with unit
@@ -87,6 +127,42 @@ pub struct Speed {
     pub unit: String,
 }
 
+/// A transition from a segment that may not be taken (turn restriction)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProhibitedTransition {
+    /// Connectors and segments traversed after leaving this segment, in order
+    pub sequence: Vec<TransitionStep>,
+    
+    /// Heading on the final segment of the sequence (forward, backward)
+    pub final_heading: Option<String>,
+    
+    /// When this restriction applies
+    pub when: Option<TransitionWhen>,
+}
+
+/// One step of a prohibited transition
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransitionStep {
+    /// Connector passed through
+    pub connector_id: String,
+    
+    /// Segment entered at the connector
+    pub segment_id: String,
+}
+
+/// When a prohibited transition applies
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TransitionWhen {
+    /// Heading on this segment the restriction applies to (forward, backward)
+    pub heading: Option<String>,
+    
+    /// Time period in OSM opening hours syntax, e.g. "Mo-Fr 07:00-09:00"
+    pub during: Option<String>,
+    
+    /// Travel modes the restriction applies to
+    pub mode: Option<Vec<String>>,
+}
+
 /// Reference to a connected segment
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConnectedSegment {
@@ -94,5 +170,5 @@ pub struct ConnectedSegment {
     pub segment_id: String,
     
     /// Position along the segment (0.0 = start, 1.0 = end)
-    pub at: f64,
+    pub at: AlongFraction,
 }
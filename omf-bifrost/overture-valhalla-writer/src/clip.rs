@@ -0,0 +1,103 @@
+// Clipping the converted segments to a bounding box. Like the download query, rows carrying
+// Overture's `bbox` struct are rejected on it first, before their geometry is decoded.
+use parquet::record::{Field, Row};
+
+use crate::writer::Point;
+
+/// Box segments are clipped to, in degrees. Segments with any part inside are kept whole.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClipBox {
+    pub xmin: f64,
+    pub ymin: f64,
+    pub xmax: f64,
+    pub ymax: f64,
+}
+
+impl ClipBox {
+    pub fn new(xmin: f64, ymin: f64, xmax: f64, ymax: f64) -> Self {
+        ClipBox { xmin, ymin, xmax, ymax }
+    }
+
+    /// Whether `other` shares any point with the box
+    pub fn overlaps(&self, other: &ClipBox) -> bool {
+        other.xmin <= self.xmax && other.xmax >= self.xmin && other.ymin <= self.ymax && other.ymax >= self.ymin
+    }
+
+    pub fn contains(&self, point: &Point) -> bool {
+        (self.xmin..=self.xmax).contains(&point.lon) && (self.ymin..=self.ymax).contains(&point.lat)
+    }
+
+    /// Whether the line from `from` to `to` passes through the box (Liang-Barsky)
+    fn crosses(&self, from: &Point, to: &Point) -> bool {
+        let (dx, dy) = (to.lon - from.lon, to.lat - from.lat);
+        let (mut enter, mut exit) = (0.0f64, 1.0f64);
+        for (p, q) in [
+            (-dx, from.lon - self.xmin),
+            (dx, self.xmax - from.lon),
+            (-dy, from.lat - self.ymin),
+            (dy, self.ymax - from.lat),
+        ] {
+            if p == 0.0 {
+                if q < 0.0 {
+                    return false;
+                }
+            } else if p < 0.0 {
+                enter = enter.max(q / p);
+            } else {
+                exit = exit.min(q / p);
+            }
+        }
+        enter <= exit
+    }
+
+    /// Whether a vertex of the line lies in the box or one of its edges passes through it
+    pub fn intersects(&self, points: &[Point]) -> bool {
+        points.iter().any(|point| self.contains(point))
+            || points.windows(2).any(|pair| self.crosses(&pair[0], &pair[1]))
+    }
+}
+
+/// The Overture `bbox` struct of a row, `None` when the row has no complete one
+pub(crate) fn row_bbox(row: &Row) -> Option<ClipBox> {
+    let (_, Field::Group(bbox)) = row.get_column_iter().find(|column| column.0 == "bbox")? else {
+        return None;
+    };
+    let (mut xmin, mut ymin, mut xmax, mut ymax) = (None, None, None, None);
+    for (name, value) in bbox.get_column_iter() {
+        let value = match value {
+            Field::Double(value) => *value,
+            Field::Float(value) => *value as f64,
+            _ => continue,
+        };
+        match name.as_str() {
+            "xmin" => xmin = Some(value),
+            "ymin" => ymin = Some(value),
+            "xmax" => xmax = Some(value),
+            "ymax" => ymax = Some(value),
+            _ => {}
+        }
+    }
+    Some(ClipBox::new(xmin?, ymin?, xmax?, ymax?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(lon: f64, lat: f64) -> Point {
+        Point { lat, lon }
+    }
+
+    #[test]
+    fn test_line_box_intersection() {
+        let clip = ClipBox::new(-122.4, 47.5, -122.3, 47.6);
+        assert!(clip.intersects(&[point(-122.35, 47.55), point(-122.0, 47.0)]));
+        // Passes through the box without a vertex in it
+        assert!(clip.intersects(&[point(-122.5, 47.55), point(-122.2, 47.55)]));
+        // Its bbox overlaps the box, but the line passes beside it
+        let beside = [point(-122.5, 47.58), point(-122.38, 47.7)];
+        assert!(clip.overlaps(&ClipBox::new(-122.5, 47.58, -122.38, 47.7)));
+        assert!(!clip.intersects(&beside));
+        assert!(!clip.intersects(&[point(-121.0, 47.0), point(-121.1, 47.1)]));
+    }
+}
@@ -0,0 +1,155 @@
+// Time conditions of Overture `during` values (a subset of OSM opening hours), shared by the
+// conditional access, speed and turn restriction mappings
+use chrono::{DateTime, Datelike, Duration, TimeZone, Timelike};
+use serde::Serialize;
+
+/// Day of week bits, matching Valhalla's `DOW` mask (Sunday is bit 0)
+const DAY_NAMES: [&str; 7] = ["Su", "Mo", "Tu", "We", "Th", "Fr", "Sa"];
+pub const ALL_DAYS: u8 = 0b111_1111;
+
+/// Period a rule applies in: on the `days` of the week between `begin` and `end`. A period
+/// ending before it begins runs past midnight into the next day.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct TimeCondition {
+    /// Day of week mask, Sunday = 1, Monday = 2, ... Saturday = 64
+    pub days: u8,
+    pub begin_hour: u8,
+    pub begin_minute: u8,
+    pub end_hour: u8,
+    pub end_minute: u8,
+}
+
+fn parse_day(day: &str) -> Option<usize> {
+    DAY_NAMES.iter().position(|name| *name == day)
+}
+
+/// Day mask of a day spec such as `Mo-Fr`, `Sa,Su` or `Fr-Mo`
+fn parse_days(days: &str) -> Option<u8> {
+    let mut mask = 0;
+    for item in days.split(',') {
+        match item.split_once('-') {
+            Some((first, last)) => {
+                let (first, last) = (parse_day(first)?, parse_day(last)?);
+                let mut day = first;
+                loop {
+                    mask |= 1 << day;
+                    if day == last {
+                        break;
+                    }
+                    day = (day + 1) % 7;
+                }
+            }
+            None => mask |= 1 << parse_day(item)?,
+        }
+    }
+    Some(mask)
+}
+
+fn parse_clock(clock: &str) -> Option<(u8, u8)> {
+    let (hour, minute) = clock.split_once(':')?;
+    let (hour, minute) = (hour.parse::<u8>().ok()?, minute.parse::<u8>().ok()?);
+    (hour <= 24 && minute < 60).then_some((hour, minute))
+}
+
+/// Parse the subset of OSM opening hours used by Overture `during` values: an optional day spec
+/// followed by one time range, e.g. `Mo-Fr 07:00-09:00` or `(07:00-09:00)`
+pub fn parse_during(during: &str) -> Option<TimeCondition> {
+    let during = during.trim().trim_start_matches('(').trim_end_matches(')');
+    let (days, times) = match during.split_whitespace().collect::<Vec<_>>().as_slice() {
+        [times] => (ALL_DAYS, *times),
+        [days, times] => (parse_days(days)?, *times),
+        _ => return None,
+    };
+    let (begin, end) = times.split_once('-')?;
+    let ((begin_hour, begin_minute), (end_hour, end_minute)) = (parse_clock(begin)?, parse_clock(end)?);
+    Some(TimeCondition { days, begin_hour, begin_minute, end_hour, end_minute })
+}
+
+impl TimeCondition {
+    fn begin(&self) -> u32 {
+        self.begin_hour as u32 * 60 + self.begin_minute as u32
+    }
+
+    fn end(&self) -> u32 {
+        self.end_hour as u32 * 60 + self.end_minute as u32
+    }
+
+    fn on_day<Tz: TimeZone>(&self, datetime: &DateTime<Tz>) -> bool {
+        self.days & (1 << datetime.weekday().num_days_from_sunday()) != 0
+    }
+
+    /// Whether the condition holds at `datetime`, read as wall clock time in its own time zone.
+    /// Pass the time in the zone of the road, e.g. via `with_timezone`; the begin minute is
+    /// included and the end minute excluded.
+    pub fn applies_at<Tz: TimeZone>(&self, datetime: &DateTime<Tz>) -> bool {
+        let minute = datetime.hour() * 60 + datetime.minute();
+        let (begin, end) = (self.begin(), self.end());
+        if begin <= end {
+            self.on_day(datetime) && begin <= minute && minute < end
+        } else if minute >= begin {
+            self.on_day(datetime)
+        } else {
+            // Past midnight, the period belongs to the day it started on
+            minute < end && self.on_day(&(datetime.clone() - Duration::days(1)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{FixedOffset, NaiveDate, Utc};
+
+    use super::*;
+
+    /// 2024-06-03 is a Monday
+    fn at(day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        NaiveDate::from_ymd_opt(2024, 6, day).unwrap().and_hms_opt(hour, minute, 0).unwrap().and_utc()
+    }
+
+    #[test]
+    fn test_parse_during() {
+        assert_eq!(
+            parse_during("Mo-Fr 07:00-09:00"),
+            Some(TimeCondition { days: 0b011_1110, begin_hour: 7, begin_minute: 0, end_hour: 9, end_minute: 0 })
+        );
+        assert_eq!(parse_during("(Sa,Su 10:30-18:00)").map(|c| c.days), Some(0b100_0001));
+        assert_eq!(parse_during("Fr-Mo 22:00-06:00").map(|c| c.days), Some(0b110_0011));
+        assert_eq!(parse_during("16:00-19:00").map(|c| c.days), Some(ALL_DAYS));
+        assert_eq!(parse_during("Mo 00:00-24:00").map(|c| c.end_hour), Some(24));
+        assert_eq!(parse_during("sunrise-sunset"), None);
+        assert_eq!(parse_during("Mo-Fr 07:00-09:00, 16:00-18:00"), None);
+        assert_eq!(parse_during("Mo-Fr 07:00-25:00"), None);
+    }
+
+    #[test]
+    fn test_applies_at() {
+        let rush_hour = parse_during("Mo-Fr 07:00-09:00").unwrap();
+        assert!(rush_hour.applies_at(&at(3, 7, 0)));
+        assert!(rush_hour.applies_at(&at(7, 8, 59)));
+        assert!(!rush_hour.applies_at(&at(3, 9, 0)));
+        assert!(!rush_hour.applies_at(&at(3, 6, 59)));
+        // Saturday
+        assert!(!rush_hour.applies_at(&at(8, 8, 0)));
+
+        let all_day = parse_during("Sa,Su 00:00-24:00").unwrap();
+        assert!(all_day.applies_at(&at(9, 23, 59)));
+        assert!(!all_day.applies_at(&at(10, 0, 0)));
+
+        // Friday night into Saturday belongs to Friday, Saturday night is not included
+        let nights = parse_during("Mo-Fr 22:00-06:00").unwrap();
+        assert!(nights.applies_at(&at(7, 23, 0)));
+        assert!(nights.applies_at(&at(8, 5, 59)));
+        assert!(!nights.applies_at(&at(9, 5, 0)));
+        assert!(!nights.applies_at(&at(3, 12, 0)));
+    }
+
+    #[test]
+    fn test_applies_at_local_time() {
+        let rush_hour = parse_during("Mo-Fr 07:00-09:00").unwrap();
+        // 15:30 UTC on Monday is 07:30 on the US west coast
+        let instant = at(3, 15, 30);
+        let pacific = FixedOffset::west_opt(8 * 3600).unwrap();
+        assert!(!rush_hour.applies_at(&instant));
+        assert!(rush_hour.applies_at(&instant.with_timezone(&pacific)));
+    }
+}
@@ -0,0 +1,123 @@
+// Connectivity export for graph analysis without Valhalla
+use std::fs::write;
+use std::path::Path;
+
+use crate::writer::{Data, Point, Segment};
+
+const EARTH_RADIUS_M: f64 = 6_371_008.8;
+
+/// One connector-to-connector edge along a segment
+#[derive(Debug, Clone, PartialEq)]
+pub struct Edge {
+    pub from_connector_id: String,
+    pub to_connector_id: String,
+    pub segment_id: String,
+    pub length_m: f64,
+}
+
+fn haversine_m(a: &Point, b: &Point) -> f64 {
+    let (lat1, lat2) = (a.lat.to_radians(), b.lat.to_radians());
+    let d_lat = lat2 - lat1;
+    let d_lon = (b.lon - a.lon).to_radians();
+    let h = (d_lat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (d_lon / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_M * h.sqrt().asin()
+}
+
+fn segment_length_m(points: &[Point]) -> f64 {
+    points.windows(2).map(|pair| haversine_m(&pair[0], &pair[1])).sum()
+}
+
+/// Split a segment into edges between consecutive connectors, ordered by their `at` position.
+/// Edge lengths are the segment length scaled by the distance between the `at` values.
+pub fn segment_edges(segment: &Segment) -> Vec<Edge> {
+    let mut connector_refs: Vec<_> = segment.connectors.iter().collect();
+    connector_refs.sort_by(|a, b| a.at.value().total_cmp(&b.at.value()));
+
+    let length_m = segment_length_m(&segment.points);
+    connector_refs
+        .windows(2)
+        .map(|pair| Edge {
+            from_connector_id: pair[0].id.clone(),
+            to_connector_id: pair[1].id.clone(),
+            segment_id: segment.id.clone(),
+            length_m: length_m * (pair[1].at.value() - pair[0].at.value()),
+        })
+        .collect()
+}
+
+/// Write the connector adjacency of all segments as CSV with the columns
+/// `from_connector_id,to_connector_id,segment_id,length_m`
+pub fn export_edge_list(data: &Data, path: &Path) -> std::io::Result<()> {
+    let mut csv = String::from("from_connector_id,to_connector_id,segment_id,length_m\n");
+    for edge in data.segments.iter().flat_map(segment_edges) {
+        csv.push_str(&format!(
+            "{},{},{},{:.2}\n",
+            edge.from_connector_id, edge.to_connector_id, edge.segment_id, edge.length_m
+        ));
+    }
+
+    write(path, csv)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::read_to_string;
+
+    use tempfile::tempdir;
+
+    use overture_types::AlongFraction;
+
+    use super::*;
+    use crate::writer::ConnectorRef;
+
+    fn connector_ref(id: &str, at: f64) -> ConnectorRef {
+        ConnectorRef { id: id.to_string(), at: AlongFraction::new(at).unwrap(), coordinate: None }
+    }
+
+    fn equator_segment(id: &str, lon_from: f64, lon_to: f64, connectors: Vec<ConnectorRef>) -> Segment {
+        Segment {
+            id: id.to_string(),
+            name: id.to_string(),
+            road_class: Some("residential".to_string()),
+            subtype: Some("road".to_string()),
+            points: vec![Point { lat: 0.0, lon: lon_from }, Point { lat: 0.0, lon: lon_to }],
+            connectors,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_export_edge_list() {
+        let data = Data {
+            segments: vec![
+                // Intermediate connector c2 splits s1 into two edges
+                equator_segment(
+                    "s1",
+                    10.0,
+                    10.02,
+                    vec![connector_ref("c1", 0.0), connector_ref("c3", 1.0), connector_ref("c2", 0.5)],
+                ),
+                equator_segment("s2", 10.02, 10.03, vec![connector_ref("c3", 0.0), connector_ref("c4", 1.0)]),
+                // A single connector gives no edge
+                equator_segment("s3", 10.03, 10.04, vec![connector_ref("c4", 0.0)]),
+            ],
+            connectors: Vec::new(),
+        };
+
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("edges.csv");
+        export_edge_list(&data, &path).unwrap();
+
+        let csv = read_to_string(&path).unwrap();
+        let rows: Vec<&str> = csv.lines().collect();
+        assert_eq!(
+            rows,
+            vec![
+                "from_connector_id,to_connector_id,segment_id,length_m",
+                "c1,c2,s1,1111.95",
+                "c2,c3,s1,1111.95",
+                "c3,c4,s2,1111.95",
+            ]
+        );
+    }
+}
@@ -0,0 +1,45 @@
+// Errors raised while reading Overture data and writing it for Valhalla
+use std::io::{Error, ErrorKind};
+
+use overture_types::AlongFractionError;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum WriterError {
+    #[error("invalid WKB: {0}")]
+    InvalidWkb(String),
+
+    #[error("expected a {expected} geometry but found a {found}")]
+    UnexpectedGeometry {
+        expected: &'static str,
+        found: &'static str,
+    },
+
+    #[error("unexpected feature type '{0}', expected segment or connector rows")]
+    UnexpectedFeatureType(String),
+
+    #[error("connector '{0}' has no point geometry")]
+    MissingConnectorGeometry(String),
+
+    #[error("connector '{connector_id}': {source}")]
+    InvalidConnectorPosition {
+        connector_id: String,
+        source: AlongFractionError,
+    },
+
+    #[error(
+        "{roads} roads need way ids or way indices beyond the 32 bits Valhalla stores (at most {max_roads} roads); \
+         split the input into smaller extracts or tiles and convert them separately"
+    )]
+    TooManyWays { roads: u64, max_roads: u64 },
+
+    #[error("segment '{0}' has no connectors; pass another connectorless policy to skip it or synthesize its nodes")]
+    ConnectorlessSegment(String),
+}
+
+// The writer's public functions return `std::io::Result`, so writer errors surface as invalid data
+impl From<WriterError> for Error {
+    fn from(error: WriterError) -> Self {
+        Error::new(ErrorKind::InvalidData, error)
+    }
+}
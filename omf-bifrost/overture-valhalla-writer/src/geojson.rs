@@ -0,0 +1,123 @@
+// GeoJSON export of the imported segments and connectors, for inspecting data in a GIS
+use std::fs::write;
+use std::path::Path;
+
+use serde_json::{json, Value};
+
+use crate::writer::{round_coordinate, Data, Point};
+
+/// Decimal places kept by default, about 1 cm
+pub const DEFAULT_COORDINATE_PRECISION: u32 = 7;
+
+/// `[lon, lat]` rounded to `precision` decimals. Every coordinate goes through here, so points
+/// that are equal at that precision, like a segment end and its connector, stay identical.
+fn position(point: &Point, precision: u32) -> Value {
+    json!([round_coordinate(point.lon, precision), round_coordinate(point.lat, precision)])
+}
+
+/// Feature collection with a LineString per segment and a Point per connector, coordinates
+/// rounded to `precision` decimals
+pub fn data_to_geojson(data: &Data, precision: u32) -> Value {
+    let segments = data.segments.iter().map(|segment| {
+        json!({
+            "type": "Feature",
+            "geometry": {
+                "type": "LineString",
+                "coordinates": segment.points.iter().map(|point| position(point, precision)).collect::<Vec<_>>(),
+            },
+            "properties": {
+                "id": segment.id,
+                "name": segment.name,
+                "class": segment.road_class,
+                "connector_ids": segment.connectors.iter().map(|connector| &connector.id).collect::<Vec<_>>(),
+                "sources": segment.sources,
+            },
+        })
+    });
+    let connectors = data.connectors.iter().map(|connector| {
+        json!({
+            "type": "Feature",
+            "geometry": { "type": "Point", "coordinates": position(&connector.coordinate, precision) },
+            "properties": { "id": connector.id, "sources": connector.sources },
+        })
+    });
+
+    json!({ "type": "FeatureCollection", "features": segments.chain(connectors).collect::<Vec<_>>() })
+}
+
+/// Write `data_to_geojson` to `path`
+pub fn export_geojson(data: &Data, path: &Path, precision: u32) -> std::io::Result<()> {
+    write(path, serde_json::to_string(&data_to_geojson(data, precision))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::read_to_string;
+
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::writer::{Connector, Segment};
+
+    fn point(lon: f64, lat: f64) -> Point {
+        Point { lat, lon }
+    }
+
+    fn segment(id: &str, points: Vec<Point>) -> Segment {
+        Segment {
+            id: id.to_string(),
+            name: id.to_string(),
+            road_class: Some("residential".to_string()),
+            subtype: Some("road".to_string()),
+            points,
+            ..Default::default()
+        }
+    }
+
+    fn decimals(value: &Value) -> usize {
+        let text = value.to_string();
+        text.split_once('.').map_or(0, |(_, fraction)| fraction.len())
+    }
+
+    #[test]
+    fn test_coordinates_rounded_and_shared_nodes_identical() {
+        // Both segments end at the connector, with float noise below the precision
+        let data = Data {
+            segments: vec![
+                segment("s1", vec![point(-122.312345678, 47.6), point(-122.3000000004, 47.6100000003)]),
+                segment("s2", vec![point(-122.2999999996, 47.6099999998), point(-122.29, 47.62)]),
+            ],
+            connectors: vec![Connector {
+                id: "c1".to_string(),
+                coordinate: point(-122.3, 47.61),
+                ..Default::default()
+            }],
+        };
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("data.geojson");
+        export_geojson(&data, &path, DEFAULT_COORDINATE_PRECISION).unwrap();
+        let geojson: Value = serde_json::from_str(&read_to_string(&path).unwrap()).unwrap();
+
+        let features = geojson["features"].as_array().unwrap();
+        assert_eq!(features.len(), 3);
+        let first = &features[0]["geometry"]["coordinates"];
+        assert_eq!(first[0], json!([-122.3123457, 47.6]));
+        for feature in features {
+            let coordinates = &feature["geometry"]["coordinates"];
+            let positions = coordinates.as_array().unwrap();
+            let values: Vec<&Value> = match positions[0].as_array() {
+                Some(_) => positions.iter().flat_map(|position| position.as_array().unwrap()).collect(),
+                None => positions.iter().collect(),
+            };
+            assert!(values.iter().all(|value| decimals(value) <= 7), "{}", coordinates);
+        }
+
+        let s1_end = &first[1];
+        let s2_start = &features[1]["geometry"]["coordinates"][0];
+        let connector = &features[2]["geometry"]["coordinates"];
+        assert_eq!(s1_end, s2_start);
+        assert_eq!(s1_end, connector);
+
+        assert_eq!(data_to_geojson(&data, 2)["features"][0]["geometry"]["coordinates"][0], json!([-122.31, 47.6]));
+    }
+}
@@ -1,2 +1,18 @@
+pub mod clip;
+pub mod conditions;
+pub mod edge_list;
+pub mod error;
+pub mod geojson;
+pub mod mapped;
+pub mod mapping;
+pub mod metrics;
+pub mod output;
+pub mod precheck;
+pub mod profile;
+pub mod restrictions;
+pub mod sources;
+pub mod strings;
 pub mod writer;
 pub mod valhalla_sys;
+pub mod verify;
+pub mod wkb;
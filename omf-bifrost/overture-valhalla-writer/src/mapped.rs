@@ -0,0 +1,200 @@
+// A segment with all its Overture attributes mapped onto Valhalla way attributes, ready to export
+use std::collections::HashMap;
+
+use crate::mapping::{
+    apply_access_restrictions, bicycle_comfort, map_cycle_lane, map_road_class, map_speed_limits, map_surface_or_default,
+    map_use, DirectionalSpeed, MIN_RIDEABLE_BICYCLE_COMFORT, ValhallaAccess, ValhallaCycleLane, ValhallaRoadClass, ValhallaSurface, ValhallaUse,
+};
+use crate::writer::Segment;
+
+/// Valhalla way attributes of one segment, see `map_segment`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MappedSegment {
+    pub road_class: ValhallaRoadClass,
+    pub way_use: ValhallaUse,
+    pub surface: ValhallaSurface,
+    /// Speed limits relative to the segment's geometry order
+    pub speed: DirectionalSpeed,
+    pub access: ValhallaAccess,
+    pub cycle_lane: ValhallaCycleLane,
+    /// See `bicycle_comfort`, written as Valhalla's `dismount` flag, see `bicycle_dismount`
+    pub bicycle_comfort: u8,
+    /// Index of the segment name in `MappingContext::names`
+    pub name_index: u32,
+}
+
+impl MappedSegment {
+    /// Whether the way is written at all; segments closed to cars, pedestrians and bicycles are skipped
+    pub fn is_routable(&self) -> bool {
+        self.access.auto || self.access.pedestrian || self.access.bicycle
+    }
+
+    /// Whether cyclists have to push their bicycle, which Valhalla's bicycle costing penalizes
+    pub fn bicycle_dismount(&self) -> bool {
+        self.bicycle_comfort < MIN_RIDEABLE_BICYCLE_COMFORT
+    }
+}
+
+/// State shared by the segments of a conversion: the table of unique names, where index 0
+/// is the empty name as in Valhalla's `UniqueNames`
+#[derive(Debug)]
+pub struct MappingContext {
+    names: Vec<String>,
+    name_indices: HashMap<String, u32>,
+}
+
+impl Default for MappingContext {
+    fn default() -> Self {
+        Self { names: vec![String::new()], name_indices: HashMap::from([(String::new(), 0)]) }
+    }
+}
+
+impl MappingContext {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Index of `name` in the name table, adding it when first seen. Surrounding whitespace
+    /// is trimmed, so empty and whitespace-only names all share the unnamed index 0.
+    pub fn name_index(&mut self, name: &str) -> u32 {
+        let name = name.trim();
+        if let Some(index) = self.name_indices.get(name) {
+            return *index;
+        }
+        let index = self.names.len() as u32;
+        self.names.push(name.to_string());
+        self.name_indices.insert(name.to_string(), index);
+        index
+    }
+
+    /// Unique names in index order
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+}
+
+/// Access by Overture class: no cars on paths and rail, no pedestrians on motorways and cycleways,
+/// no bicycles on motorways, footways and steps (Valhalla's OSM defaults)
+fn class_access(road_class: &str) -> ValhallaAccess {
+    let mut access = ValhallaAccess::default();
+    access.pedestrian = !matches!(
+        road_class,
+        "motorway" | "trunk" | "cycleway" | "standard_gauge"
+    );
+    access.bicycle = !matches!(
+        road_class,
+        "motorway" | "footway" | "steps" | "pedestrian" | "standard_gauge"
+    );
+    access.auto = !matches!(
+        road_class,
+        "null" | "steps" | "path" | "living_street" | "pedestrian" | "footway" | "cycleway" | "standard_gauge"
+    );
+    access
+}
+
+/// Map the class, use, surface, speed limits, access and name of a segment. Access restrictions
+/// override the class defaults for the modes they name.
+pub fn map_segment(segment: &Segment, ctx: &mut MappingContext) -> MappedSegment {
+    let class = segment.road_class.as_deref().unwrap_or("null");
+    let subclass = segment.subclass.as_deref();
+    let road_class = map_road_class(class, subclass);
+    let way_use = map_use(segment.subtype.as_deref(), class, subclass);
+    let cycle_lane = map_cycle_lane(class, &segment.access_restrictions);
+
+    MappedSegment {
+        road_class,
+        way_use,
+        surface: map_surface_or_default(segment.surface.as_deref(), road_class, way_use),
+        speed: map_speed_limits(&segment.speed_limits),
+        access: apply_access_restrictions(class_access(class), &segment.access_restrictions),
+        cycle_lane,
+        bicycle_comfort: bicycle_comfort(segment.surface.as_deref(), cycle_lane),
+        name_index: ctx.name_index(&segment.name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use overture_types::{AccessRestriction, AccessWhen, Speed, SpeedLimit, SpeedLimitWhen};
+
+    use super::*;
+    use crate::mapping::NEUTRAL_BICYCLE_COMFORT;
+
+    fn segment(name: &str, class: &str, surface: Option<&str>, speed_limits: Vec<SpeedLimit>) -> Segment {
+        Segment {
+            id: format!("{}-id", name),
+            name: name.to_string(),
+            road_class: Some(class.to_string()),
+            subtype: Some("road".to_string()),
+            speed_limits,
+            surface: surface.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_map_segment() {
+        let mut ctx = MappingContext::new();
+        let speed_limits = vec![SpeedLimit {
+            max_speed: Some(Speed { value: 30.0, unit: "mph".to_string() }),
+            min_speed: None,
+            when: Some(SpeedLimitWhen { heading: Some("forward".to_string()) }),
+        }];
+
+        let mapped = map_segment(&segment("Main Street", "primary", Some("gravel"), speed_limits), &mut ctx);
+        assert_eq!(mapped.road_class, ValhallaRoadClass::Primary);
+        assert_eq!(mapped.way_use, ValhallaUse::Road);
+        assert_eq!(mapped.surface, ValhallaSurface::Gravel);
+        assert_eq!(mapped.speed, DirectionalSpeed { forward: Some(48), backward: None });
+        assert!(mapped.access.auto && mapped.access.pedestrian && mapped.access.bicycle);
+        assert_eq!(mapped.name_index, 1);
+        assert!(mapped.is_routable());
+
+        let footway = map_segment(&segment("", "footway", None, Vec::new()), &mut ctx);
+        assert_eq!(footway.way_use, ValhallaUse::Footway);
+        assert_eq!(footway.surface, ValhallaSurface::Path);
+        assert!(!footway.access.auto && footway.access.pedestrian && !footway.access.bicycle);
+        assert_eq!(footway.name_index, 0);
+
+        let rail = map_segment(&segment("Main Street", "standard_gauge", None, Vec::new()), &mut ctx);
+        assert!(!rail.is_routable());
+        assert_eq!(rail.name_index, 1);
+        assert_eq!(ctx.names(), ["", "Main Street"]);
+    }
+
+    #[test]
+    fn test_bicycle_comfort() {
+        let mut ctx = MappingContext::new();
+        let cycleway = map_segment(&segment("", "cycleway", Some("paved"), Vec::new()), &mut ctx);
+        let gravel_path = map_segment(&segment("", "path", Some("gravel"), Vec::new()), &mut ctx);
+        assert_eq!(cycleway.cycle_lane, ValhallaCycleLane::Separated);
+        assert_eq!(gravel_path.cycle_lane, ValhallaCycleLane::None);
+        assert!(cycleway.bicycle_comfort > gravel_path.bicycle_comfort);
+
+        let unknown = map_segment(&segment("", "residential", None, Vec::new()), &mut ctx);
+        assert_eq!(unknown.bicycle_comfort, NEUTRAL_BICYCLE_COMFORT);
+
+        let mut bike_lane = segment("", "residential", Some("asphalt"), Vec::new());
+        bike_lane.access_restrictions = vec![AccessRestriction {
+            access_type: "designated".to_string(),
+            when: Some(AccessWhen { vehicle: None, bicycle: Some(true), pedestrian: None }),
+        }];
+        let bike_lane = map_segment(&bike_lane, &mut ctx);
+        assert_eq!(bike_lane.cycle_lane, ValhallaCycleLane::Dedicated);
+        assert!(bike_lane.bicycle_comfort > map_segment(&segment("", "residential", Some("asphalt"), Vec::new()), &mut ctx).bicycle_comfort);
+
+        assert!(!gravel_path.bicycle_dismount());
+        // The "service" surface maps to impassable
+        assert!(map_segment(&segment("", "path", Some("service"), Vec::new()), &mut ctx).bicycle_dismount());
+    }
+
+    #[test]
+    fn test_blank_names_share_unnamed_index() {
+        let mut ctx = MappingContext::new();
+        assert_eq!(map_segment(&segment("", "residential", None, Vec::new()), &mut ctx).name_index, 0);
+        assert_eq!(map_segment(&segment("   ", "residential", None, Vec::new()), &mut ctx).name_index, 0);
+        assert_eq!(map_segment(&segment("Main St ", "residential", None, Vec::new()), &mut ctx).name_index, 1);
+        assert_eq!(map_segment(&segment("Main St", "residential", None, Vec::new()), &mut ctx).name_index, 1);
+        assert_eq!(ctx.names(), ["", "Main St"]);
+    }
+}
@@ -0,0 +1,609 @@
+// Mapping of Overture transportation attributes onto Valhalla way attributes.
+// The tables follow docs/rfc/01-overture-graph-tiles/valhalla-overture-attribute-mapping.md,
+// enum values match "graphconstants.h" in the Valhalla repo.
+
+use log::debug;
+use overture_types::{AccessRestriction, Speed, SpeedLimit};
+use serde::{Deserialize, Serialize};
+
+/// Valhalla `RoadClass` ("enum class RoadClass : uint8_t")
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ValhallaRoadClass {
+    Motorway = 0,
+    Trunk = 1,
+    Primary = 2,
+    Secondary = 3,
+    Tertiary = 4,
+    Unclassified = 5,
+    Residential = 6,
+    ServiceOther = 7,
+}
+
+/// Valhalla `Surface` ("enum class Surface : uint8_t")
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ValhallaSurface {
+    PavedSmooth = 0,
+    Paved = 1,
+    PavedRough = 2,
+    Compacted = 3,
+    Dirt = 4,
+    Gravel = 5,
+    Path = 6,
+    Impassable = 7,
+}
+
+/// Valhalla `CycleLane`: how a way's cycle lane is separated from other traffic
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ValhallaCycleLane {
+    None = 0,
+    Shared = 1,
+    Dedicated = 2,
+    Separated = 3,
+}
+
+/// Valhalla `Use` ("enum class Use : uint8_t"), limited to the values bifrost emits
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ValhallaUse {
+    Road = 0,
+    Track = 3,
+    Driveway = 4,
+    Alley = 5,
+    ParkingAisle = 6,
+    Cycleway = 20,
+    Sidewalk = 24,
+    Footway = 25,
+    Steps = 26,
+    Ferry = 41,
+    Rail = 50,
+}
+
+impl ValhallaUse {
+    pub fn is_ferry(self) -> bool {
+        self == ValhallaUse::Ferry
+    }
+
+    pub fn is_rail(self) -> bool {
+        self == ValhallaUse::Rail
+    }
+}
+
+/// Traffic control or barrier at a connector, carried on the Valhalla node
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ValhallaNodeKind {
+    /// Plain intersection without controls
+    #[default]
+    Intersection,
+    TrafficSignal,
+    StopSign,
+    Gate,
+}
+
+/// Map a connector subtype to the node kind, unknown or missing subtypes are plain intersections
+pub fn map_node_kind(subtype: Option<&str>) -> ValhallaNodeKind {
+    match subtype {
+        Some("traffic_signal" | "traffic_signals" | "signal") => ValhallaNodeKind::TrafficSignal,
+        Some("stop" | "stop_sign") => ValhallaNodeKind::StopSign,
+        Some("gate") => ValhallaNodeKind::Gate,
+        _ => ValhallaNodeKind::Intersection,
+    }
+}
+
+/// Strength of an Overture access rule, used to break conflicts: designated > denied > allowed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum AccessTier {
+    Allowed,
+    Denied,
+    Designated,
+}
+
+impl AccessTier {
+    fn from_access_type(access_type: &str) -> Option<Self> {
+        match access_type {
+            "allowed" => Some(AccessTier::Allowed),
+            "denied" => Some(AccessTier::Denied),
+            "designated" => Some(AccessTier::Designated),
+            _ => None,
+        }
+    }
+}
+
+/// Per-mode access of a way after applying Overture access restrictions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ValhallaAccess {
+    pub auto: bool,
+    pub pedestrian: bool,
+    pub bicycle: bool,
+
+    // Strongest rule that decided each mode so far, only needed while mapping
+    #[serde(skip)]
+    auto_set_by: Option<AccessTier>,
+    #[serde(skip)]
+    pedestrian_set_by: Option<AccessTier>,
+    #[serde(skip)]
+    bicycle_set_by: Option<AccessTier>,
+}
+
+impl Default for ValhallaAccess {
+    /// All modes are allowed unless explicitly restricted
+    fn default() -> Self {
+        Self {
+            auto: true,
+            pedestrian: true,
+            bicycle: true,
+            auto_set_by: None,
+            pedestrian_set_by: None,
+            bicycle_set_by: None,
+        }
+    }
+}
+
+impl ValhallaAccess {
+    fn apply(value: &mut bool, set_by: &mut Option<AccessTier>, allowed: bool, tier: AccessTier) {
+        if set_by.is_none_or(|current| tier >= current) {
+            *value = allowed;
+            *set_by = Some(tier);
+        }
+    }
+}
+
+/// Overture subclasses that mark minor service and footway roads
+const MINOR_ROAD_SUBCLASSES: &[&str] = &["parking_aisle", "driveway", "alley", "sidewalk", "crosswalk"];
+
+/// Map the Overture class to a Valhalla road class; a minor-road `subclass` (parking aisle,
+/// driveway, sidewalk...) always demotes to `ServiceOther`, other subclasses such as `link` keep the class
+pub fn map_road_class(class: &str, subclass: Option<&str>) -> ValhallaRoadClass {
+    if subclass.is_some_and(|subclass| MINOR_ROAD_SUBCLASSES.contains(&subclass)) {
+        return ValhallaRoadClass::ServiceOther;
+    }
+
+    match class {
+        "motorway" => ValhallaRoadClass::Motorway,
+        "trunk" => ValhallaRoadClass::Trunk,
+        "primary" => ValhallaRoadClass::Primary,
+        "secondary" => ValhallaRoadClass::Secondary,
+        "tertiary" => ValhallaRoadClass::Tertiary,
+        "residential" => ValhallaRoadClass::Residential,
+        "unclassified" => ValhallaRoadClass::Unclassified,
+        _ => ValhallaRoadClass::ServiceOther,
+    }
+}
+
+/// Pick the Valhalla `Use` from the Overture subtype (rail, water) and class, per the RFC "Road Types" list.
+/// A `subclass` refines the class when present, e.g. `service` with `subclass=driveway` becomes `Driveway`.
+pub fn map_use(subtype: Option<&str>, class: &str, subclass: Option<&str>) -> ValhallaUse {
+    let class = match subclass {
+        Some(subclass @ ("parking_aisle" | "driveway" | "alley" | "sidewalk")) => subclass,
+        _ => class,
+    };
+
+    match (subtype, class) {
+        (Some("rail"), _) => ValhallaUse::Rail,
+        (Some("water"), _) | (_, "ferry") => ValhallaUse::Ferry,
+        (_, "track") => ValhallaUse::Track,
+        (_, "driveway") => ValhallaUse::Driveway,
+        (_, "alley") => ValhallaUse::Alley,
+        (_, "parking_aisle") => ValhallaUse::ParkingAisle,
+        (_, "cycleway") => ValhallaUse::Cycleway,
+        (_, "sidewalk") => ValhallaUse::Sidewalk,
+        (_, "footway" | "path" | "living_street") => ValhallaUse::Footway,
+        (_, "steps") => ValhallaUse::Steps,
+        _ => ValhallaUse::Road,
+    }
+}
+
+/// Valhalla surface of a known Overture surface value
+fn known_surface(surface: &str) -> Option<ValhallaSurface> {
+    match surface {
+        "metal" | "rubber" => Some(ValhallaSurface::PavedSmooth),
+        "paved" | "asphalt" => Some(ValhallaSurface::Paved),
+        "bricks" | "wood" => Some(ValhallaSurface::PavedRough),
+        "paving_stones" | "cobblestone" | "tiles" => Some(ValhallaSurface::Compacted),
+        "dirt" | "unpaved" => Some(ValhallaSurface::Dirt),
+        "gravel" | "shells" | "rock" => Some(ValhallaSurface::Gravel),
+        "service" => Some(ValhallaSurface::Impassable),
+        _ => None,
+    }
+}
+
+pub fn map_surface_type(surface: Option<&str>) -> ValhallaSurface {
+    surface.and_then(known_surface).unwrap_or(ValhallaSurface::Path)
+}
+
+/// Cycle lane of a segment: cycleways are separated from traffic, other ways with a
+/// designated bicycle access rule get a dedicated lane
+pub fn map_cycle_lane(class: &str, restrictions: &[AccessRestriction]) -> ValhallaCycleLane {
+    if class == "cycleway" {
+        return ValhallaCycleLane::Separated;
+    }
+    let designated_for_bicycles = restrictions.iter().any(|restriction| {
+        AccessTier::from_access_type(&restriction.access_type) == Some(AccessTier::Designated)
+            && restriction.when.as_ref().is_some_and(|when| when.bicycle == Some(true))
+    });
+    if designated_for_bicycles {
+        ValhallaCycleLane::Dedicated
+    } else {
+        ValhallaCycleLane::None
+    }
+}
+
+/// Bicycle comfort of a way without a known surface or a cycle lane
+pub const NEUTRAL_BICYCLE_COMFORT: u8 = 50;
+
+/// Lowest bicycle comfort a way can be ridden at, below it cyclists have to dismount
+pub const MIN_RIDEABLE_BICYCLE_COMFORT: u8 = 10;
+
+/// Bicycle comfort from 0 (unrideable) to 100: smooth surfaces score higher than loose ones
+/// and a cycle lane adds more the better it is separated. An unknown surface is neutral.
+pub fn bicycle_comfort(surface: Option<&str>, cycle_lane: ValhallaCycleLane) -> u8 {
+    let surface_comfort = match surface.and_then(known_surface) {
+        Some(ValhallaSurface::PavedSmooth) => 70,
+        Some(ValhallaSurface::Paved) => 60,
+        Some(ValhallaSurface::PavedRough) => 45,
+        Some(ValhallaSurface::Compacted) => 40,
+        Some(ValhallaSurface::Dirt) => 25,
+        Some(ValhallaSurface::Gravel) | Some(ValhallaSurface::Path) => 20,
+        Some(ValhallaSurface::Impassable) => return 0,
+        None => NEUTRAL_BICYCLE_COMFORT,
+    };
+    let lane_comfort = match cycle_lane {
+        ValhallaCycleLane::None => 0,
+        ValhallaCycleLane::Shared => 5,
+        ValhallaCycleLane::Dedicated => 15,
+        ValhallaCycleLane::Separated => 25,
+    };
+    (surface_comfort + lane_comfort).min(100)
+}
+
+/// Surface assumed when Overture gives none: tracks are compacted, footways and paths
+/// unpaved paths, every other road and way is paved
+pub fn default_surface(road_class: ValhallaRoadClass, way_use: ValhallaUse) -> ValhallaSurface {
+    match (way_use, road_class) {
+        (ValhallaUse::Track, _) => ValhallaSurface::Compacted,
+        (ValhallaUse::Footway, _) => ValhallaSurface::Path,
+        _ => ValhallaSurface::Paved,
+    }
+}
+
+/// Like `map_surface_type`, but a missing or unknown surface falls back to `default_surface`
+/// for the road class and use. Known Overture surfaces always win.
+pub fn map_surface_or_default(
+    surface: Option<&str>,
+    road_class: ValhallaRoadClass,
+    way_use: ValhallaUse,
+) -> ValhallaSurface {
+    surface
+        .and_then(known_surface)
+        .unwrap_or_else(|| default_surface(road_class, way_use))
+}
+
+/// Resolve Overture access restrictions into per-mode access. A rule without `when` modes
+/// applies to every mode; a designated rule also denies the modes it does not name.
+pub fn map_access_restrictions(restrictions: &[AccessRestriction]) -> ValhallaAccess {
+    apply_access_restrictions(ValhallaAccess::default(), restrictions)
+}
+
+/// Resolve access restrictions on top of `access`, e.g. the defaults of the road class.
+/// Modes no restriction decides keep their value from `access`.
+pub fn apply_access_restrictions(mut access: ValhallaAccess, restrictions: &[AccessRestriction]) -> ValhallaAccess {
+    for restriction in restrictions {
+        let Some(tier) = AccessTier::from_access_type(&restriction.access_type) else {
+            debug!("Ignoring unsupported access type '{}'", restriction.access_type);
+            continue;
+        };
+        let allowed = tier != AccessTier::Denied;
+
+        let (auto, bicycle, pedestrian) = match &restriction.when {
+            Some(when) => (
+                when.vehicle.unwrap_or(false),
+                when.bicycle.unwrap_or(false),
+                when.pedestrian.unwrap_or(false),
+            ),
+            None => (true, true, true),
+        };
+
+        let modes = [
+            (auto, &mut access.auto, &mut access.auto_set_by),
+            (bicycle, &mut access.bicycle, &mut access.bicycle_set_by),
+            (pedestrian, &mut access.pedestrian, &mut access.pedestrian_set_by),
+        ];
+        for (named, value, set_by) in modes {
+            if named {
+                ValhallaAccess::apply(value, set_by, allowed, tier);
+            } else if tier == AccessTier::Designated {
+                ValhallaAccess::apply(value, set_by, false, AccessTier::Denied);
+            }
+        }
+    }
+
+    access
+}
+
+/// Speed limits of a way in km/h, `forward` and `backward` relative to the way's node order
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DirectionalSpeed {
+    pub forward: Option<u8>,
+    pub backward: Option<u8>,
+}
+
+impl DirectionalSpeed {
+    /// The same limits for a way with the nodes in reverse order
+    pub fn reversed(self) -> Self {
+        Self { forward: self.backward, backward: self.forward }
+    }
+
+    /// Highest limit in either direction
+    pub fn max(self) -> Option<u8> {
+        self.forward.max(self.backward)
+    }
+}
+
+const KPH_PER_MPH: f64 = 1.609344;
+
+/// Convert an Overture speed to whole km/h, saturating at the 255 km/h Valhalla can store
+fn speed_to_kph(speed: &Speed) -> u8 {
+    let kph = match speed.unit.as_str() {
+        "mph" => speed.value * KPH_PER_MPH,
+        _ => speed.value,
+    };
+    kph.round().clamp(0.0, u8::MAX as f64) as u8
+}
+
+/// Resolve Overture max speed limits into forward and backward limits. A limit with a `forward`
+/// or `backward` heading overrides the undirected limit for that direction; without directional
+/// limits both directions get the single value.
+pub fn map_speed_limits(limits: &[SpeedLimit]) -> DirectionalSpeed {
+    let mut undirected = None;
+    let mut forward = None;
+    let mut backward = None;
+
+    for limit in limits {
+        let Some(max_speed) = &limit.max_speed else {
+            continue;
+        };
+        let kph = Some(speed_to_kph(max_speed));
+        match limit.when.as_ref().and_then(|when| when.heading.as_deref()) {
+            Some("forward") => forward = forward.or(kph),
+            Some("backward") => backward = backward.or(kph),
+            Some(heading) => debug!("Ignoring speed limit with unsupported heading '{}'", heading),
+            None => undirected = undirected.or(kph),
+        }
+    }
+
+    DirectionalSpeed {
+        forward: forward.or(undirected),
+        backward: backward.or(undirected),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use overture_types::{AccessWhen, SpeedLimitWhen};
+
+    use super::*;
+
+    fn restriction(access_type: &str, when: Option<AccessWhen>) -> AccessRestriction {
+        AccessRestriction {
+            access_type: access_type.to_string(),
+            when,
+        }
+    }
+
+    fn when_modes(vehicle: bool, bicycle: bool, pedestrian: bool) -> Option<AccessWhen> {
+        Some(AccessWhen {
+            vehicle: Some(vehicle),
+            bicycle: Some(bicycle),
+            pedestrian: Some(pedestrian),
+        })
+    }
+
+    #[test]
+    fn test_road_class_serde_roundtrip() {
+        use ValhallaRoadClass::*;
+        for class in [
+            Motorway,
+            Trunk,
+            Primary,
+            Secondary,
+            Tertiary,
+            Unclassified,
+            Residential,
+            ServiceOther,
+        ] {
+            let json = serde_json::to_string(&class).unwrap();
+            assert_eq!(serde_json::from_str::<ValhallaRoadClass>(&json).unwrap(), class);
+        }
+        assert_eq!(serde_json::to_string(&ServiceOther).unwrap(), "\"service_other\"");
+    }
+
+    #[test]
+    fn test_surface_serde_roundtrip() {
+        use ValhallaSurface::*;
+        for surface in [
+            PavedSmooth,
+            Paved,
+            PavedRough,
+            Compacted,
+            Dirt,
+            Gravel,
+            Path,
+            Impassable,
+        ] {
+            let json = serde_json::to_string(&surface).unwrap();
+            assert_eq!(serde_json::from_str::<ValhallaSurface>(&json).unwrap(), surface);
+        }
+        assert_eq!(serde_json::to_string(&PavedSmooth).unwrap(), "\"paved_smooth\"");
+    }
+
+    #[test]
+    fn test_access_serde_roundtrip() {
+        let access = map_access_restrictions(&[restriction("denied", when_modes(true, false, false))]);
+        let json = serde_json::to_string(&access).unwrap();
+        let loaded: ValhallaAccess = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.auto, access.auto);
+        assert_eq!(loaded.pedestrian, access.pedestrian);
+        assert_eq!(loaded.bicycle, access.bicycle);
+    }
+
+    #[test]
+    fn test_access_serialization_omits_private_fields() {
+        let access = map_access_restrictions(&[restriction("denied", None)]);
+        let value = serde_json::to_value(access).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({"auto": false, "pedestrian": false, "bicycle": false})
+        );
+    }
+
+    #[test]
+    fn test_map_road_class() {
+        assert_eq!(map_road_class("motorway", None), ValhallaRoadClass::Motorway);
+        assert_eq!(map_road_class("unclassified", None), ValhallaRoadClass::Unclassified);
+        assert_eq!(map_road_class("footway", None), ValhallaRoadClass::ServiceOther);
+        assert_eq!(map_road_class("unknown", None), ValhallaRoadClass::ServiceOther);
+    }
+
+    #[test]
+    fn test_service_subclass_refines_use() {
+        let parking_aisle = map_use(Some("road"), "service", Some("parking_aisle"));
+        let driveway = map_use(Some("road"), "service", Some("driveway"));
+        assert_eq!(parking_aisle, ValhallaUse::ParkingAisle);
+        assert_eq!(driveway, ValhallaUse::Driveway);
+        assert_ne!(parking_aisle, driveway);
+
+        // Without a subclass the class decides
+        assert_eq!(map_use(Some("road"), "service", None), ValhallaUse::Road);
+        assert_eq!(map_use(Some("road"), "footway", Some("sidewalk")), ValhallaUse::Sidewalk);
+        assert_eq!(map_use(Some("road"), "motorway", Some("link")), ValhallaUse::Road);
+    }
+
+    #[test]
+    fn test_subclass_refines_road_class() {
+        assert_eq!(map_road_class("service", Some("parking_aisle")), ValhallaRoadClass::ServiceOther);
+        assert_eq!(map_road_class("residential", Some("alley")), ValhallaRoadClass::ServiceOther);
+        assert_eq!(map_road_class("motorway", Some("link")), ValhallaRoadClass::Motorway);
+        assert_eq!(map_road_class("primary", None), ValhallaRoadClass::Primary);
+    }
+
+    #[test]
+    fn test_use_serde_roundtrip() {
+        for way_use in [ValhallaUse::Road, ValhallaUse::Ferry, ValhallaUse::Rail] {
+            let json = serde_json::to_string(&way_use).unwrap();
+            assert_eq!(serde_json::from_str::<ValhallaUse>(&json).unwrap(), way_use);
+        }
+        assert_eq!(serde_json::to_string(&ValhallaUse::ParkingAisle).unwrap(), "\"parking_aisle\"");
+    }
+
+    #[test]
+    fn test_map_use_ferry_and_rail() {
+        let ferry = map_use(Some("water"), "unknown", None);
+        assert_eq!(ferry, ValhallaUse::Ferry);
+        assert_eq!(ferry as u8, 41);
+        assert!(ferry.is_ferry());
+        assert_eq!(map_use(Some("road"), "ferry", None), ValhallaUse::Ferry);
+
+        let rail = map_use(Some("rail"), "standard_gauge", None);
+        assert_eq!(rail, ValhallaUse::Rail);
+        assert_eq!(rail as u8, 50);
+        assert!(rail.is_rail());
+    }
+
+    #[test]
+    fn test_map_use_roads() {
+        assert_eq!(map_use(Some("road"), "residential", None), ValhallaUse::Road);
+        assert_eq!(map_use(Some("road"), "footway", None), ValhallaUse::Footway);
+        assert_eq!(map_use(Some("road"), "living_street", None), ValhallaUse::Footway);
+        assert_eq!(map_use(None, "parking_aisle", None), ValhallaUse::ParkingAisle);
+        assert_eq!(map_use(None, "null", None), ValhallaUse::Road);
+    }
+
+    #[test]
+    fn test_map_node_kind() {
+        assert_eq!(map_node_kind(Some("traffic_signals")), ValhallaNodeKind::TrafficSignal);
+        assert_eq!(map_node_kind(Some("stop")), ValhallaNodeKind::StopSign);
+        assert_eq!(map_node_kind(Some("gate")), ValhallaNodeKind::Gate);
+        assert_eq!(map_node_kind(Some("crossing")), ValhallaNodeKind::Intersection);
+        assert_eq!(map_node_kind(None), ValhallaNodeKind::Intersection);
+    }
+
+    #[test]
+    fn test_map_surface_type() {
+        assert_eq!(map_surface_type(Some("asphalt")), ValhallaSurface::Paved);
+        assert_eq!(map_surface_type(Some("cobblestone")), ValhallaSurface::Compacted);
+        assert_eq!(map_surface_type(Some("shells")), ValhallaSurface::Gravel);
+        assert_eq!(map_surface_type(Some("something")), ValhallaSurface::Path);
+        assert_eq!(map_surface_type(None), ValhallaSurface::Path);
+    }
+
+    #[test]
+    fn test_surface_defaults_by_road_class() {
+        let motorway = map_road_class("motorway", None);
+        let motorway_use = map_use(Some("road"), "motorway", None);
+        assert_eq!(map_surface_or_default(None, motorway, motorway_use), ValhallaSurface::Paved);
+        assert_eq!(map_surface_or_default(Some("something"), motorway, motorway_use), ValhallaSurface::Paved);
+
+        let track = map_road_class("track", None);
+        let track_use = map_use(Some("road"), "track", None);
+        assert_eq!(map_surface_or_default(None, track, track_use), ValhallaSurface::Compacted);
+
+        // Explicit surfaces stay authoritative
+        assert_eq!(map_surface_or_default(Some("gravel"), motorway, motorway_use), ValhallaSurface::Gravel);
+        assert_eq!(map_surface_or_default(Some("asphalt"), track, track_use), ValhallaSurface::Paved);
+    }
+
+    #[test]
+    fn test_map_access_restrictions_precedence() {
+        // Designated beats a later denied rule for the same mode
+        let access = map_access_restrictions(&[
+            restriction("designated", when_modes(false, true, false)),
+            restriction("denied", when_modes(false, true, false)),
+        ]);
+        assert!(access.bicycle);
+        assert!(!access.auto);
+        assert!(!access.pedestrian);
+
+        // Denied beats a later allowed rule
+        let access = map_access_restrictions(&[
+            restriction("denied", None),
+            restriction("allowed", when_modes(false, false, true)),
+        ]);
+        assert!(!access.pedestrian);
+
+        // Unknown access types are ignored
+        let access = map_access_restrictions(&[restriction("maybe", None)]);
+        assert_eq!(access, ValhallaAccess::default());
+    }
+
+    fn speed_limit(value: f64, unit: &str, heading: Option<&str>) -> SpeedLimit {
+        SpeedLimit {
+            max_speed: Some(Speed { value, unit: unit.to_string() }),
+            min_speed: None,
+            when: heading.map(|heading| SpeedLimitWhen { heading: Some(heading.to_string()) }),
+        }
+    }
+
+    #[test]
+    fn test_map_speed_limits_directional() {
+        let speed = map_speed_limits(&[
+            speed_limit(50.0, "km/h", None),
+            speed_limit(30.0, "km/h", Some("backward")),
+        ]);
+        assert_eq!(speed, DirectionalSpeed { forward: Some(50), backward: Some(30) });
+        assert_eq!(speed.reversed(), DirectionalSpeed { forward: Some(30), backward: Some(50) });
+        assert_eq!(speed.max(), Some(50));
+
+        // A single limit applies to both directions, mph is converted
+        let speed = map_speed_limits(&[speed_limit(25.0, "mph", None)]);
+        assert_eq!(speed, DirectionalSpeed { forward: Some(40), backward: Some(40) });
+
+        assert_eq!(map_speed_limits(&[]), DirectionalSpeed::default());
+    }
+}
@@ -0,0 +1,125 @@
+// Tallies of the mapping decisions taken during a conversion, to tune the mapping tables
+use std::collections::BTreeMap;
+use std::fs::write;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::mapping::{ValhallaAccess, ValhallaRoadClass, ValhallaSurface};
+
+/// Number of segments each travel mode was denied on
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccessDenied {
+    pub auto: u64,
+    pub pedestrian: u64,
+    pub bicycle: u64,
+}
+
+/// Number of routable segments without connectors per `ConnectorlessPolicy` outcome
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConnectorlessOutcomes {
+    pub synthesized: u64,
+    pub skipped: u64,
+}
+
+/// Segment counts per mapped road class and surface, and per denied access mode
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MappingMetrics {
+    pub segments: u64,
+    pub road_classes: BTreeMap<ValhallaRoadClass, u64>,
+    pub surfaces: BTreeMap<ValhallaSurface, u64>,
+    pub access_denied: AccessDenied,
+    #[serde(default)]
+    pub connectorless: ConnectorlessOutcomes,
+}
+
+impl MappingMetrics {
+    /// Count the mapping outcome of one segment
+    pub fn record(&mut self, road_class: ValhallaRoadClass, surface: ValhallaSurface, access: &ValhallaAccess) {
+        self.segments += 1;
+        *self.road_classes.entry(road_class).or_default() += 1;
+        *self.surfaces.entry(surface).or_default() += 1;
+        self.access_denied.auto += u64::from(!access.auto);
+        self.access_denied.pedestrian += u64::from(!access.pedestrian);
+        self.access_denied.bicycle += u64::from(!access.bicycle);
+    }
+
+    /// Human readable summary, one line per tally
+    pub fn summary(&self) -> String {
+        let mut summary = format!("Mapped {} segments\n", self.segments);
+        summary.push_str("Road classes:\n");
+        for (road_class, count) in &self.road_classes {
+            summary.push_str(&format!("  {:?}: {}\n", road_class, count));
+        }
+        summary.push_str("Surfaces:\n");
+        for (surface, count) in &self.surfaces {
+            summary.push_str(&format!("  {:?}: {}\n", surface, count));
+        }
+        summary.push_str("Access denied:\n");
+        summary.push_str(&format!("  auto: {}\n", self.access_denied.auto));
+        summary.push_str(&format!("  pedestrian: {}\n", self.access_denied.pedestrian));
+        summary.push_str(&format!("  bicycle: {}\n", self.access_denied.bicycle));
+        summary.push_str("Connectorless segments:\n");
+        summary.push_str(&format!("  synthesized: {}\n", self.connectorless.synthesized));
+        summary.push_str(&format!("  skipped: {}\n", self.connectorless.skipped));
+        summary
+    }
+
+    /// Write the tallies as pretty-printed JSON
+    pub fn write_json(&self, path: &Path) -> std::io::Result<()> {
+        write(path, serde_json::to_string_pretty(self)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::read_to_string;
+
+    use tempfile::tempdir;
+
+    use super::*;
+
+    fn access(auto: bool, pedestrian: bool) -> ValhallaAccess {
+        let mut access = ValhallaAccess::default();
+        access.auto = auto;
+        access.pedestrian = pedestrian;
+        access
+    }
+
+    #[test]
+    fn test_mapping_metrics_tallies() {
+        let mut metrics = MappingMetrics::default();
+        metrics.record(ValhallaRoadClass::Motorway, ValhallaSurface::Paved, &access(true, false));
+        metrics.record(ValhallaRoadClass::Residential, ValhallaSurface::Paved, &access(true, true));
+        metrics.record(ValhallaRoadClass::Residential, ValhallaSurface::Gravel, &access(true, true));
+        metrics.record(ValhallaRoadClass::ServiceOther, ValhallaSurface::Path, &access(false, true));
+
+        assert_eq!(metrics.segments, 4);
+        assert_eq!(
+            metrics.road_classes,
+            BTreeMap::from([
+                (ValhallaRoadClass::Motorway, 1),
+                (ValhallaRoadClass::Residential, 2),
+                (ValhallaRoadClass::ServiceOther, 1),
+            ])
+        );
+        assert_eq!(
+            metrics.surfaces,
+            BTreeMap::from([
+                (ValhallaSurface::Paved, 2),
+                (ValhallaSurface::Gravel, 1),
+                (ValhallaSurface::Path, 1),
+            ])
+        );
+        assert_eq!(metrics.access_denied, AccessDenied { auto: 1, pedestrian: 1, bicycle: 0 });
+        assert!(metrics.summary().contains("  Residential: 2\n"));
+
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("metrics.json");
+        metrics.write_json(&path).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(json["road_classes"]["residential"], 2);
+        assert_eq!(json["surfaces"]["gravel"], 1);
+        assert_eq!(json["access_denied"]["auto"], 1);
+    }
+}
@@ -0,0 +1,101 @@
+// Deterministic merging of per-segment conversion output
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use crate::valhalla_sys::{OsmWay, OsmWayNode};
+
+/// Ways and way nodes produced for one segment. The nodes' `way_index` is local,
+/// i.e. relative to this segment's `ways`.
+#[derive(Debug, Default)]
+pub struct SegmentOutput {
+    pub ways: Vec<OsmWay>,
+    pub way_nodes: Vec<OsmWayNode>,
+}
+
+/// Collects the output of conversion workers keyed by the original segment index, so
+/// the final `ways.bin`/`way_nodes.bin` order does not depend on which worker finishes first
+#[derive(Debug, Default)]
+pub struct ConversionOutput {
+    parts: Mutex<BTreeMap<usize, SegmentOutput>>,
+}
+
+impl ConversionOutput {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the output for a segment, replacing any earlier output for the same index
+    pub fn append(&self, segment_index: usize, output: SegmentOutput) {
+        self.parts
+            .lock()
+            .expect("conversion output lock poisoned")
+            .insert(segment_index, output);
+    }
+
+    /// Concatenate all outputs in segment order, rebasing local way indices onto the merged ways
+    pub fn finish(self) -> (Vec<OsmWay>, Vec<OsmWayNode>) {
+        let parts = self.parts.into_inner().expect("conversion output lock poisoned");
+
+        let mut ways = Vec::new();
+        let mut way_nodes = Vec::new();
+        for (_, part) in parts {
+            let way_offset = ways.len() as u32;
+            ways.extend(part.ways);
+            way_nodes.extend(part.way_nodes.into_iter().map(|mut way_node| {
+                way_node.set_way_index(way_offset + way_node.way_index());
+                way_node
+            }));
+        }
+
+        (ways, way_nodes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    fn segment_output(segment_index: usize) -> SegmentOutput {
+        let osmid = segment_index as u64 + 1;
+        SegmentOutput {
+            ways: vec![OsmWay::new(osmid, 1, 2, true, true)],
+            way_nodes: vec![
+                OsmWayNode::new(0, 0, osmid * 10, -122.3, 47.6, 1),
+                OsmWayNode::new(0, 1, osmid * 10 + 1, -122.31, 47.61, 1),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_out_of_order_appends_yield_index_order() {
+        let output = ConversionOutput::new();
+        for segment_index in [2, 0, 1] {
+            output.append(segment_index, segment_output(segment_index));
+        }
+
+        let (ways, way_nodes) = output.finish();
+        let osmids: Vec<u64> = ways.iter().map(OsmWay::osmid).collect();
+        assert_eq!(osmids, vec![1, 2, 3]);
+        let way_indices: Vec<u32> = way_nodes.iter().map(OsmWayNode::way_index).collect();
+        assert_eq!(way_indices, vec![0, 0, 1, 1, 2, 2]);
+    }
+
+    #[test]
+    fn test_parallel_appends() {
+        let output = ConversionOutput::new();
+        thread::scope(|scope| {
+            for segment_index in (0..8).rev() {
+                let output = &output;
+                scope.spawn(move || output.append(segment_index, segment_output(segment_index)));
+            }
+        });
+
+        let (ways, way_nodes) = output.finish();
+        let osmids: Vec<u64> = ways.iter().map(OsmWay::osmid).collect();
+        assert_eq!(osmids, (1..=8).collect::<Vec<u64>>());
+        assert_eq!(way_nodes.len(), 16);
+        assert_eq!(way_nodes[15].way_index(), 7);
+    }
+}
@@ -0,0 +1,95 @@
+// Reference integrity scan run before the conversion starts
+use std::collections::HashSet;
+
+use crate::writer::Data;
+
+/// Number of dangling references listed in the report
+pub const MAX_REPORTED_DANGLING_REFS: usize = 10;
+
+/// Outcome of `precheck_connector_refs`
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ConnectorRefReport {
+    /// Connector references over all segments
+    pub connector_refs: usize,
+    /// References to connectors missing from the connector data
+    pub dangling_refs: usize,
+    /// First dangling references as `(segment_id, connector_id)`
+    pub examples: Vec<(String, String)>,
+}
+
+impl ConnectorRefReport {
+    pub fn is_clean(&self) -> bool {
+        self.dangling_refs == 0
+    }
+}
+
+/// Count the segment connector references that point to a connector missing from `data.connectors`
+pub fn precheck_connector_refs(data: &Data) -> ConnectorRefReport {
+    let connector_ids: HashSet<&str> = data.connectors.iter().map(|connector| connector.id.as_str()).collect();
+
+    let mut report = ConnectorRefReport::default();
+    for segment in &data.segments {
+        for connector_ref in &segment.connectors {
+            report.connector_refs += 1;
+            if connector_ids.contains(connector_ref.id.as_str()) {
+                continue;
+            }
+            report.dangling_refs += 1;
+            if report.examples.len() < MAX_REPORTED_DANGLING_REFS {
+                report.examples.push((segment.id.clone(), connector_ref.id.clone()));
+            }
+        }
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use overture_types::AlongFraction;
+
+    use super::*;
+    use crate::writer::{Connector, ConnectorRef, Segment};
+
+    fn segment(id: &str, connector_ids: &[&str]) -> Segment {
+        Segment {
+            id: id.to_string(),
+            name: id.to_string(),
+            road_class: Some("residential".to_string()),
+            subtype: Some("road".to_string()),
+            connectors: connector_ids
+                .iter()
+                .map(|id| ConnectorRef { id: id.to_string(), at: AlongFraction::START, coordinate: None })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    fn connector(id: &str) -> Connector {
+        Connector { id: id.to_string(), ..Default::default() }
+    }
+
+    #[test]
+    fn test_precheck_counts_dangling_refs() {
+        let data = Data {
+            segments: vec![segment("s1", &["c1", "c2"]), segment("s2", &["c2", "missing1"]), segment("s3", &["missing2", "missing1"])],
+            connectors: vec![connector("c1"), connector("c2")],
+        };
+
+        let report = precheck_connector_refs(&data);
+        assert_eq!(report.connector_refs, 6);
+        assert_eq!(report.dangling_refs, 3);
+        assert!(!report.is_clean());
+        assert_eq!(
+            report.examples,
+            vec![
+                ("s2".to_string(), "missing1".to_string()),
+                ("s3".to_string(), "missing2".to_string()),
+                ("s3".to_string(), "missing1".to_string()),
+            ]
+        );
+
+        let data = Data { segments: vec![segment("s1", &["c1"])], connectors: vec![connector("c1")] };
+        assert!(precheck_connector_refs(&data).is_clean());
+    }
+}
@@ -0,0 +1,78 @@
+// Wall-clock timings of pipeline stages, shown by `--profile`
+use std::time::{Duration, Instant};
+
+/// Durations of named stages in the order they first ran. Recording a stage again adds to
+/// its earlier duration.
+#[derive(Debug, Default, Clone)]
+pub struct StageTimings {
+    stages: Vec<(String, Duration)>,
+}
+
+impl StageTimings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&mut self, stage: &str, duration: Duration) {
+        match self.stages.iter_mut().find(|(name, _)| name == stage) {
+            Some((_, total)) => *total += duration,
+            None => self.stages.push((stage.to_string(), duration)),
+        }
+    }
+
+    /// Run `f` and record how long it took as `stage`
+    pub fn time<T>(&mut self, stage: &str, f: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let result = f();
+        self.record(stage, start.elapsed());
+        result
+    }
+
+    pub fn stages(&self) -> &[(String, Duration)] {
+        &self.stages
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stages.is_empty()
+    }
+
+    pub fn total(&self) -> Duration {
+        self.stages.iter().map(|(_, duration)| *duration).sum()
+    }
+
+    /// Table with one line per stage and the total, plus each stage's share of it
+    pub fn summary(&self) -> String {
+        let total = self.total();
+        let width = self.stages.iter().map(|(name, _)| name.len()).max().unwrap_or(0).max("total".len());
+        let mut summary = String::from("Stage timings:\n");
+        for (name, duration) in &self.stages {
+            let share = if total.is_zero() { 0.0 } else { duration.as_secs_f64() / total.as_secs_f64() * 100.0 };
+            summary.push_str(&format!("  {:<width$}  {:>12.3?}  {:>5.1}%\n", name, duration, share, width = width));
+        }
+        summary.push_str(&format!("  {:<width$}  {:>12.3?}\n", "total", total, width = width));
+        summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stages_aggregate_in_first_run_order() {
+        let mut timings = StageTimings::new();
+        timings.record("import", Duration::from_millis(300));
+        timings.record("export", Duration::from_millis(100));
+        timings.record("import", Duration::from_millis(200));
+        assert_eq!(timings.time("mapping", || 42), 42);
+
+        let names: Vec<&str> = timings.stages().iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, vec!["import", "export", "mapping"]);
+        assert_eq!(timings.stages()[0].1, Duration::from_millis(500));
+        assert!(timings.total() >= Duration::from_millis(600));
+
+        let summary = timings.summary();
+        assert!(summary.contains("import"), "{}", summary);
+        assert!(summary.lines().last().unwrap().trim_start().starts_with("total"), "{}", summary);
+    }
+}
@@ -0,0 +1,149 @@
+// Turn restrictions from Overture `prohibited_transitions`, split into unconditional
+// restrictions and time-dependent ones carrying a Valhalla style time domain
+use std::fs::write;
+use std::path::Path;
+
+use log::warn;
+use overture_types::ProhibitedTransition;
+use serde::Serialize;
+
+use crate::conditions::{parse_during, TimeCondition};
+use crate::writer::{Data, Segment};
+
+/// Sidecar file the restrictions are written to next to `ways.bin`
+pub const RESTRICTIONS_FILE: &str = "restrictions.json";
+
+/// A turn from `from_segment_id` through the `via_connector_ids` onto `to_segment_id` that may not be taken
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TurnRestriction {
+    pub from_segment_id: String,
+    /// Heading on the from segment the restriction applies to, both when absent
+    pub heading: Option<String>,
+    pub via_connector_ids: Vec<String>,
+    /// Segments passed between the first connector and `to_segment_id`
+    pub via_segment_ids: Vec<String>,
+    pub to_segment_id: String,
+    /// Travel modes restricted, all when empty
+    pub modes: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub condition: Option<TimeCondition>,
+}
+
+fn turn_restriction(segment: &Segment, transition: &ProhibitedTransition) -> Option<TurnRestriction> {
+    let (last, via) = transition.sequence.split_last()?;
+    let when = transition.when.clone().unwrap_or_default();
+
+    let condition = when.during.as_deref().and_then(|during| {
+        let condition = parse_during(during);
+        if condition.is_none() {
+            warn!(
+                "Segment '{}': cannot parse restriction condition '{}', emitting the restriction unconditionally",
+                segment.id, during
+            );
+        }
+        condition
+    });
+
+    Some(TurnRestriction {
+        from_segment_id: segment.id.clone(),
+        heading: when.heading,
+        via_connector_ids: transition.sequence.iter().map(|step| step.connector_id.clone()).collect(),
+        via_segment_ids: via.iter().map(|step| step.segment_id.clone()).collect(),
+        to_segment_id: last.segment_id.clone(),
+        modes: when.mode.unwrap_or_default(),
+        condition,
+    })
+}
+
+/// Turn restrictions of all segments, unconditional ones separate from time-dependent ones
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct RestrictionRecords {
+    pub restrictions: Vec<TurnRestriction>,
+    pub time_restrictions: Vec<TurnRestriction>,
+}
+
+impl RestrictionRecords {
+    pub fn is_empty(&self) -> bool {
+        self.restrictions.is_empty() && self.time_restrictions.is_empty()
+    }
+}
+
+/// Collect the prohibited transitions of all segments. Transitions with a condition that
+/// cannot be parsed are kept as unconditional restrictions.
+pub fn collect_restrictions(data: &Data) -> RestrictionRecords {
+    let mut records = RestrictionRecords::default();
+    for segment in &data.segments {
+        for transition in &segment.prohibited_transitions {
+            let Some(restriction) = turn_restriction(segment, transition) else {
+                warn!("Segment '{}' has a prohibited transition without a sequence", segment.id);
+                continue;
+            };
+            if restriction.condition.is_some() {
+                records.time_restrictions.push(restriction);
+            } else {
+                records.restrictions.push(restriction);
+            }
+        }
+    }
+    records
+}
+
+/// Write the restrictions as JSON with `restrictions` and `time_restrictions` arrays
+pub fn export_restrictions(records: &RestrictionRecords, path: &Path) -> std::io::Result<()> {
+    write(path, serde_json::to_string_pretty(records)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use overture_types::{TransitionStep, TransitionWhen};
+
+    use super::*;
+
+    fn segment_with_transitions(id: &str, prohibited_transitions: Vec<ProhibitedTransition>) -> Segment {
+        Segment {
+            id: id.to_string(),
+            name: id.to_string(),
+            road_class: Some("primary".to_string()),
+            subtype: Some("road".to_string()),
+            prohibited_transitions,
+            ..Default::default()
+        }
+    }
+
+    fn no_turn(to_segment_id: &str, during: Option<&str>) -> ProhibitedTransition {
+        ProhibitedTransition {
+            sequence: vec![TransitionStep { connector_id: "c1".to_string(), segment_id: to_segment_id.to_string() }],
+            final_heading: Some("forward".to_string()),
+            when: Some(TransitionWhen {
+                heading: Some("forward".to_string()),
+                during: during.map(str::to_string),
+                mode: Some(vec!["motor_vehicle".to_string()]),
+            }),
+        }
+    }
+
+    #[test]
+    fn test_time_conditional_no_turn() {
+        let data = Data {
+            segments: vec![segment_with_transitions(
+                "s1",
+                vec![no_turn("s2", Some("Mo-Fr 07:00-09:00")), no_turn("s3", None), no_turn("s4", Some("school hours"))],
+            )],
+            connectors: Vec::new(),
+        };
+
+        let records = collect_restrictions(&data);
+        assert_eq!(records.time_restrictions.len(), 1);
+        let timed = &records.time_restrictions[0];
+        assert_eq!(timed.from_segment_id, "s1");
+        assert_eq!(timed.via_connector_ids, vec!["c1"]);
+        assert_eq!(timed.to_segment_id, "s2");
+        assert_eq!(timed.modes, vec!["motor_vehicle"]);
+        assert_eq!(timed.condition, parse_during("Mo-Fr 07:00-09:00"));
+
+        // The unparseable condition falls back to an unconditional restriction
+        let to_segments: Vec<&str> = records.restrictions.iter().map(|r| r.to_segment_id.as_str()).collect();
+        assert_eq!(to_segments, vec!["s3", "s4"]);
+        assert!(records.restrictions.iter().all(|r| r.condition.is_none()));
+    }
+}
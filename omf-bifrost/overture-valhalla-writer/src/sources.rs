@@ -0,0 +1,38 @@
+// Overture `sources` provenance of the converted features, keyed by the way and node ids
+// written to `ways.bin`, so a Valhalla edge can be traced back to the dataset it came from
+use std::fs::write;
+use std::path::Path;
+
+use overture_types::Source;
+use serde::Serialize;
+
+/// Sidecar file the sources are written to next to `ways.bin`
+pub const SOURCES_FILE: &str = "sources.json";
+
+/// The ways written for one segment, with the segment's sources
+#[derive(Debug, Clone, Serialize)]
+pub struct WaySources {
+    pub way_ids: Vec<u64>,
+    pub segment_id: String,
+    pub sources: Vec<Source>,
+}
+
+/// The node written for a connector, with the connector's sources
+#[derive(Debug, Clone, Serialize)]
+pub struct NodeSources {
+    pub node_id: u64,
+    pub connector_id: String,
+    pub sources: Vec<Source>,
+}
+
+/// Every exported road, and the connectors that carry sources
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct SourceRecords {
+    pub ways: Vec<WaySources>,
+    pub nodes: Vec<NodeSources>,
+}
+
+/// Write the sources as JSON with `ways` and `nodes` arrays
+pub fn export_sources(records: &SourceRecords, path: &Path) -> std::io::Result<()> {
+    write(path, serde_json::to_string_pretty(records)?)
+}
@@ -0,0 +1,17 @@
+// The name table the `name_index` of every way written to `ways.bin` points into
+use std::fs::write;
+use std::path::Path;
+
+/// File the names are written to next to `ways.bin`
+pub const STRINGS_FILE: &str = "strings.bin";
+
+/// Write `names` in index order, each followed by a NUL byte. Index 0 is the empty name of
+/// unnamed ways.
+pub fn export_strings(names: &[String], path: &Path) -> std::io::Result<()> {
+    let mut bytes = Vec::with_capacity(names.iter().map(|name| name.len() + 1).sum());
+    for name in names {
+        bytes.extend_from_slice(name.as_bytes());
+        bytes.push(0);
+    }
+    write(path, bytes)
+}
@@ -1,9 +1,37 @@
+use crate::mapping::{
+    DirectionalSpeed, ValhallaCycleLane, ValhallaNodeKind, ValhallaRoadClass, ValhallaSurface, ValhallaUse,
+};
+
+/// "kGate" in Valhalla's "enum class NodeType : uint8_t"
+const NODE_TYPE_GATE: u32 = 1;
+
 fn encode_lat_lon(decoded_lat: f64, decoded_lon: f64) -> (u32, u32) {
     let encoded_lat = ((decoded_lat + 90.0) * 10f64.powi(7)) as u32;
     let encoded_lon = ((decoded_lon + 180.0) * 10f64.powi(7)) as u32;
     (encoded_lat, encoded_lon)
 }
 
+/// Inverse of `encode_lat_lon`, returns (lat, lon)
+pub fn decode_lat_lon(encoded_lat: u32, encoded_lon: u32) -> (f64, f64) {
+    let decoded_lat = encoded_lat as f64 / 10f64.powi(7) - 90.0;
+    let decoded_lon = encoded_lon as f64 / 10f64.powi(7) - 180.0;
+    (decoded_lat, decoded_lon)
+}
+
+/// Read back a slice written with `slice_as_bytes`; `None` if the length is not a
+/// multiple of the struct size
+fn structs_from_bytes<T>(bytes: &[u8]) -> Option<Vec<T>> {
+    let size = size_of::<T>();
+    if !bytes.len().is_multiple_of(size) {
+        return None;
+    }
+    let structs = bytes
+        .chunks_exact(size)
+        .map(|chunk| unsafe { std::ptr::read_unaligned(chunk.as_ptr() as *const T) })
+        .collect();
+    Some(structs)
+}
+
 #[expect(non_camel_case_types, non_upper_case_globals)]
 pub mod ffi {
     include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
@@ -16,9 +44,40 @@ pub struct OsmNode(ffi::OSMNode);
 impl OsmNode {
     pub fn slice_as_bytes(slice: &[Self]) -> &[u8] {
         let ptr = slice.as_ptr() as *const u8;
-        let size = size_of::<Self>() * slice.len();
+        let size = size_of_val(slice);
         unsafe { std::slice::from_raw_parts(ptr, size) }
     }
+
+    pub fn from_bytes(bytes: &[u8]) -> Option<Vec<Self>> {
+        structs_from_bytes(bytes)
+    }
+
+    /// Plain intersection node ("kStreetIntersection") without traffic controls
+    pub fn new(osmid: u64, lng: f64, lat: f64, intersection: u32) -> Self
+    {
+        let mut node = OsmNode::default();
+        node.0.osmid_ = osmid;
+
+        let (lat7, lng7) = encode_lat_lon(lat, lng);
+        node.0.lng7_ = lng7;
+        node.0.lat7_ = lat7;
+        node.0.set_intersection_(intersection);
+
+        // TODO: could also be 4095 ("kAllAccess")? See "graphconstants.h" in Valhalla
+        // TODO: get from Overture data
+        node.0.set_access_(2047);
+
+        node
+    }
+
+    /// Set the signal, stop sign and gate flags for the node kind, clearing the others.
+    /// There is no elevation setter: Valhalla's OSMNode has no elevation field and its tile
+    /// builder takes elevation from its own DEM tiles, so nodes are written without one.
+    pub fn set_node_kind(&mut self, kind: ValhallaNodeKind) {
+        self.0.set_traffic_signal_((kind == ValhallaNodeKind::TrafficSignal) as u64);
+        self.0.set_stop_sign_((kind == ValhallaNodeKind::StopSign) as u64);
+        self.0.set_type_(if kind == ValhallaNodeKind::Gate { NODE_TYPE_GATE } else { 0 });
+    }
 }
 
 #[repr(transparent)]
@@ -28,28 +87,45 @@ pub struct OsmWayNode(ffi::OSMWayNode);
 impl OsmWayNode {
     pub fn slice_as_bytes(slice: &[Self]) -> &[u8] {
         let ptr = slice.as_ptr() as *const u8;
-        let size = size_of::<Self>() * slice.len();
+        let size = size_of_val(slice);
         unsafe { std::slice::from_raw_parts(ptr, size) }
     }
 
+    pub fn from_bytes(bytes: &[u8]) -> Option<Vec<Self>> {
+        structs_from_bytes(bytes)
+    }
+
+    /// Decoded (lat, lon) of the node
+    pub fn lat_lon(&self) -> (f64, f64) {
+        decode_lat_lon(self.0.node.lat7_, self.0.node.lng7_)
+    }
+
     pub fn new(way_index: u32, way_shape_node_index: u32, osmid: u64, lng: f64, lat: f64, intersection: u32) -> Self
+    {
+        Self::from_node(OsmNode::new(osmid, lng, lat, intersection), way_index, way_shape_node_index)
+    }
+
+    pub fn from_node(node: OsmNode, way_index: u32, way_shape_node_index: u32) -> Self
     {
         let mut waynode = OsmWayNode::default();
+        waynode.0.node = node.0;
         waynode.0.way_index = way_index;
         waynode.0.way_shape_node_index = way_shape_node_index;
+        waynode
+    }
 
-        waynode.0.node.osmid_ = osmid;
-
-        let (lat7, lng7) = encode_lat_lon(lat, lng);
-        waynode.0.node.lng7_ = lng7;
-        waynode.0.node.lat7_ = lat7;
-        waynode.0.node.set_intersection_(intersection);
+    /// Whether the node is flagged as a traffic signal, see `OsmNode::set_node_kind`
+    pub fn traffic_signal(&self) -> bool {
+        self.0.node.traffic_signal_() == 1
+    }
 
-        // TODO: could also be 4095 ("kAllAccess")? See "graphconstants.h" in Valhalla
-        // TODO: get from Overture data
-        waynode.0.node.set_access_(2047);
+    /// Index of the way in `ways.bin` this node belongs to
+    pub fn way_index(&self) -> u32 {
+        self.0.way_index
+    }
 
-        waynode
+    pub fn set_way_index(&mut self, way_index: u32) {
+        self.0.way_index = way_index;
     }
 }
 
@@ -60,10 +136,18 @@ pub struct OsmWay(ffi::OSMWay);
 impl OsmWay {
     pub fn slice_as_bytes(slice: &[Self]) -> &[u8] {
         let ptr = slice.as_ptr() as *const u8;
-        let size = size_of::<Self>() * slice.len();
+        let size = size_of_val(slice);
         unsafe { std::slice::from_raw_parts(ptr, size) }
     }
 
+    pub fn from_bytes(bytes: &[u8]) -> Option<Vec<Self>> {
+        structs_from_bytes(bytes)
+    }
+
+    pub fn node_count(&self) -> u16 {
+        self.0.nodecount_
+    }
+
     pub fn new(osmid:u64, name_index:u32, nodecount:u16, auto_allowed: bool, pedestrian_allowed: bool) -> Self
     {
         let mut way = OsmWay::default();
@@ -100,5 +184,194 @@ impl OsmWay {
         way.0.speed_ = 25; // 25 km/h
 
         way
-    }    
+    }
+
+    pub fn osmid(&self) -> u64 {
+        self.0.osmwayid_
+    }
+
+    /// Position of the way's name in `strings.bin`
+    pub fn name_index(&self) -> u32 {
+        self.0.name_index_
+    }
+
+    /// Whether cars may use the way in its node order
+    pub fn auto_allowed(&self) -> bool {
+        self.0.auto_forward_() == 1
+    }
+
+    pub fn pedestrian_allowed(&self) -> bool {
+        self.0.pedestrian_forward_() == 1
+    }
+
+    pub fn set_use(&mut self, way_use: ValhallaUse) {
+        self.0.set_use_(way_use as u32);
+        self.0.set_ferry_(way_use.is_ferry() as u32);
+        self.0.set_rail_(way_use.is_rail() as u32);
+    }
+
+    pub fn set_road_class(&mut self, road_class: ValhallaRoadClass) {
+        self.0.set_road_class_(road_class as u32);
+    }
+
+    pub fn set_surface(&mut self, surface: ValhallaSurface) {
+        self.0.set_surface_(surface as u32);
+    }
+
+    /// Bicycle access in both directions, the cycle lane on both sides of the way and whether
+    /// cyclists have to dismount
+    pub fn set_bicycle(&mut self, allowed: bool, cycle_lane: ValhallaCycleLane, dismount: bool) {
+        self.0.set_bike_forward_(allowed as u16);
+        self.0.set_bike_backward_(allowed as u16);
+        self.0.set_cycle_lane_right_(cycle_lane as u16);
+        self.0.set_cycle_lane_left_(cycle_lane as u16);
+        self.0.set_dismount_(dismount as u16);
+    }
+
+    pub fn bicycle_allowed(&self) -> bool {
+        self.0.bike_forward_() == 1
+    }
+
+    /// Valhalla `CycleLane` value of the right side, the left side gets the same
+    pub fn cycle_lane(&self) -> u16 {
+        self.0.cycle_lane_right_()
+    }
+
+    pub fn bicycle_dismount(&self) -> bool {
+        self.0.dismount_() == 1
+    }
+
+    /// Valhalla `Surface` value
+    pub fn surface(&self) -> u32 {
+        self.0.surface_()
+    }
+
+    /// Tagged forward and backward speed limits, see `set_speed`
+    pub fn speed(&self) -> DirectionalSpeed {
+        DirectionalSpeed {
+            forward: (self.0.forward_tagged_speed_() == 1).then_some(self.0.forward_speed_),
+            backward: (self.0.backward_tagged_speed_() == 1).then_some(self.0.backward_speed_),
+        }
+    }
+
+    /// Tagged speed limits; `speed_` and `speed_limit_` get the higher of the two directions
+    pub fn set_speed(&mut self, speed: DirectionalSpeed) {
+        if let Some(forward) = speed.forward {
+            self.0.forward_speed_ = forward;
+            self.0.set_forward_tagged_speed_(1);
+        }
+        if let Some(backward) = speed.backward {
+            self.0.backward_speed_ = backward;
+            self.0.set_backward_tagged_speed_(1);
+        }
+        if let Some(max) = speed.max() {
+            self.0.speed_ = max;
+            self.0.speed_limit_ = max;
+            self.0.set_tagged_speed_(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_way_node_bytes_roundtrip() {
+        let waynodes = vec![
+            OsmWayNode::new(0, 0, 1, -122.3, 47.6, 1),
+            OsmWayNode::new(1, 1, 2, 139.7, 35.7, 1),
+        ];
+        let bytes = OsmWayNode::slice_as_bytes(&waynodes);
+        let decoded = OsmWayNode::from_bytes(bytes).unwrap();
+        assert_eq!(decoded.len(), 2);
+        assert_eq!(decoded[1].way_index(), 1);
+        let (lat, lon) = decoded[1].lat_lon();
+        assert!((lat - 35.7).abs() < 1e-6 && (lon - 139.7).abs() < 1e-6);
+
+        assert!(OsmWayNode::from_bytes(&bytes[..bytes.len() - 1]).is_none());
+    }
+
+    #[test]
+    fn test_osm_node_kind_flags() {
+        let mut node = OsmNode::new(7, -122.3, 47.6, 1);
+        assert_eq!(node.0.traffic_signal_(), 0);
+        assert_eq!(node.0.type_(), 0);
+
+        node.set_node_kind(ValhallaNodeKind::TrafficSignal);
+        assert_eq!(node.0.traffic_signal_(), 1);
+        assert_eq!(node.0.stop_sign_(), 0);
+
+        node.set_node_kind(ValhallaNodeKind::Gate);
+        assert_eq!(node.0.traffic_signal_(), 0);
+        assert_eq!(node.0.type_(), NODE_TYPE_GATE);
+
+        let waynode = OsmWayNode::from_node(node, 3, 4);
+        assert_eq!(waynode.0.node.osmid_, 7);
+        assert_eq!(waynode.0.node.type_(), NODE_TYPE_GATE);
+        assert_eq!(waynode.0.way_index, 3);
+        assert_eq!(waynode.0.way_shape_node_index, 4);
+    }
+
+    #[test]
+    fn test_osm_way_set_use_flags() {
+        let mut way = OsmWay::new(1, 1, 2, false, true);
+        assert_eq!(way.0.use_(), ValhallaUse::Footway as u32);
+
+        way.set_use(ValhallaUse::Ferry);
+        assert_eq!(way.0.use_(), 41);
+        assert_eq!(way.0.ferry_(), 1);
+        assert_eq!(way.0.rail_(), 0);
+
+        way.set_use(ValhallaUse::Rail);
+        assert_eq!(way.0.use_(), 50);
+        assert_eq!(way.0.ferry_(), 0);
+        assert_eq!(way.0.rail_(), 1);
+    }
+
+    #[test]
+    fn test_osm_way_set_road_class_and_surface() {
+        let mut way = OsmWay::new(1, 1, 2, true, true);
+        assert_eq!(way.0.road_class_(), 7);
+        assert_eq!(way.0.surface_(), 3);
+
+        way.set_road_class(ValhallaRoadClass::Primary);
+        way.set_surface(ValhallaSurface::Gravel);
+        assert_eq!(way.0.road_class_(), 2);
+        assert_eq!(way.0.surface_(), 5);
+    }
+
+    #[test]
+    fn test_osm_way_set_bicycle() {
+        let mut way = OsmWay::new(1, 1, 2, true, true);
+        assert!(!way.bicycle_allowed());
+
+        way.set_bicycle(true, ValhallaCycleLane::Separated, false);
+        assert!(way.bicycle_allowed());
+        assert_eq!(way.0.bike_backward_(), 1);
+        assert_eq!((way.0.cycle_lane_right_(), way.0.cycle_lane_left_()), (3, 3));
+        assert!(!way.bicycle_dismount());
+
+        way.set_bicycle(true, ValhallaCycleLane::None, true);
+        assert_eq!(way.cycle_lane(), 0);
+        assert!(way.bicycle_dismount());
+    }
+
+    #[test]
+    fn test_osm_way_set_speed() {
+        let mut way = OsmWay::new(1, 1, 2, true, true);
+        way.set_speed(DirectionalSpeed::default());
+        assert_eq!(way.0.speed_, 25);
+        assert_eq!(way.0.tagged_speed_(), 0);
+        assert_eq!(way.speed(), DirectionalSpeed::default());
+
+        way.set_speed(DirectionalSpeed { forward: Some(50), backward: Some(30) });
+        assert_eq!(way.0.forward_speed_, 50);
+        assert_eq!(way.0.backward_speed_, 30);
+        assert_eq!(way.0.forward_tagged_speed_(), 1);
+        assert_eq!(way.0.backward_tagged_speed_(), 1);
+        assert_eq!(way.0.speed_, 50);
+        assert_eq!(way.0.speed_limit_, 50);
+        assert_eq!(way.0.tagged_speed_(), 1);
+    }
 }
@@ -0,0 +1,128 @@
+// Sanity checks for the .bin files before handing them to Valhalla
+use std::fs::read;
+use std::path::Path;
+
+use crate::valhalla_sys::{OsmWay, OsmWayNode};
+
+/// Number of violations reported before the checks stop collecting more
+pub const MAX_REPORTED_VIOLATIONS: usize = 10;
+
+fn push_violation(violations: &mut Vec<String>, violation: String) {
+    if violations.len() < MAX_REPORTED_VIOLATIONS {
+        violations.push(violation);
+    }
+}
+
+/// Check `ways.bin` and `way_nodes.bin` in `output_dir` and return the first violations found:
+/// file sizes that are not a multiple of the struct size, way nodes referencing a missing
+/// way, way node counts not adding up, and coordinates outside the valid range.
+/// An empty list means the files passed all checks.
+pub fn verify_bin_files(output_dir: &Path) -> std::io::Result<Vec<String>> {
+    let way_bytes = read(output_dir.join("ways.bin"))?;
+    let way_node_bytes = read(output_dir.join("way_nodes.bin"))?;
+
+    let mut violations = Vec::new();
+    let ways = OsmWay::from_bytes(&way_bytes);
+    if ways.is_none() {
+        push_violation(&mut violations, format!(
+            "ways.bin is {} bytes, not a multiple of the OSMWay size ({} bytes)",
+            way_bytes.len(), size_of::<OsmWay>()
+        ));
+    }
+    let way_nodes = OsmWayNode::from_bytes(&way_node_bytes);
+    if way_nodes.is_none() {
+        push_violation(&mut violations, format!(
+            "way_nodes.bin is {} bytes, not a multiple of the OSMWayNode size ({} bytes)",
+            way_node_bytes.len(), size_of::<OsmWayNode>()
+        ));
+    }
+    let (Some(ways), Some(way_nodes)) = (ways, way_nodes) else {
+        return Ok(violations);
+    };
+
+    for (index, way_node) in way_nodes.iter().enumerate() {
+        if way_node.way_index() as usize >= ways.len() {
+            push_violation(&mut violations, format!(
+                "way node {} references way {} but there are only {} ways",
+                index, way_node.way_index(), ways.len()
+            ));
+        }
+        let (lat, lon) = way_node.lat_lon();
+        if !(-90.0..=90.0).contains(&lat) || !(-180.0..=180.0).contains(&lon) {
+            push_violation(&mut violations, format!(
+                "way node {} decodes to out of range coordinate ({}, {})",
+                index, lat, lon
+            ));
+        }
+    }
+
+    let expected_way_nodes: usize = ways.iter().map(|way| way.node_count() as usize).sum();
+    if expected_way_nodes != way_nodes.len() {
+        push_violation(&mut violations, format!(
+            "ways declare {} nodes in total but way_nodes.bin holds {}",
+            expected_way_nodes, way_nodes.len()
+        ));
+    }
+
+    Ok(violations)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::write;
+
+    use tempfile::tempdir;
+
+    use super::*;
+
+    fn write_bin_files(output_dir: &Path, ways: &[OsmWay], way_nodes: &[OsmWayNode]) {
+        write(output_dir.join("ways.bin"), OsmWay::slice_as_bytes(ways)).unwrap();
+        write(output_dir.join("way_nodes.bin"), OsmWayNode::slice_as_bytes(way_nodes)).unwrap();
+    }
+
+    fn way_nodes(way_index: u32) -> Vec<OsmWayNode> {
+        vec![
+            OsmWayNode::new(way_index, 0, 1, -122.3, 47.6, 1),
+            OsmWayNode::new(way_index, 1, 2, -122.31, 47.61, 1),
+        ]
+    }
+
+    #[test]
+    fn test_valid_files_pass() {
+        let dir = tempdir().unwrap();
+        write_bin_files(dir.path(), &[OsmWay::new(1, 1, 2, true, true)], &way_nodes(0));
+        assert_eq!(verify_bin_files(dir.path()).unwrap(), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_truncated_file_fails_size_check() {
+        let dir = tempdir().unwrap();
+        write_bin_files(dir.path(), &[OsmWay::new(1, 1, 2, true, true)], &way_nodes(0));
+        let bytes = read(dir.path().join("way_nodes.bin")).unwrap();
+        write(dir.path().join("way_nodes.bin"), &bytes[..bytes.len() - 3]).unwrap();
+
+        let violations = verify_bin_files(dir.path()).unwrap();
+        assert_eq!(violations.len(), 1);
+        assert!(violations[0].contains("way_nodes.bin"), "{:?}", violations);
+        assert!(violations[0].contains("not a multiple"), "{:?}", violations);
+    }
+
+    #[test]
+    fn test_dangling_way_index_fails_reference_check() {
+        let dir = tempdir().unwrap();
+        write_bin_files(dir.path(), &[OsmWay::new(1, 1, 2, true, true)], &way_nodes(3));
+
+        let violations = verify_bin_files(dir.path()).unwrap();
+        assert_eq!(violations.len(), 2);
+        assert!(violations.iter().all(|v| v.contains("references way 3")), "{:?}", violations);
+    }
+
+    #[test]
+    fn test_node_count_mismatch() {
+        let dir = tempdir().unwrap();
+        write_bin_files(dir.path(), &[OsmWay::new(1, 1, 3, true, true)], &way_nodes(0));
+
+        let violations = verify_bin_files(dir.path()).unwrap();
+        assert_eq!(violations, vec!["ways declare 3 nodes in total but way_nodes.bin holds 2"]);
+    }
+}
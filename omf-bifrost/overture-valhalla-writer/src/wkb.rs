@@ -0,0 +1,109 @@
+// WKB decoding shared by segment and connector geometries
+use geo_types::{Geometry, LineString};
+use geozero::wkb::Wkb;
+use geozero::ToGeo;
+
+use crate::error::WriterError;
+
+fn geometry_type_name(geometry: &Geometry<f64>) -> &'static str {
+    match geometry {
+        Geometry::Point(_) => "Point",
+        Geometry::Line(_) => "Line",
+        Geometry::LineString(_) => "LineString",
+        Geometry::Polygon(_) => "Polygon",
+        Geometry::MultiPoint(_) => "MultiPoint",
+        Geometry::MultiLineString(_) => "MultiLineString",
+        Geometry::MultiPolygon(_) => "MultiPolygon",
+        Geometry::GeometryCollection(_) => "GeometryCollection",
+        Geometry::Rect(_) => "Rect",
+        Geometry::Triangle(_) => "Triangle",
+    }
+}
+
+pub fn wkb_to_geometry(bytes: &[u8]) -> Result<Geometry<f64>, WriterError> {
+    Wkb(bytes)
+        .to_geo()
+        .map_err(|e| WriterError::InvalidWkb(e.to_string()))
+}
+
+pub fn as_point(geometry: Geometry<f64>) -> Result<geo_types::Point<f64>, WriterError> {
+    match geometry {
+        Geometry::Point(point) => Ok(point),
+        other => Err(WriterError::UnexpectedGeometry {
+            expected: "Point",
+            found: geometry_type_name(&other),
+        }),
+    }
+}
+
+pub fn as_linestring(geometry: Geometry<f64>) -> Result<LineString<f64>, WriterError> {
+    match geometry {
+        Geometry::LineString(line) => Ok(line),
+        other => Err(WriterError::UnexpectedGeometry {
+            expected: "LineString",
+            found: geometry_type_name(&other),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wkb_header(geometry_type: u32) -> Vec<u8> {
+        let mut wkb = vec![1u8];
+        wkb.extend_from_slice(&geometry_type.to_le_bytes());
+        wkb
+    }
+
+    fn push_coords(wkb: &mut Vec<u8>, coords: &[(f64, f64)]) {
+        for (x, y) in coords {
+            wkb.extend_from_slice(&x.to_le_bytes());
+            wkb.extend_from_slice(&y.to_le_bytes());
+        }
+    }
+
+    #[test]
+    fn test_valid_point() {
+        let mut wkb = wkb_header(1);
+        push_coords(&mut wkb, &[(-122.3, 47.6)]);
+
+        let point = as_point(wkb_to_geometry(&wkb).unwrap()).unwrap();
+        assert_eq!((point.x(), point.y()), (-122.3, 47.6));
+    }
+
+    #[test]
+    fn test_valid_linestring() {
+        let coords = [(-122.3, 47.6), (-122.31, 47.61)];
+        let mut wkb = wkb_header(2);
+        wkb.extend_from_slice(&(coords.len() as u32).to_le_bytes());
+        push_coords(&mut wkb, &coords);
+
+        let line = as_linestring(wkb_to_geometry(&wkb).unwrap()).unwrap();
+        let points: Vec<(f64, f64)> = line.points().map(|p| (p.x(), p.y())).collect();
+        assert_eq!(points, coords);
+    }
+
+    #[test]
+    fn test_polygon_rejected() {
+        let ring = [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 0.0)];
+        let mut wkb = wkb_header(3);
+        wkb.extend_from_slice(&1u32.to_le_bytes());
+        wkb.extend_from_slice(&(ring.len() as u32).to_le_bytes());
+        push_coords(&mut wkb, &ring);
+
+        let geometry = wkb_to_geometry(&wkb).unwrap();
+        let error = as_linestring(geometry.clone()).unwrap_err();
+        assert!(matches!(
+            error,
+            WriterError::UnexpectedGeometry { expected: "LineString", found: "Polygon" }
+        ));
+        assert_eq!(error.to_string(), "expected a LineString geometry but found a Polygon");
+        assert!(matches!(as_point(geometry), Err(WriterError::UnexpectedGeometry { .. })));
+    }
+
+    #[test]
+    fn test_invalid_wkb() {
+        assert!(matches!(wkb_to_geometry(&[1, 2, 3]), Err(WriterError::InvalidWkb(_))));
+    }
+}
@@ -1,13 +1,37 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs::{write, File};
+use std::io::{Error, ErrorKind};
+use std::num::NonZeroUsize;
+use std::sync::mpsc::sync_channel;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Instant;
 use parquet::file::reader::{FileReader, SerializedFileReader};
 use std::path::Path;
 use parquet::record::Field;
-use parquet::record::List;
-use log::info;
+use parquet::record::{List, Row};
+use log::{debug, info, warn};
+use overture_types::{
+    AccessRestriction, AccessWhen, AlongFraction, ProhibitedTransition, Source, Speed, SpeedLimit, SpeedLimitWhen, TransitionStep,
+    TransitionWhen
+};
 
-use crate::valhalla_sys::{OsmWay, OsmWayNode};
+use crate::clip::{row_bbox, ClipBox};
+use crate::error::WriterError;
+use crate::mapped::{map_segment, MappedSegment, MappingContext};
+use crate::mapping::{map_node_kind, ValhallaAccess, ValhallaNodeKind};
+use crate::metrics::MappingMetrics;
+use crate::output::{ConversionOutput, SegmentOutput};
+use crate::precheck::precheck_connector_refs;
+use crate::profile::StageTimings;
+use crate::restrictions::{collect_restrictions, export_restrictions, RESTRICTIONS_FILE};
+use crate::sources::{export_sources, NodeSources, SourceRecords, WaySources, SOURCES_FILE};
+use crate::strings::{export_strings, STRINGS_FILE};
+use crate::valhalla_sys::{OsmNode, OsmWay, OsmWayNode};
+use crate::wkb::{as_linestring, as_point, wkb_to_geometry};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Default, Clone)]
 pub struct Point {
     pub lat: f64,
     pub lon: f64
@@ -16,78 +40,177 @@ pub struct Point {
 #[derive(Debug)]
 pub struct ConnectorRef {
     pub id: String,
-    pub at: f64
+    pub at: AlongFraction,
+    /// Connector position when the segment carries it inline
+    pub coordinate: Option<Point>
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct Connector {
     pub id: String,
-    pub coordinate: Point
+    pub coordinate: Point,
+    /// Traffic control or barrier at the connector, from its `subtype`
+    pub node_kind: ValhallaNodeKind,
+    /// Datasets the connector was derived from
+    pub sources: Vec<Source>
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct Segment {
+    pub id: String,
     pub name: String,
     pub road_class: Option<String>,
+    /// Overture subtype (road, rail, water)
+    pub subtype: Option<String>,
+    /// Overture subclass refining the class (e.g. parking_aisle, driveway, sidewalk)
+    pub subclass: Option<String>,
     pub points: Vec<Point>,
     pub connectors: Vec<ConnectorRef>,
+    /// Max speed limits applying to the whole segment
+    pub speed_limits: Vec<SpeedLimit>,
+    /// Overture road surface of the whole segment (paved, gravel...)
+    pub surface: Option<String>,
+    /// Turns onto other segments that may not be taken, see `restrictions`
+    pub prohibited_transitions: Vec<ProhibitedTransition>,
+    /// Access rules applying to the whole segment, resolved on top of the class defaults
+    pub access_restrictions: Vec<AccessRestriction>,
+    /// Datasets the segment was derived from
+    pub sources: Vec<Source>,
+}
+
+/// Options controlling `convert_overture_to_valhalla`
+#[derive(Debug, Default, Clone)]
+pub struct ConvertOptions {
+    /// Write empty output files with a warning instead of failing when no segment is routable
+    pub allow_empty: bool,
+    /// Skip segments and connectors with implausible coordinates, see `geometry_problem`
+    pub strict_geometry: bool,
+    /// Round segment vertices and connector coordinates to this many decimals before matching
+    /// them, to absorb float drift from the parquet encoding; output keeps full precision
+    pub match_precision: Option<u32>,
+    /// Worker threads parsing segment rows, each taking a whole row group at a time when the
+    /// file has several; defaults to the available CPU cores
+    pub threads: Option<usize>,
+    /// Segment rows handed to a worker at once in files with a single row group, defaults to
+    /// `DEFAULT_CHUNK_SIZE`. Larger chunks cost less scheduling, smaller ones spread uneven
+    /// rows better over the workers.
+    pub chunk_size: Option<usize>,
+    /// Convert only this fraction (0-1] of the segments, picked by `segment_in_sample`
+    pub sample_rate: Option<f64>,
+    /// Seed for `sample_rate`; the same seed always selects the same segments
+    pub seed: u64,
+    /// Degrees a connector may lie from the vertex at its `at` position and still be matched
+    /// there by id, defaults to `DEFAULT_CONNECTOR_SNAP_TOLERANCE`
+    pub connector_snap_tolerance: Option<f64>,
+    /// Also write every road a second time with its nodes reversed, defaults to
+    /// `DEFAULT_EMIT_REVERSE_WAYS`. Transitional: the duplicate ways work around Valhalla
+    /// rejecting single ways and will be dropped once roads are written with proper directions.
+    pub emit_reverse_ways: Option<bool>,
+    /// Scan the segments for references to missing connectors first and stop before
+    /// converting anything if there are any, see `precheck_connector_refs`
+    pub precheck: bool,
+    /// Also write `<mode>_ways.bin` and `<mode>_way_nodes.bin` for auto, pedestrian and bicycle,
+    /// each with only the roads open to that mode, see `TRAVEL_MODES`
+    pub split_by_mode: bool,
+    /// Also write `sources.json`, mapping way and node ids to the Overture features and the
+    /// datasets they came from, see `sources`
+    pub preserve_sources: bool,
+    /// What to do with segments none of whose nodes is a connector
+    pub connectorless_policy: ConnectorlessPolicy,
+    /// Keep only the segments passing through this box, see `clip`
+    pub clip: Option<ClipBox>,
+}
+
+/// Handling of connectorless segments, whose nodes all failed to match a connector (or that
+/// reference none), so they cannot join the rest of the graph through shared connector nodes
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectorlessPolicy {
+    /// Leave the segment out of the output
+    Skip,
+    /// Give every vertex, including both endpoints, a node of its own
+    #[default]
+    Synthesize,
+    /// Stop the conversion with an error naming the segment
+    Error,
+}
+
+impl std::str::FromStr for ConnectorlessPolicy {
+    type Err = String;
+
+    fn from_str(policy: &str) -> Result<Self, Self::Err> {
+        match policy {
+            "skip" => Ok(Self::Skip),
+            "synthesize" => Ok(Self::Synthesize),
+            "error" => Ok(Self::Error),
+            other => Err(format!("unknown connectorless policy '{}', expected skip, synthesize or error", other)),
+        }
+    }
+}
+
+/// Segment rows per parsing task unless `ConvertOptions::chunk_size` is set
+pub const DEFAULT_CHUNK_SIZE: usize = 1024;
+
+/// Connector snap tolerance in degrees (about 11 m) unless `ConvertOptions::connector_snap_tolerance` is set
+pub const DEFAULT_CONNECTOR_SNAP_TOLERANCE: f64 = 1e-4;
+
+/// Whether roads are double-written unless `ConvertOptions::emit_reverse_ways` is set
+pub const DEFAULT_EMIT_REVERSE_WAYS: bool = true;
+
+impl ConvertOptions {
+    fn threads(&self) -> usize {
+        self.threads
+            .or_else(|| thread::available_parallelism().ok().map(NonZeroUsize::get))
+            .unwrap_or(1)
+            .max(1)
+    }
+
+    fn chunk_size(&self) -> usize {
+        self.chunk_size.unwrap_or(DEFAULT_CHUNK_SIZE).max(1)
+    }
+
+    fn connector_snap_tolerance(&self) -> f64 {
+        self.connector_snap_tolerance.unwrap_or(DEFAULT_CONNECTOR_SNAP_TOLERANCE)
+    }
+
+    fn emit_reverse_ways(&self) -> bool {
+        self.emit_reverse_ways.unwrap_or(DEFAULT_EMIT_REVERSE_WAYS)
+    }
 }
 
+/// Tolerance in degrees when matching segment vertices to connectors by coordinate
+const CONNECTOR_MATCH_TOLERANCE: f64 = 1e-6;
+
+/// Margin on vertex fractions within which a connector `at` counts as lying on the vertex
+const FRACTION_EPSILON: f64 = 1e-9;
+
+/// Largest plausible distance in degrees between consecutive vertices of one segment
+const MAX_VERTEX_JUMP_DEGREES: f64 = 1.0;
+
 #[derive(Debug)]
 pub struct Data {
     pub segments: Vec<Segment>,
     pub connectors: Vec<Connector>,
 }
 
-fn parse_point_wkb(wkb_data: &[u8]) -> Point {
-    use geozero::wkb::Wkb;
-    use geozero::ToGeo;
-    use geo_types::Geometry;
-    
-    let wkb = Wkb(wkb_data);
-    let geometry: Geometry<f64> = wkb.to_geo().unwrap();
-    
-    match geometry {
-        Geometry::Point(point) => {
-            Point {
-                lat: point.y(),
-                lon: point.x()
-            }
-        }
-        _ => {
-            panic!("Expected WKB to represent a Point");
-        }   
-    }
-}
-
-fn process_geometry_vector(wkb_data: &[u8]) -> Vec<Point> {
-    use geozero::wkb::Wkb;
-    use geozero::ToGeo;
-    use geo_types::Geometry;
-    
-    let wkb = Wkb(wkb_data);
-    let geometry: Geometry<f64> = wkb.to_geo().unwrap();
-    
-    match geometry {
-        Geometry::LineString(line) => {
-            let mut output : Vec<Point> = Vec::new();
-            for point in line.points() {
-                let added_point = Point {
-                    lat: point.y(),
-                    lon: point.x()
-                };
-                output.push(added_point);
-            }
-            output
-        }
-        _ => {
-            panic!("Expected WKB to represent a LineString");
-        }   
-    }
+fn parse_point_wkb(wkb_data: &[u8]) -> Result<Point, WriterError> {
+    let point = as_point(wkb_to_geometry(wkb_data)?)?;
+    Ok(Point {
+        lat: point.y(),
+        lon: point.x()
+    })
 }
 
+fn process_geometry_vector(wkb_data: &[u8]) -> Result<Vec<Point>, WriterError> {
+    let line = as_linestring(wkb_to_geometry(wkb_data)?)?;
+    Ok(line.points()
+        .map(|point| Point {
+            lat: point.y(),
+            lon: point.x()
+        })
+        .collect())
+}
 
-fn process_connector_refs(connector_ref_list : List) -> Vec<ConnectorRef>
+fn process_connector_refs(connector_ref_list : List) -> Result<Vec<ConnectorRef>, WriterError>
 {
     let mut connector_refs = Vec::new();
 
@@ -95,285 +218,2431 @@ fn process_connector_refs(connector_ref_list : List) -> Vec<ConnectorRef>
         if let Field::Group(group) = connector_ref {
             let mut connector_ref = ConnectorRef {
                 id: String::new(),
-                at: 0.0
+                at: AlongFraction::START,
+                coordinate: None
             };
+            let mut at: Option<f64> = None;
+            let mut lat: Option<f64> = None;
+            let mut lon: Option<f64> = None;
             for row in group.get_column_iter() {
                 if row.0 == "connector_id" {
                     if let Field::Str(id) = row.1 {
                         connector_ref.id = id.to_string();
                     }
                 } else if row.0 == "at" {
-                    if let Field::Double(at) = row.1 {
-                        connector_ref.at = *at;
+                    if let Field::Double(value) = row.1 {
+                        at = Some(*value);
+                    }
+                } else if row.0 == "lat" {
+                    if let Field::Double(value) = row.1 {
+                        lat = Some(*value);
+                    }
+                } else if row.0 == "lon" {
+                    if let Field::Double(value) = row.1 {
+                        lon = Some(*value);
+                    }
+                } else if row.0 == "geometry" {
+                    if let Field::Bytes(byte_array) = row.1 {
+                        connector_ref.coordinate = Some(parse_point_wkb(byte_array.data())?);
                     }
                 }
             }
+            if let (Some(lat), Some(lon)) = (lat, lon) {
+                connector_ref.coordinate = Some(Point { lat, lon });
+            }
+            if let Some(at) = at {
+                connector_ref.at = AlongFraction::new(at).map_err(|source| WriterError::InvalidConnectorPosition {
+                    connector_id: connector_ref.id.clone(),
+                    source
+                })?;
+            }
             connector_refs.push(connector_ref);
         }
     }
 
-    connector_refs
+    Ok(connector_refs)
 }
 
-pub fn import_overture_data(segment_path: &Path, connector_path: &Path) -> std::io::Result<Data> {
-    let file = File::open(segment_path)?;
-    let reader = SerializedFileReader::new(file)?;
+fn process_speed(speed_group: &Row) -> Option<Speed>
+{
+    let mut value: Option<f64> = None;
+    let mut unit = String::from("km/h");
+    for field in speed_group.get_column_iter() {
+        if field.0 == "value" {
+            value = match field.1 {
+                Field::Int(value) => Some(*value as f64),
+                Field::Long(value) => Some(*value as f64),
+                Field::Double(value) => Some(*value),
+                _ => None,
+            };
+        } else if field.0 == "unit" {
+            if let Field::Str(value) = field.1 {
+                unit = value.to_string();
+            }
+        }
+    }
+    value.map(|value| Speed { value, unit })
+}
 
-    let iter = reader.get_row_iter(None)?;
+/// Read the max speed limits of a segment. Limits restricted to part of the segment (`between`)
+/// or to conditions other than the heading (time, vehicle, mode...) are skipped.
+fn process_speed_limits(speed_limit_list: List) -> Vec<SpeedLimit>
+{
+    let mut speed_limits = Vec::new();
 
-    let mut segments: Vec<Segment> = Vec::new();
-    for row in iter {
-        let mut primary_name = String::new();
-        let mut road_class: Option<String> = None;
-        let mut geometry : Option<Vec<Point>> = None;
-        let mut connectors: Option<Vec<ConnectorRef>> = None;
-        for column in row?.into_columns() {
-            if column.0 == "names" {
-                if let Field::Group(group) = column.1 {
-                    for field in group.get_column_iter() {
-                        if field.0 == "primary" {
-                            if let Field::Str(name) = field.1 {
-                                primary_name = name.to_string();
+    for speed_limit in speed_limit_list.elements() {
+        let Field::Group(group) = speed_limit else {
+            continue;
+        };
+        let mut limit = SpeedLimit { max_speed: None, min_speed: None, when: None };
+        let mut conditional = false;
+        for column in group.get_column_iter() {
+            if column.0 == "max_speed" {
+                if let Field::Group(speed) = column.1 {
+                    limit.max_speed = process_speed(speed);
+                }
+            } else if column.0 == "min_speed" {
+                if let Field::Group(speed) = column.1 {
+                    limit.min_speed = process_speed(speed);
+                }
+            } else if column.0 == "between" {
+                conditional |= column.1 != &Field::Null;
+            } else if column.0 == "when" {
+                if let Field::Group(when) = column.1 {
+                    for condition in when.get_column_iter() {
+                        if condition.0 == "heading" {
+                            if let Field::Str(heading) = condition.1 {
+                                limit.when = Some(SpeedLimitWhen { heading: Some(heading.to_string()) });
                             }
+                        } else {
+                            conditional |= condition.1 != &Field::Null;
                         }
                     }
                 }
-            } else if column.0 == "geometry" {
-                let field : Field = column.1;
-                if let Field::Bytes(byte_array) = field {
-                    geometry = Some(process_geometry_vector(byte_array.data()));
+            }
+        }
+
+        if conditional {
+            debug!("Skipping conditional speed limit {:?}", limit);
+            continue;
+        }
+        speed_limits.push(limit);
+    }
+
+    speed_limits
+}
+
+/// Read the road surface of a segment, ignoring surfaces restricted to part of it (`between`)
+fn process_road_surface(road_surface_list: List) -> Option<String>
+{
+    for road_surface in road_surface_list.elements() {
+        let Field::Group(group) = road_surface else {
+            continue;
+        };
+        let mut value: Option<String> = None;
+        let mut partial = false;
+        for column in group.get_column_iter() {
+            if column.0 == "value" {
+                if let Field::Str(surface) = column.1 {
+                    value = Some(surface.to_string());
+                }
+            } else if column.0 == "between" {
+                partial |= column.1 != &Field::Null;
+            }
+        }
+        if !partial && value.is_some() {
+            return value;
+        }
+    }
+
+    None
+}
+
+fn string_list(list: &List) -> Vec<String>
+{
+    list.elements()
+        .iter()
+        .filter_map(|element| match element {
+            Field::Str(value) => Some(value.to_string()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Read the `connector_id`/`segment_id` steps of a transition sequence
+fn process_transition_sequence(sequence_list: &List) -> Vec<TransitionStep>
+{
+    let mut sequence = Vec::new();
+    for step in sequence_list.elements() {
+        let Field::Group(group) = step else {
+            continue;
+        };
+        let mut connector_id = None;
+        let mut segment_id = None;
+        for column in group.get_column_iter() {
+            match (column.0.as_str(), column.1) {
+                ("connector_id", Field::Str(value)) => connector_id = Some(value.to_string()),
+                ("segment_id", Field::Str(value)) => segment_id = Some(value.to_string()),
+                _ => {}
+            }
+        }
+        if let (Some(connector_id), Some(segment_id)) = (connector_id, segment_id) {
+            sequence.push(TransitionStep { connector_id, segment_id });
+        }
+    }
+    sequence
+}
+
+/// Read the prohibited transitions of a segment with their heading, `during` and mode conditions
+fn process_prohibited_transitions(transition_list: List) -> Vec<ProhibitedTransition>
+{
+    let mut transitions = Vec::new();
+
+    for transition in transition_list.elements() {
+        let Field::Group(group) = transition else {
+            continue;
+        };
+        let mut prohibited = ProhibitedTransition { sequence: Vec::new(), final_heading: None, when: None };
+        for column in group.get_column_iter() {
+            if column.0 == "sequence" {
+                if let Field::ListInternal(sequence_list) = column.1 {
+                    prohibited.sequence = process_transition_sequence(sequence_list);
                 }
-            } else if column.0 == "connectors" {
-                let field : Field = column.1;
-                if let Field::ListInternal(connectorref_list) = field {
-                    connectors = Some(process_connector_refs(connectorref_list));
+            } else if column.0 == "final_heading" {
+                if let Field::Str(heading) = column.1 {
+                    prohibited.final_heading = Some(heading.to_string());
+                }
+            } else if column.0 == "when" {
+                if let Field::Group(when) = column.1 {
+                    let mut conditions = TransitionWhen::default();
+                    for condition in when.get_column_iter() {
+                        match (condition.0.as_str(), condition.1) {
+                            ("heading", Field::Str(heading)) => conditions.heading = Some(heading.to_string()),
+                            ("during", Field::Str(during)) => conditions.during = Some(during.to_string()),
+                            ("mode", Field::ListInternal(modes)) => conditions.mode = Some(string_list(modes)),
+                            _ => {}
+                        }
+                    }
+                    prohibited.when = Some(conditions);
                 }
-            } else if column.0 == "class" {
-                let field : Field = column.1;
-                if let Field::Str(class) = field {
-                    road_class = Some(class.to_string());
-                }            
             }
         }
+        transitions.push(prohibited);
+    }
+
+    transitions
+}
 
-        // TODO: check if we have geometry and connectors before pushing
-        segments.push(Segment {
-            name: primary_name,
-            road_class,
-            points: geometry.unwrap(),
-            connectors: connectors.unwrap()
-        });
+/// Read the `sources` provenance list of a feature, skipping entries without a dataset
+fn process_sources(source_list: &List) -> Vec<Source>
+{
+    let mut sources = Vec::new();
+    for source in source_list.elements() {
+        let Field::Group(group) = source else {
+            continue;
+        };
+        let mut parsed = Source::default();
+        for column in group.get_column_iter() {
+            match (column.0.as_str(), column.1) {
+                ("property", Field::Str(value)) => parsed.property = Some(value.to_string()),
+                ("dataset", Field::Str(value)) => parsed.dataset = value.to_string(),
+                ("record_id", Field::Str(value)) => parsed.record_id = Some(value.to_string()),
+                ("update_time", Field::Str(value)) => parsed.update_time = Some(value.to_string()),
+                ("confidence", Field::Double(value)) => parsed.confidence = Some(*value),
+                ("confidence", Field::Float(value)) => parsed.confidence = Some(*value as f64),
+                _ => {}
+            }
+        }
+        if !parsed.dataset.is_empty() {
+            sources.push(parsed);
+        }
     }
+    sources
+}
 
-    let file = File::open(connector_path)?;
-    let reader = SerializedFileReader::new(file)?;
+/// Set the `AccessWhen` flag an Overture travel mode falls under, false for modes Valhalla
+/// does not distinguish
+fn apply_access_mode(when: &mut AccessWhen, mode: &str) -> bool
+{
+    let flag = match mode {
+        "vehicle" | "motor_vehicle" | "car" | "motorcycle" | "truck" | "hgv" | "bus" => &mut when.vehicle,
+        "bicycle" => &mut when.bicycle,
+        "foot" | "pedestrian" => &mut when.pedestrian,
+        _ => return false,
+    };
+    *flag = Some(true);
+    true
+}
 
-    let iter = reader.get_row_iter(None)?;
+/// Read the access restrictions of a segment. Modes come from the `when.mode` list or the
+/// `when.vehicle`/`bicycle`/`pedestrian` flags; rules limited to a part of the segment, a
+/// heading, a time or other conditions are skipped.
+fn process_access_restrictions(restriction_list: List) -> Vec<AccessRestriction>
+{
+    let mut restrictions = Vec::new();
 
-    let mut connectors: Vec<Connector> = Vec::new();
-    for row in iter {
-        let mut id = String::new();
-        let mut coordinate: Option<Point> = None;
-        for column in row?.into_columns() {
-            if column.0 == "id" {
-                if let Field::Str(id_str) = column.1 {
-                    id = id_str.to_string();
+    for restriction in restriction_list.elements() {
+        let Field::Group(group) = restriction else {
+            continue;
+        };
+        let mut access_type = None;
+        let mut when: Option<AccessWhen> = None;
+        let mut conditional = false;
+        for column in group.get_column_iter() {
+            if column.0 == "access_type" {
+                if let Field::Str(value) = column.1 {
+                    access_type = Some(value.to_string());
+                }
+            } else if column.0 == "between" {
+                conditional |= column.1 != &Field::Null;
+            } else if column.0 == "when" {
+                let Field::Group(conditions) = column.1 else {
+                    continue;
+                };
+                let mut modes = AccessWhen { vehicle: None, bicycle: None, pedestrian: None };
+                let mut has_modes = false;
+                for condition in conditions.get_column_iter() {
+                    match (condition.0.as_str(), condition.1) {
+                        ("mode", Field::ListInternal(mode_list)) => {
+                            for mode in string_list(mode_list) {
+                                if !apply_access_mode(&mut modes, &mode) {
+                                    conditional = true;
+                                }
+                            }
+                            has_modes = true;
+                        }
+                        ("vehicle", Field::Bool(value)) => {
+                            modes.vehicle = Some(*value);
+                            has_modes = true;
+                        }
+                        ("bicycle", Field::Bool(value)) => {
+                            modes.bicycle = Some(*value);
+                            has_modes = true;
+                        }
+                        ("pedestrian", Field::Bool(value)) => {
+                            modes.pedestrian = Some(*value);
+                            has_modes = true;
+                        }
+                        (_, Field::Null) => {}
+                        _ => conditional = true,
+                    }
                 }
-            } else if column.0 == "geometry" {
-                if let Field::Bytes(byte_array) = column.1 {
-                    coordinate = Some(parse_point_wkb(byte_array.data()));
+                if has_modes {
+                    when = Some(modes);
                 }
             }
         }
 
-
-        connectors.push(Connector {
-            id,
-            coordinate: coordinate.unwrap()
-        });
+        let Some(access_type) = access_type else {
+            continue;
+        };
+        if conditional {
+            debug!("Skipping conditional {} access restriction {:?}", access_type, when);
+            continue;
+        }
+        restrictions.push(AccessRestriction { access_type, when });
     }
 
-    Ok(Data { segments, connectors })
+    restrictions
 }
 
-#[derive(Debug)]
-struct IndexedPoint {
-    index: usize,
-    point: Point
+/// Parse one segment row, `None` when it has no LineString geometry
+fn segment_from_row(row: Row) -> Result<Option<Segment>, WriterError>
+{
+    let mut id = String::new();
+    let mut primary_name = String::new();
+    let mut road_class: Option<String> = None;
+    let mut subtype: Option<String> = None;
+    let mut subclass: Option<String> = None;
+    let mut geometry : Option<Vec<Point>> = None;
+    let mut connectors: Option<Vec<ConnectorRef>> = None;
+    let mut speed_limits: Vec<SpeedLimit> = Vec::new();
+    let mut surface: Option<String> = None;
+    let mut prohibited_transitions: Vec<ProhibitedTransition> = Vec::new();
+    let mut access_restrictions: Vec<AccessRestriction> = Vec::new();
+    let mut sources: Vec<Source> = Vec::new();
+    for column in row.into_columns() {
+        if column.0 == "id" {
+            if let Field::Str(value) = column.1 {
+                id = value.to_string();
+            }
+        } else if column.0 == "names" {
+            if let Field::Group(group) = column.1 {
+                for field in group.get_column_iter() {
+                    if field.0 == "primary" {
+                        if let Field::Str(name) = field.1 {
+                            primary_name = name.trim().to_string();
+                        }
+                    }
+                }
+            }
+        } else if column.0 == "geometry" {
+            let field : Field = column.1;
+            if let Field::Bytes(byte_array) = field {
+                geometry = Some(process_geometry_vector(byte_array.data())?);
+            }
+        } else if column.0 == "connectors" {
+            let field : Field = column.1;
+            if let Field::ListInternal(connectorref_list) = field {
+                connectors = Some(process_connector_refs(connectorref_list)?);
+            }
+        } else if column.0 == "class" {
+            let field : Field = column.1;
+            if let Field::Str(class) = field {
+                road_class = Some(class.to_string());
+            }            
+        } else if column.0 == "subtype" {
+            if let Field::Str(value) = column.1 {
+                subtype = Some(value.to_string());
+            }
+        } else if column.0 == "subclass" {
+            if let Field::Str(value) = column.1 {
+                subclass = Some(value.to_string());
+            }
+        } else if column.0 == "speed_limits" {
+            if let Field::ListInternal(speed_limit_list) = column.1 {
+                speed_limits = process_speed_limits(speed_limit_list);
+            }
+        } else if column.0 == "road_surface" {
+            if let Field::ListInternal(road_surface_list) = column.1 {
+                surface = process_road_surface(road_surface_list);
+            }
+        } else if column.0 == "prohibited_transitions" {
+            if let Field::ListInternal(transition_list) = column.1 {
+                prohibited_transitions = process_prohibited_transitions(transition_list);
+            }
+        } else if column.0 == "access_restrictions" {
+            if let Field::ListInternal(restriction_list) = column.1 {
+                access_restrictions = process_access_restrictions(restriction_list);
+            }
+        } else if column.0 == "sources" {
+            if let Field::ListInternal(source_list) = column.1 {
+                sources = process_sources(&source_list);
+            }
+        }
+    }
+
+    let Some(points) = geometry else {
+        warn!("Skipping segment '{}' without a LineString geometry", primary_name);
+        return Ok(None);
+    };
+
+    Ok(Some(Segment {
+        id,
+        name: primary_name,
+        road_class,
+        subtype,
+        subclass,
+        points,
+        connectors: connectors.unwrap_or_default(),
+        speed_limits,
+        surface,
+        prohibited_transitions,
+        access_restrictions,
+        sources
+    }))
 }
 
-#[derive(Debug)]
-struct Permissions {
-    pedestrian_allowed: bool,
-    auto_allowed: bool,
+/// Overture feature `type` of a row, `None` when the file has no `type` column
+fn row_type(row: &Row) -> Option<&str>
+{
+    row.get_column_iter().find(|column| column.0 == "type").and_then(|column| match column.1 {
+        Field::Str(value) => Some(value.as_str()),
+        _ => None,
+    })
 }
 
+/// Whether a segment row passes through `clip`. The row's `bbox` struct, when it has one,
+/// rejects segments clear of the box before their geometry is decoded; the others are
+/// decoded and checked precisely.
+fn segment_in_clip(row: Row, clip: &ClipBox) -> Result<Option<Segment>, WriterError>
+{
+    if let Some(bbox) = row_bbox(&row) {
+        if !clip.overlaps(&bbox) {
+            return Ok(None);
+        }
+    }
+    Ok(segment_from_row(row)?.filter(|segment| clip.intersects(&segment.points)))
+}
 
-#[derive(Debug)]
-struct ExportedRoad
+/// Parse a row of the segment file, dropping segments outside `clip`. A combined transportation
+/// download also holds connector rows, told apart by the `type` column; rows without one are
+/// segments.
+fn parse_transportation_row(row: Row, data: &mut Data, clip: Option<&ClipBox>) -> Result<(), WriterError>
 {
-    points: Vec<IndexedPoint>,
-    permissions: Permissions
+    match row_type(&row) {
+        None | Some("segment") => match clip {
+            Some(clip) => data.segments.extend(segment_in_clip(row, clip)?),
+            None => data.segments.extend(segment_from_row(row)?),
+        },
+        Some("connector") => data.connectors.push(connector_from_row(row)?),
+        Some(other) => return Err(WriterError::UnexpectedFeatureType(other.to_string())),
+    }
+    Ok(())
+}
+
+/// Parse the segment rows on `threads` workers, a row group at a time when the file has
+/// several, otherwise in chunks of `chunk_size` rows. Either way the segments keep their file
+/// order. Connector rows of a mixed file are returned alongside the segments, segments outside
+/// `clip` are dropped.
+fn import_segments(segment_path: &Path, chunk_size: usize, threads: usize, clip: Option<&ClipBox>) -> std::io::Result<Data> {
+    let file = File::open(segment_path)?;
+    let reader = SerializedFileReader::new(file)?;
+
+    let row_groups = reader.num_row_groups();
+    if threads > 1 && row_groups > 1 {
+        debug!("Reading {} row groups of {} on {} threads", row_groups, segment_path.display(), threads);
+        return import_row_groups(segment_path, row_groups, threads, clip);
+    }
+    import_row_chunks(&reader, chunk_size, threads, clip)
 }
 
-fn get_point_for_connector(
-    connector_ref: &ConnectorRef,
-    all_connectors: &[Connector]
-) -> Option<Point> {
-    all_connectors.iter()
-        .find(|c| c.id == connector_ref.id)
-        .map(|c| c.coordinate.clone())
+/// Concatenate parsed parts in the order of their index
+fn merge_parsed(parsed: BTreeMap<usize, Data>) -> Data {
+    let mut data = Data { segments: Vec::new(), connectors: Vec::new() };
+    for part in parsed.into_values() {
+        data.segments.extend(part.segments);
+        data.connectors.extend(part.connectors);
+    }
+    data
 }
 
-fn get_connector_index_for_point(
-    point: &Point,
-    connector_refs: &[ConnectorRef],
-    all_connectors: &[Connector]
-) -> Option<usize>{
-    for (connector_ref_index, connector_ref) in connector_refs.iter().enumerate() {
-        let connector_point = get_point_for_connector(connector_ref, all_connectors);
-        if connector_point.is_some() {
-            let connector_point = connector_point.unwrap();
-            if (point.lat - connector_point.lat).abs() < 1e-6 &&
-               (point.lon - connector_point.lon).abs() < 1e-6 {
-                return Some(connector_ref_index);
+/// Parse each of the `row_groups` of the file on one of `threads` workers. Every worker opens
+/// the file itself, as readers sharing one handle would share its position too.
+fn import_row_groups(segment_path: &Path, row_groups: usize, threads: usize, clip: Option<&ClipBox>) -> std::io::Result<Data> {
+    let next_row_group = AtomicUsize::new(0);
+    let parsed_row_groups: Mutex<BTreeMap<usize, Data>> = Mutex::new(BTreeMap::new());
+
+    let parse_row_groups = || -> std::io::Result<()> {
+        let reader = SerializedFileReader::new(File::open(segment_path)?)?;
+        loop {
+            let index = next_row_group.fetch_add(1, Ordering::Relaxed);
+            if index >= row_groups {
+                return Ok(());
+            }
+            let mut data = Data { segments: Vec::new(), connectors: Vec::new() };
+            for row in reader.get_row_group(index)?.get_row_iter(None)? {
+                parse_transportation_row(row?, &mut data, clip)?;
             }
+            parsed_row_groups.lock().expect("parsed row groups poisoned").insert(index, data);
         }
-    }
+    };
 
-    None
+    thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads.min(row_groups))
+            .map(|_| scope.spawn(|| parse_row_groups().inspect_err(|_| {
+                // Leave the remaining row groups unread once one failed
+                next_row_group.store(row_groups, Ordering::Relaxed);
+            })))
+            .collect();
+        workers.into_iter().try_for_each(|worker| worker.join().expect("row group worker panicked"))
+    })?;
+
+    Ok(merge_parsed(parsed_row_groups.into_inner().expect("parsed row groups poisoned")))
 }
 
-fn process_segment(
-    segment: &Segment,
-    all_connectors: &[Connector],
-    next_index: &mut usize,
-    permissions: Permissions
-) -> ExportedRoad {
-    let mut exported_road = ExportedRoad {
-        points: Vec::new(),
-        permissions
-    };
+/// Parse the rows in chunks of `chunk_size` on `threads` workers. Chunks are numbered as they
+/// are read so the segments keep their file order whatever worker parsed them.
+fn import_row_chunks(reader: &SerializedFileReader<File>, chunk_size: usize, threads: usize, clip: Option<&ClipBox>) -> std::io::Result<Data> {
+    let iter = reader.get_row_iter(None)?;
 
-    for point in segment.points.iter() {
-        let connector_index = get_connector_index_for_point(point, &segment.connectors, all_connectors);
-        if connector_index.is_some() {
-            let connector_ref = &segment.connectors[connector_index.unwrap()];
-            let connector_osm_index = all_connectors.iter()
-                .position(|c| c.id == connector_ref.id)
-                .expect("Connector not found in all connectors");
-            exported_road.points.push(IndexedPoint {
-                index: connector_osm_index,
-                point: point.clone()
-            });
-        } else {
-            // If no connector found, just use the point itself
-            exported_road.points.push(IndexedPoint {
-                index: *next_index,
-                point: point.clone()
-            });
-            *next_index += 1;
+    let (sender, receiver) = sync_channel::<(usize, Vec<Row>)>(threads * 2);
+    let receiver = Mutex::new(receiver);
+    let parsed_chunks: Mutex<BTreeMap<usize, Data>> = Mutex::new(BTreeMap::new());
+
+    thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads)
+            .map(|_| scope.spawn(|| -> Result<(), WriterError> {
+                loop {
+                    let next_chunk = receiver.lock().expect("chunk receiver poisoned").recv();
+                    let Ok((chunk_index, rows)) = next_chunk else {
+                        return Ok(());
+                    };
+                    let mut data = Data { segments: Vec::with_capacity(rows.len()), connectors: Vec::new() };
+                    for row in rows {
+                        parse_transportation_row(row, &mut data, clip)?;
+                    }
+                    parsed_chunks.lock().expect("parsed chunks poisoned").insert(chunk_index, data);
+                }
+            }))
+            .collect();
+
+        let mut read_result: std::io::Result<()> = Ok(());
+        let mut chunk_index = 0;
+        let mut chunk = Vec::with_capacity(chunk_size);
+        for row in iter {
+            match row {
+                Ok(row) => chunk.push(row),
+                Err(err) => {
+                    read_result = Err(err.into());
+                    break;
+                }
+            }
+            if chunk.len() == chunk_size {
+                // Sending only fails once every worker stopped on an error, reported below
+                if sender.send((chunk_index, std::mem::take(&mut chunk))).is_err() {
+                    break;
+                }
+                chunk_index += 1;
+            }
         }
-    }
+        if read_result.is_ok() && !chunk.is_empty() {
+            let _ = sender.send((chunk_index, chunk));
+        }
+        // Closing the channel lets the workers finish
+        drop(sender);
+
+        for worker in workers {
+            worker.join().expect("segment worker panicked")?;
+        }
+        read_result
+    })?;
 
-    exported_road
+    Ok(merge_parsed(parsed_chunks.into_inner().expect("parsed chunks poisoned")))
 }
 
-fn export_roads(exported_roads: &[ExportedRoad], output_dir: &Path) -> std::io::Result<()> {
-    let mut ways = Vec::new();
-    let mut waynodes = Vec::new();
+/// Read segments and connectors. The connector file may be absent when the segments carry
+/// inline connector coordinates.
+pub fn import_overture_data(segment_path: &Path, connector_path: &Path) -> std::io::Result<Data> {
+    import_overture_data_with_options(segment_path, connector_path, &ConvertOptions::default())
+}
 
-    for (way_index, exported_road) in exported_roads.iter().enumerate() {
-        let node_count = exported_road.points.len() as u16;
-        let offset_way_index: u64 = way_index as u64 * 2;
-        let auto_allowed = exported_road.permissions.auto_allowed;
-        let pedestrian_allowed = exported_road.permissions.pedestrian_allowed;
-        ways.push(OsmWay::new(offset_way_index + 1, 1, node_count, auto_allowed, pedestrian_allowed));
-        ways.push(OsmWay::new(offset_way_index + 2, 1, node_count, auto_allowed, pedestrian_allowed));
+/// Like `import_overture_data`, parsing segments with the `threads` and `chunk_size` of `options`
+/// and keeping only those within its `clip` box
+pub fn import_overture_data_with_options(
+    segment_path: &Path,
+    connector_path: &Path,
+    options: &ConvertOptions
+) -> std::io::Result<Data> {
+    let Data { segments, connectors: mixed_connectors } =
+        import_segments(segment_path, options.chunk_size(), options.threads(), options.clip.as_ref())?;
 
-        // Valhalla complains when road is only one way, so for now we export it twice, this is the first time...
-        for (point_index, point) in exported_roads[way_index].points.iter().enumerate() {
-            // TODO: only make intersection if other way intersects
-            let intersection: u64 = 1;
+    let mut connectors: Vec<Connector> = Vec::new();
+    if connector_path.exists() {
+        connectors = import_connectors(connector_path)?;
+    } else if mixed_connectors.is_empty() {
+        info!("No connector file at {}, using inline connector coordinates", connector_path.display());
+    }
+    if !mixed_connectors.is_empty() {
+        info!("Read {} connectors mixed into {}", mixed_connectors.len(), segment_path.display());
+        // The connector file wins for connectors present in both
+        let known: HashSet<String> = connectors.iter().map(|connector| connector.id.clone()).collect();
+        connectors.extend(mixed_connectors.into_iter().filter(|connector| !known.contains(&connector.id)));
+    }
 
-            waynodes.push(OsmWayNode::new(
-                offset_way_index as u32,
-                point_index as u32,
-                point.index as u64,
-                point.point.lon,
-                point.point.lat,
-                intersection as u32,
-            ));
+    // Inline connector coordinates take precedence, the connector file fills in the rest
+    let mut connector_positions: HashMap<String, usize> = connectors
+        .iter()
+        .enumerate()
+        .map(|(index, connector)| (connector.id.clone(), index))
+        .collect();
+    for connector_ref in segments.iter().flat_map(|segment| &segment.connectors) {
+        let Some(coordinate) = &connector_ref.coordinate else {
+            continue;
+        };
+        match connector_positions.get(&connector_ref.id) {
+            Some(&index) => connectors[index].coordinate = coordinate.clone(),
+            None => {
+                connector_positions.insert(connector_ref.id.clone(), connectors.len());
+                connectors.push(Connector {
+                    id: connector_ref.id.clone(),
+                    coordinate: coordinate.clone(),
+                    node_kind: ValhallaNodeKind::default(),
+                    sources: Vec::new()
+                });
+            }
         }
+    }
 
-        // ... and this is the second time.
-        for (point_index, point) in exported_roads[way_index].points.iter().rev().enumerate() {
-            // TODO: only make intersection if other way intersects
-            let intersection: u64 = 1;
+    Ok(Data { segments, connectors })
+}
 
-            waynodes.push(OsmWayNode::new(
-                offset_way_index as u32,
-                point_index as u32,
-                point.index as u64,
-                point.point.lon,
-                point.point.lat,
-                intersection as u32,
-            ));
+/// Parse one connector row
+fn connector_from_row(row: Row) -> Result<Connector, WriterError>
+{
+    let mut id = String::new();
+    let mut coordinate: Option<Point> = None;
+    let mut subtype: Option<String> = None;
+    let mut sources: Vec<Source> = Vec::new();
+    for column in row.into_columns() {
+        if column.0 == "id" {
+            if let Field::Str(id_str) = column.1 {
+                id = id_str.to_string();
+            }
+        } else if column.0 == "geometry" {
+            if let Field::Bytes(byte_array) = column.1 {
+                coordinate = Some(parse_point_wkb(byte_array.data())?);
+            }
+        } else if column.0 == "subtype" {
+            if let Field::Str(value) = column.1 {
+                subtype = Some(value.to_string());
+            }
+        } else if column.0 == "sources" {
+            if let Field::ListInternal(source_list) = column.1 {
+                sources = process_sources(&source_list);
+            }
         }
     }
 
-    write(output_dir.join("ways.bin"), OsmWay::slice_as_bytes(&ways))?;
-    write(output_dir.join("way_nodes.bin"), OsmWayNode::slice_as_bytes(&waynodes))?;
-    Ok(())
+    let Some(coordinate) = coordinate else {
+        return Err(WriterError::MissingConnectorGeometry(id));
+    };
+    Ok(Connector {
+        id,
+        coordinate,
+        node_kind: map_node_kind(subtype.as_deref()),
+        sources
+    })
 }
 
-fn check_permissions(road_class: &str) -> Permissions {
-    let pedestrian_allowed = !matches!(
-        road_class,
-        "motorway" | "trunk" | "cycleway" | "standard_gauge"
-    );
+fn import_connectors(connector_path: &Path) -> std::io::Result<Vec<Connector>> {
+    let file = File::open(connector_path)?;
+    let reader = SerializedFileReader::new(file)?;
 
-    let auto_allowed = !matches!(
-        road_class,
-        "null" | "steps" | "path" | "living_street" | "pedestrian" | "footway" | "cycleway" | "standard_gauge"
-    );
+    let iter = reader.get_row_iter(None)?;
 
-    Permissions {
-        pedestrian_allowed,
-        auto_allowed,
+    let mut connectors: Vec<Connector> = Vec::new();
+    for row in iter {
+        connectors.push(connector_from_row(row?)?);
     }
+
+    Ok(connectors)
 }
 
-pub fn convert_overture_to_valhalla(input_dir : &Path, output_dir: &Path) -> std::io::Result<()>
-{
-    let segment_path = input_dir.join("segment.parquet");
-    let connector_path = input_dir.join("connector.parquet");
-    let overture_data = import_overture_data(&segment_path, &connector_path)?;
+fn point_problem(point: &Point) -> Option<String> {
+    if point.lat == 0.0 && point.lon == 0.0 {
+        Some("coordinate at (0, 0)".to_string())
+    } else if point.lat.abs() > 90.0 {
+        Some(format!("latitude {} out of range, lat/lon may be swapped", point.lat))
+    } else if point.lon.abs() > 180.0 {
+        Some(format!("longitude {} out of range", point.lon))
+    } else {
+        None
+    }
+}
 
-    let mut exported_roads: Vec<ExportedRoad> = Vec::new();
-    let mut next_index = 1;
-    for (index, segment) in overture_data.segments.iter().enumerate() {
-        let road_class: &str = segment.road_class.as_deref().unwrap_or("null");
+/// Reason a geometry is implausible, if any: null island, out-of-range (often swapped)
+/// coordinates, or a jump between consecutive vertices no real segment would make
+fn geometry_problem(points: &[Point]) -> Option<String> {
+    if let Some(problem) = points.iter().find_map(point_problem) {
+        return Some(problem);
+    }
+    points.windows(2).find_map(|pair| {
+        let jump = (pair[1].lat - pair[0].lat).hypot(pair[1].lon - pair[0].lon);
+        (jump > MAX_VERTEX_JUMP_DEGREES)
+            .then(|| format!("vertex jump of {:.3} degrees", jump))
+    })
+}
 
-        info!("Processing segment {} / {}: {} ({})", index + 1, overture_data.segments.len(), segment.name, road_class);
-        let permissions = check_permissions(road_class);
+/// Drop segments and connectors with implausible geometry, reporting why
+fn remove_implausible_geometry(data: &mut Data) {
+    let segment_count = data.segments.len();
+    data.segments.retain(|segment| match geometry_problem(&segment.points) {
+        Some(problem) => {
+            warn!("Skipping segment '{}': {}", segment.name, problem);
+            false
+        }
+        None => true,
+    });
 
-        if !permissions.auto_allowed && !permissions.pedestrian_allowed {
-            info!("- Ignored");
-            continue;
-        } else {
-            if permissions.auto_allowed {
-                info!("- Auto allowed");
-            }
-            if permissions.pedestrian_allowed {
-                info!("- Pedestrian allowed");
-            }
+    let connector_count = data.connectors.len();
+    data.connectors.retain(|connector| match point_problem(&connector.coordinate) {
+        Some(problem) => {
+            warn!("Skipping connector '{}': {}", connector.id, problem);
+            false
         }
+        None => true,
+    });
 
-        exported_roads.push(process_segment(segment, &overture_data.connectors, &mut next_index, permissions));
-    }
+    info!(
+        "Strict geometry removed {} segments and {} connectors",
+        segment_count - data.segments.len(),
+        connector_count - data.connectors.len()
+    );
+}
 
-    export_roads(&exported_roads, output_dir)?;
+/// Whether a segment belongs to the `sample_rate` sample for `seed`. Decided by a seeded FNV-1a
+/// hash of the Overture id, so the sample is the same on every run and platform.
+pub fn segment_in_sample(segment_id: &str, sample_rate: f64, seed: u64) -> bool {
+    const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
 
-    Ok(())
+    let hash = seed
+        .to_le_bytes()
+        .iter()
+        .chain(segment_id.as_bytes())
+        .fold(FNV_OFFSET_BASIS, |hash, byte| (hash ^ *byte as u64).wrapping_mul(FNV_PRIME));
+    (hash as f64 / u64::MAX as f64) < sample_rate
+}
+
+/// Keep the sampled segments; connectors are left alone so every connector a kept segment
+/// references is still there
+fn sample_segments(data: &mut Data, sample_rate: f64, seed: u64) {
+    let segment_count = data.segments.len();
+    data.segments.retain(|segment| segment_in_sample(&segment.id, sample_rate, seed));
+    info!(
+        "Sampled {} of {} segments (rate {}, seed {})",
+        data.segments.len(),
+        segment_count,
+        sample_rate,
+        seed
+    );
+}
+
+#[derive(Debug)]
+struct IndexedPoint {
+    index: usize,
+    point: Point,
+    node_kind: ValhallaNodeKind
+}
+
+#[derive(Debug)]
+struct ExportedRoad
+{
+    points: Vec<IndexedPoint>,
+    /// Way attributes, speed limits relative to the order of `points`
+    mapped: MappedSegment,
+    /// No node is a connector, all were synthesized
+    connectorless: bool
+}
+
+/// `value` rounded half away from zero to `decimals` decimal places
+pub(crate) fn round_coordinate(value: f64, decimals: u32) -> f64 {
+    let factor = 10f64.powi(decimals as i32);
+    (value * factor).round() / factor
+}
+
+fn points_within(a: &Point, b: &Point, match_precision: Option<u32>, tolerance: f64) -> bool {
+    let (a_lat, a_lon, b_lat, b_lon) = match match_precision {
+        Some(decimals) => (
+            round_coordinate(a.lat, decimals),
+            round_coordinate(a.lon, decimals),
+            round_coordinate(b.lat, decimals),
+            round_coordinate(b.lon, decimals),
+        ),
+        None => (a.lat, a.lon, b.lat, b.lon),
+    };
+
+    (a_lat - b_lat).abs() < tolerance &&
+    (a_lon - b_lon).abs() < tolerance
+}
+
+fn points_match(a: &Point, b: &Point, match_precision: Option<u32>) -> bool {
+    points_within(a, b, match_precision, CONNECTOR_MATCH_TOLERANCE)
+}
+
+/// Position of each vertex along the geometry as a fraction of its length
+fn vertex_fractions(points: &[Point]) -> Vec<f64> {
+    let mut fractions = Vec::with_capacity(points.len());
+    let mut length = 0.0;
+    fractions.push(0.0);
+    for pair in points.windows(2) {
+        length += (pair[1].lat - pair[0].lat).hypot(pair[1].lon - pair[0].lon);
+        fractions.push(length);
+    }
+    fractions.truncate(points.len());
+    if length > 0.0 {
+        fractions.iter_mut().for_each(|fraction| *fraction /= length);
+    }
+    fractions
+}
+
+/// Looks up the connectors referenced by segments: by id first, checking that the connector
+/// lies near the vertex at its `at` position, then by coordinate for the connectors left over
+struct ConnectorSearch<'a> {
+    connectors: &'a [Connector],
+    positions: HashMap<&'a str, usize>,
+    match_precision: Option<u32>,
+    snap_tolerance: f64
+}
+
+impl<'a> ConnectorSearch<'a> {
+    fn new(connectors: &'a [Connector], options: &ConvertOptions) -> Self {
+        let positions = connectors
+            .iter()
+            .enumerate()
+            .map(|(index, connector)| (connector.id.as_str(), index))
+            .collect();
+        ConnectorSearch {
+            connectors,
+            positions,
+            match_precision: options.match_precision,
+            snap_tolerance: options.connector_snap_tolerance()
+        }
+    }
+
+    /// Index of the connector in `connectors`
+    fn position(&self, connector_id: &str) -> Option<usize> {
+        self.positions.get(connector_id).copied()
+    }
+
+    fn coordinate(&self, connector_id: &str) -> Option<&Point> {
+        self.position(connector_id).map(|index| &self.connectors[index].coordinate)
+    }
+
+    /// Whether the connector lies at `point`
+    fn is_at(&self, point: &Point, connector_ref: &ConnectorRef) -> bool {
+        self.coordinate(&connector_ref.id)
+            .is_some_and(|coordinate| points_match(point, coordinate, self.match_precision))
+    }
+
+    /// Index into `segment.connectors` of the connector at each vertex of `segment`
+    fn match_vertices(&self, segment: &Segment) -> Vec<Option<usize>> {
+        let fractions = vertex_fractions(&segment.points);
+        let mut matches: Vec<Option<usize>> = vec![None; segment.points.len()];
+        let mut unplaced: Vec<usize> = Vec::new();
+        for (ref_index, connector_ref) in segment.connectors.iter().enumerate() {
+            let Some(coordinate) = self.coordinate(&connector_ref.id) else {
+                continue;
+            };
+            let at = connector_ref.at.value();
+            let vertex = fractions
+                .iter()
+                .enumerate()
+                .min_by(|a, b| (a.1 - at).abs().total_cmp(&(b.1 - at).abs()))
+                .map(|(vertex, _)| vertex);
+            match vertex {
+                Some(vertex) if matches[vertex].is_none()
+                    && points_within(&segment.points[vertex], coordinate, self.match_precision, self.snap_tolerance) => {
+                    matches[vertex] = Some(ref_index);
+                }
+                _ => unplaced.push(ref_index)
+            }
+        }
+
+        // Connectors away from their `at` vertex, e.g. on reverse digitized segments
+        for (vertex, point) in segment.points.iter().enumerate() {
+            if matches[vertex].is_none() {
+                matches[vertex] = unplaced
+                    .iter()
+                    .copied()
+                    .find(|&ref_index| self.is_at(point, &segment.connectors[ref_index]));
+            }
+        }
+
+        matches
+    }
+
+    /// Shape nodes of `segment` with the index into `segment.connectors` of the connector at
+    /// each. Connectors whose `at` falls between two vertices and that matched no vertex get a
+    /// node inserted at that position along the geometry.
+    fn shape_nodes(&self, segment: &Segment) -> Vec<(Point, Option<usize>)> {
+        let matches = self.match_vertices(segment);
+        let fractions = vertex_fractions(&segment.points);
+        let mut nodes: Vec<(f64, Point, Option<usize>)> = fractions
+            .iter()
+            .zip(&segment.points)
+            .zip(&matches)
+            .map(|((&fraction, point), &connector_index)| (fraction, point.clone(), connector_index))
+            .collect();
+
+        let mut inserted = false;
+        for (ref_index, connector_ref) in segment.connectors.iter().enumerate() {
+            if self.position(&connector_ref.id).is_none() || matches.contains(&Some(ref_index)) {
+                continue;
+            }
+            let at = connector_ref.at.value();
+            let Some(vertex) = fractions
+                .windows(2)
+                .position(|pair| pair[0] + FRACTION_EPSILON < at && at < pair[1] - FRACTION_EPSILON)
+            else {
+                continue;
+            };
+            let (from, to) = (&segment.points[vertex], &segment.points[vertex + 1]);
+            let t = (at - fractions[vertex]) / (fractions[vertex + 1] - fractions[vertex]);
+            let point = Point {
+                lat: from.lat + (to.lat - from.lat) * t,
+                lon: from.lon + (to.lon - from.lon) * t
+            };
+            debug!("Inserting a node for connector '{}' at {} along segment '{}'", connector_ref.id, at, segment.id);
+            nodes.push((at, point, Some(ref_index)));
+            inserted = true;
+        }
+
+        if inserted {
+            // Stable, so an inserted node never precedes a vertex at the same position
+            nodes.sort_by(|a, b| a.0.total_cmp(&b.0));
+        }
+        nodes.into_iter().map(|(_, point, connector_index)| (point, connector_index)).collect()
+    }
+}
+
+/// Nodes of a segment with their ids, `None` when it is connectorless and `policy` skips it
+fn process_segment(
+    segment: &Segment,
+    search: &ConnectorSearch,
+    next_index: &mut usize,
+    mapped: MappedSegment,
+    policy: ConnectorlessPolicy
+) -> Result<Option<ExportedRoad>, WriterError> {
+    let shape_nodes = search.shape_nodes(segment);
+    let connectorless = shape_nodes.iter().all(|(_, connector_index)| connector_index.is_none());
+    if connectorless {
+        match policy {
+            ConnectorlessPolicy::Skip => {
+                warn!("Skipping segment '{}' without connectors", segment.id);
+                return Ok(None);
+            }
+            ConnectorlessPolicy::Synthesize => {
+                debug!("Segment '{}' has no connectors, synthesizing its nodes", segment.id);
+            }
+            ConnectorlessPolicy::Error => return Err(WriterError::ConnectorlessSegment(segment.id.clone())),
+        }
+    }
+
+    let mut exported_road = ExportedRoad {
+        points: Vec::new(),
+        mapped,
+        connectorless
+    };
+
+    // `at` of each matched connector, in geometry order
+    let mut matched_at: Vec<f64> = Vec::new();
+    for (point, connector_index) in shape_nodes {
+        if let Some(connector_index) = connector_index {
+            let connector_ref = &segment.connectors[connector_index];
+            matched_at.push(connector_ref.at.value());
+            let connector_osm_index = search.position(&connector_ref.id)
+                .expect("Connector not found in all connectors");
+            exported_road.points.push(IndexedPoint {
+                index: connector_osm_index,
+                point,
+                node_kind: search.connectors[connector_osm_index].node_kind
+            });
+        } else {
+            // If no connector found, just use the point itself
+            exported_road.points.push(IndexedPoint {
+                index: *next_index,
+                point,
+                node_kind: ValhallaNodeKind::default()
+            });
+            *next_index += 1;
+        }
+    }
+
+    match connector_order(&matched_at) {
+        ConnectorOrder::Ascending => {}
+        ConnectorOrder::Descending => {
+            warn!("Segment '{}' is digitized against its connector positions, reversing its points", segment.id);
+            exported_road.points.reverse();
+            exported_road.mapped.speed = exported_road.mapped.speed.reversed();
+        }
+        ConnectorOrder::Inconsistent => {
+            warn!("Segment '{}' has connector positions that are not monotonic along its geometry: {:?}", segment.id, matched_at);
+        }
+    }
+
+    Ok(Some(exported_road))
+}
+
+#[derive(Debug, PartialEq)]
+enum ConnectorOrder {
+    Ascending,
+    Descending,
+    Inconsistent,
+}
+
+/// How the `at` values of a segment's connectors run along its geometry
+fn connector_order(at_values: &[f64]) -> ConnectorOrder {
+    if at_values.windows(2).all(|pair| pair[0] <= pair[1]) {
+        ConnectorOrder::Ascending
+    } else if at_values.windows(2).all(|pair| pair[0] >= pair[1]) {
+        ConnectorOrder::Descending
+    } else {
+        ConnectorOrder::Inconsistent
+    }
+}
+
+/// Way ids of a road and of its reversed duplicate, from its index among all exported roads
+fn road_way_ids(road_index: usize) -> [u64; 2] {
+    let offset_way_id = road_index as u64 * 2;
+    [offset_way_id + 1, offset_way_id + 2]
+}
+
+/// Largest way id and way index Valhalla keeps: `OSMWayNode::way_index` is 32 bits, as is the
+/// way id in its edge info without the extended id bits
+const MAX_WAY_ID: u64 = u32::MAX as u64;
+
+/// Most roads whose way ids and way indices fit `MAX_WAY_ID`. Every road takes two way ids,
+/// see `road_way_ids`, whether or not its reversed duplicate is written.
+fn max_roads() -> u64 {
+    MAX_WAY_ID / 2
+}
+
+/// Fail before writing anything when `road_count` roads would overflow the way ids or way
+/// indices, which would otherwise wrap around silently
+fn check_way_capacity(road_count: usize) -> Result<(), WriterError> {
+    let roads = road_count as u64;
+    if roads > max_roads() {
+        return Err(WriterError::TooManyWays { roads, max_roads: max_roads() });
+    }
+    Ok(())
+}
+
+/// Ways and way nodes for one road; way node `way_index` values are local to the road.
+/// With `emit_reverse_ways` the road is written a second time with its nodes reversed.
+fn road_output(road_index: usize, exported_road: &ExportedRoad, emit_reverse_ways: bool) -> SegmentOutput {
+    let mut output = SegmentOutput::default();
+
+    let node_count = exported_road.points.len() as u16;
+    let mapped = &exported_road.mapped;
+    // The second way has the nodes in reverse order, so its directions swap
+    let speeds = [mapped.speed, mapped.speed.reversed()];
+    let way_count = if emit_reverse_ways { 2 } else { 1 };
+    for (way_id, speed) in road_way_ids(road_index).into_iter().zip(speeds).take(way_count) {
+        let mut way = OsmWay::new(way_id, mapped.name_index, node_count, mapped.access.auto, mapped.access.pedestrian);
+        way.set_road_class(mapped.road_class);
+        way.set_surface(mapped.surface);
+        way.set_bicycle(mapped.access.bicycle, mapped.cycle_lane, mapped.bicycle_dismount());
+        way.set_use(mapped.way_use);
+        way.set_speed(speed);
+        output.ways.push(way);
+    }
+
+    // Valhalla complains when road is only one way, so for now we export it twice, this is the first time...
+    for (point_index, point) in exported_road.points.iter().enumerate() {
+        // TODO: only make intersection if other way intersects
+        let intersection: u64 = 1;
+
+        let mut node = OsmNode::new(point.index as u64, point.point.lon, point.point.lat, intersection as u32);
+        node.set_node_kind(point.node_kind);
+        output.way_nodes.push(OsmWayNode::from_node(node, 0, point_index as u32));
+    }
+
+    if !emit_reverse_ways {
+        return output;
+    }
+
+    // ... and this is the second time.
+    for (point_index, point) in exported_road.points.iter().rev().enumerate() {
+        // TODO: only make intersection if other way intersects
+        let intersection: u64 = 1;
+
+        let mut node = OsmNode::new(point.index as u64, point.point.lon, point.point.lat, intersection as u32);
+        node.set_node_kind(point.node_kind);
+        output.way_nodes.push(OsmWayNode::from_node(node, 0, point_index as u32));
+    }
+
+    output
+}
+
+/// Write `roads` with their index among all exported roads, which sets their way ids, to
+/// `ways.bin` and `way_nodes.bin` in `output_dir`, prefixed with `prefix`
+fn write_roads<'a>(
+    roads: impl Iterator<Item = (usize, &'a ExportedRoad)>,
+    output_dir: &Path,
+    prefix: &str,
+    emit_reverse_ways: bool
+) -> std::io::Result<usize> {
+    let output = ConversionOutput::new();
+    let mut road_count = 0;
+    for (road_index, exported_road) in roads {
+        output.append(road_index, road_output(road_index, exported_road, emit_reverse_ways));
+        road_count += 1;
+    }
+    let (ways, waynodes) = output.finish();
+
+    write(output_dir.join(format!("{}ways.bin", prefix)), OsmWay::slice_as_bytes(&ways))?;
+    write(output_dir.join(format!("{}way_nodes.bin", prefix)), OsmWayNode::slice_as_bytes(&waynodes))?;
+    Ok(road_count)
+}
+
+fn export_roads(exported_roads: &[ExportedRoad], output_dir: &Path, emit_reverse_ways: bool) -> std::io::Result<()> {
+    check_way_capacity(exported_roads.len())?;
+    write_roads(exported_roads.iter().enumerate(), output_dir, "", emit_reverse_ways)?;
+    Ok(())
+}
+
+/// Travel modes written by `ConvertOptions::split_by_mode`
+pub const TRAVEL_MODES: [&str; 3] = ["auto", "pedestrian", "bicycle"];
+
+fn mode_allowed(access: &ValhallaAccess, mode: &str) -> bool {
+    match mode {
+        "auto" => access.auto,
+        "pedestrian" => access.pedestrian,
+        "bicycle" => access.bicycle,
+        _ => false,
+    }
+}
+
+/// Write one `<mode>_ways.bin`/`<mode>_way_nodes.bin` pair per travel mode. Way ids and node
+/// ids are kept, so nodes shared by several modes appear in each of their files.
+fn export_roads_by_mode(exported_roads: &[ExportedRoad], output_dir: &Path, emit_reverse_ways: bool) -> std::io::Result<()> {
+    for mode in TRAVEL_MODES {
+        let roads = exported_roads.iter().enumerate().filter(|(_, road)| mode_allowed(&road.mapped.access, mode));
+        let road_count = write_roads(roads, output_dir, &format!("{}_", mode), emit_reverse_ways)?;
+        info!("Wrote {} roads open to {}", road_count, mode);
+    }
+    Ok(())
+}
+
+/// Convert the segments and connectors in `input_dir` into `ways.bin` and `way_nodes.bin`
+/// in `output_dir`, returning tallies of the mapping decisions
+pub fn convert_overture_to_valhalla(
+    input_dir : &Path,
+    output_dir: &Path,
+    options: &ConvertOptions
+) -> std::io::Result<MappingMetrics>
+{
+    convert_overture_to_valhalla_with_timings(input_dir, output_dir, options, &mut StageTimings::new())
+}
+
+/// `convert_overture_to_valhalla`, recording the duration of the import, mapping and export
+/// stages in `timings`
+pub fn convert_overture_to_valhalla_with_timings(
+    input_dir : &Path,
+    output_dir: &Path,
+    options: &ConvertOptions,
+    timings: &mut StageTimings
+) -> std::io::Result<MappingMetrics>
+{
+    let start = Instant::now();
+    let segment_path = input_dir.join("segment.parquet");
+    let connector_path = input_dir.join("connector.parquet");
+    let mut overture_data = import_overture_data_with_options(&segment_path, &connector_path, options)?;
+
+    if options.precheck {
+        let report = precheck_connector_refs(&overture_data);
+        if !report.is_clean() {
+            for (segment_id, connector_id) in &report.examples {
+                warn!("Segment '{}' references missing connector '{}'", segment_id, connector_id);
+            }
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "{} of {} connector references point to missing connectors",
+                    report.dangling_refs,
+                    report.connector_refs
+                ),
+            ));
+        }
+        info!("Precheck passed: all {} connector references resolve", report.connector_refs);
+    }
+
+    if options.strict_geometry {
+        remove_implausible_geometry(&mut overture_data);
+    }
+
+    if let Some(sample_rate) = options.sample_rate {
+        sample_segments(&mut overture_data, sample_rate, options.seed);
+    }
+    timings.record("import", start.elapsed());
+
+    let start = Instant::now();
+    let search = ConnectorSearch::new(&overture_data.connectors, options);
+    let mut ctx = MappingContext::new();
+    let mut metrics = MappingMetrics::default();
+    let mut exported_roads: Vec<ExportedRoad> = Vec::new();
+    let mut source_records = SourceRecords::default();
+    let mut next_index = 1;
+    for (index, segment) in overture_data.segments.iter().enumerate() {
+        let road_class: &str = segment.road_class.as_deref().unwrap_or("null");
+
+        info!("Processing segment {} / {}: {} ({})", index + 1, overture_data.segments.len(), segment.name, road_class);
+        let mapped = map_segment(segment, &mut ctx);
+        metrics.record(mapped.road_class, mapped.surface, &mapped.access);
+
+        if !mapped.is_routable() {
+            info!("- Ignored");
+            continue;
+        } else {
+            if mapped.access.auto {
+                info!("- Auto allowed");
+            }
+            if mapped.access.pedestrian {
+                info!("- Pedestrian allowed");
+            }
+            if mapped.access.bicycle {
+                info!("- Bicycle allowed");
+            }
+        }
+
+        let Some(exported_road) = process_segment(
+            segment,
+            &search,
+            &mut next_index,
+            mapped,
+            options.connectorless_policy,
+        )? else {
+            metrics.connectorless.skipped += 1;
+            continue;
+        };
+        if exported_road.connectorless {
+            metrics.connectorless.synthesized += 1;
+        }
+
+        if options.preserve_sources {
+            let way_count = if options.emit_reverse_ways() { 2 } else { 1 };
+            source_records.ways.push(WaySources {
+                way_ids: road_way_ids(exported_roads.len())[..way_count].to_vec(),
+                segment_id: segment.id.clone(),
+                sources: segment.sources.clone(),
+            });
+        }
+        exported_roads.push(exported_road);
+    }
+
+    if exported_roads.is_empty() {
+        if !options.allow_empty {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "No routable segments found in {} ({} segments read); Valhalla cannot build a graph without edges",
+                    input_dir.display(),
+                    overture_data.segments.len()
+                ),
+            ));
+        }
+        warn!("No routable segments found, writing empty output files");
+    }
+    timings.record("mapping", start.elapsed());
+
+    let start = Instant::now();
+    export_roads(&exported_roads, output_dir, options.emit_reverse_ways())?;
+    export_strings(ctx.names(), &output_dir.join(STRINGS_FILE))?;
+    if options.split_by_mode {
+        export_roads_by_mode(&exported_roads, output_dir, options.emit_reverse_ways())?;
+    }
+
+    let restrictions = collect_restrictions(&overture_data);
+    if !restrictions.is_empty() {
+        info!(
+            "Writing {} restrictions and {} time-dependent restrictions",
+            restrictions.restrictions.len(),
+            restrictions.time_restrictions.len()
+        );
+        export_restrictions(&restrictions, &output_dir.join(RESTRICTIONS_FILE))?;
+    }
+
+    if options.preserve_sources {
+        // Connector nodes are numbered by their position among all connectors
+        source_records.nodes = overture_data
+            .connectors
+            .iter()
+            .enumerate()
+            .filter(|(_, connector)| !connector.sources.is_empty())
+            .map(|(index, connector)| NodeSources {
+                node_id: index as u64,
+                connector_id: connector.id.clone(),
+                sources: connector.sources.clone(),
+            })
+            .collect();
+        info!("Writing sources of {} ways and {} nodes", source_records.ways.len(), source_records.nodes.len());
+        export_sources(&source_records, &output_dir.join(SOURCES_FILE))?;
+    }
+    timings.record("export", start.elapsed());
+
+    Ok(metrics)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use arrow::array::{
+        Array, ArrayRef, BinaryArray, Float64Array, Float64Builder, ListArray, ListBuilder, RecordBatch, StringArray,
+        StringBuilder, StructArray, StructBuilder,
+    };
+    use arrow::buffer::OffsetBuffer;
+    use arrow::datatypes::{DataType, Field as ArrowField, Fields, Schema};
+    use parquet::arrow::ArrowWriter;
+    use parquet::file::writer::SerializedFileWriter;
+    use parquet::schema::parser::parse_message_type;
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::mapping::{DirectionalSpeed, ValhallaCycleLane, ValhallaRoadClass, ValhallaSurface};
+    use crate::metrics::ConnectorlessOutcomes;
+
+    struct TestConnectorRef {
+        id: &'static str,
+        at: f64,
+        lon_lat: Option<(f64, f64)>,
+    }
+
+    struct TestSegment<'a> {
+        id: &'a str,
+        name: &'static str,
+        class: &'static str,
+        points: Vec<(f64, f64)>,
+        connectors: Vec<TestConnectorRef>,
+    }
+
+    fn linestring_wkb(points: &[(f64, f64)]) -> Vec<u8> {
+        let mut wkb = vec![1u8];
+        wkb.extend_from_slice(&2u32.to_le_bytes());
+        wkb.extend_from_slice(&(points.len() as u32).to_le_bytes());
+        for (lon, lat) in points {
+            wkb.extend_from_slice(&lon.to_le_bytes());
+            wkb.extend_from_slice(&lat.to_le_bytes());
+        }
+        wkb
+    }
+
+    fn write_segment_parquet(path: &Path, segments: &[TestSegment]) {
+        write_segment_row_groups(path, segments, segments.len().max(1));
+    }
+
+    /// Like `write_segment_parquet`, starting a new row group every `rows_per_group` segments
+    fn write_segment_row_groups(path: &Path, segments: &[TestSegment], rows_per_group: usize) {
+        let batches: Vec<RecordBatch> = segments.chunks(rows_per_group).map(segment_batch).collect();
+        let schema = batches.first().map_or_else(|| segment_batch(&[]).schema(), |batch| batch.schema());
+        let mut writer = ArrowWriter::try_new(File::create(path).unwrap(), schema, None).unwrap();
+        for batch in &batches {
+            writer.write(batch).unwrap();
+            // Flushing closes the row group
+            writer.flush().unwrap();
+        }
+        writer.close().unwrap();
+    }
+
+    fn segment_batch(segments: &[TestSegment]) -> RecordBatch {
+        let ids: ArrayRef = Arc::new(StringArray::from_iter_values(segments.iter().map(|s| s.id)));
+        let classes: ArrayRef =
+            Arc::new(StringArray::from_iter_values(segments.iter().map(|s| s.class)));
+        let names: ArrayRef = Arc::new(StructArray::from(vec![(
+            Arc::new(ArrowField::new("primary", DataType::Utf8, true)),
+            Arc::new(StringArray::from_iter_values(segments.iter().map(|s| s.name))) as ArrayRef,
+        )]));
+        let geometry: ArrayRef = Arc::new(BinaryArray::from_iter_values(
+            segments.iter().map(|s| linestring_wkb(&s.points)),
+        ));
+
+        let connector_fields = Fields::from(vec![
+            ArrowField::new("connector_id", DataType::Utf8, true),
+            ArrowField::new("at", DataType::Float64, true),
+            ArrowField::new("lon", DataType::Float64, true),
+            ArrowField::new("lat", DataType::Float64, true),
+        ]);
+        let mut connectors = ListBuilder::new(StructBuilder::from_fields(connector_fields, 0));
+        for segment in segments {
+            for connector in &segment.connectors {
+                let refs = connectors.values();
+                refs.field_builder::<StringBuilder>(0).unwrap().append_value(connector.id);
+                refs.field_builder::<Float64Builder>(1).unwrap().append_value(connector.at);
+                refs.field_builder::<Float64Builder>(2)
+                    .unwrap()
+                    .append_option(connector.lon_lat.map(|(lon, _)| lon));
+                refs.field_builder::<Float64Builder>(3)
+                    .unwrap()
+                    .append_option(connector.lon_lat.map(|(_, lat)| lat));
+                refs.append(true);
+            }
+            connectors.append(true);
+        }
+        let connectors: ArrayRef = Arc::new(connectors.finish());
+
+        let columns = [
+            ("id", ids),
+            ("names", names),
+            ("class", classes),
+            ("geometry", geometry),
+            ("connectors", connectors),
+        ];
+        let schema = Arc::new(Schema::new(
+            columns
+                .iter()
+                .map(|(name, array)| ArrowField::new(*name, array.data_type().clone(), true))
+                .collect::<Vec<_>>(),
+        ));
+        RecordBatch::try_new(schema, columns.into_iter().map(|(_, a)| a).collect()).unwrap()
+    }
+
+    fn write_connector_parquet(path: &Path, connectors: &[(&str, (f64, f64), Option<&str>)]) {
+        let ids: ArrayRef = Arc::new(StringArray::from_iter_values(connectors.iter().map(|c| c.0)));
+        let geometry: ArrayRef = Arc::new(BinaryArray::from_iter_values(connectors.iter().map(|(_, (lon, lat), _)| point_wkb(*lon, *lat))));
+        let subtypes: ArrayRef = Arc::new(StringArray::from_iter(connectors.iter().map(|c| c.2)));
+
+        let batch = RecordBatch::try_from_iter([("id", ids), ("geometry", geometry), ("subtype", subtypes)]).unwrap();
+        let mut writer = ArrowWriter::try_new(File::create(path).unwrap(), batch.schema(), None).unwrap();
+        writer.write(&batch).unwrap();
+        writer.close().unwrap();
+    }
+
+    fn point_wkb(lon: f64, lat: f64) -> Vec<u8> {
+        let mut wkb = vec![1u8];
+        wkb.extend_from_slice(&1u32.to_le_bytes());
+        wkb.extend_from_slice(&lon.to_le_bytes());
+        wkb.extend_from_slice(&lat.to_le_bytes());
+        wkb
+    }
+
+    /// Transportation file with segment and connector rows told apart by `type`
+    fn write_mixed_parquet(path: &Path, rows: &[(&str, &str, Vec<u8>)]) {
+        let ids: ArrayRef = Arc::new(StringArray::from_iter_values(rows.iter().map(|r| r.0)));
+        let types: ArrayRef = Arc::new(StringArray::from_iter_values(rows.iter().map(|r| r.1)));
+        let geometry: ArrayRef = Arc::new(BinaryArray::from_iter_values(rows.iter().map(|r| r.2.as_slice())));
+        let classes: ArrayRef = Arc::new(StringArray::from_iter(
+            rows.iter().map(|r| (r.1 == "segment").then_some("residential")),
+        ));
+
+        let batch =
+            RecordBatch::try_from_iter([("id", ids), ("type", types), ("geometry", geometry), ("class", classes)])
+                .unwrap();
+        let mut writer = ArrowWriter::try_new(File::create(path).unwrap(), batch.schema(), None).unwrap();
+        writer.write(&batch).unwrap();
+        writer.close().unwrap();
+    }
+
+    #[test]
+    fn test_import_mixed_segment_connector_rows() {
+        let input_dir = tempdir().unwrap();
+        let segment_path = input_dir.path().join("segment.parquet");
+        write_mixed_parquet(
+            &segment_path,
+            &[
+                ("c1", "connector", point_wkb(-122.3, 47.6)),
+                ("s1", "segment", linestring_wkb(&[(-122.3, 47.6), (-122.31, 47.61)])),
+                ("c2", "connector", point_wkb(-122.31, 47.61)),
+                ("s2", "segment", linestring_wkb(&[(-122.31, 47.61), (-122.32, 47.62)])),
+            ],
+        );
+
+        let options = ConvertOptions { chunk_size: Some(1), threads: Some(2), ..Default::default() };
+        let data =
+            import_overture_data_with_options(&segment_path, &input_dir.path().join("connector.parquet"), &options)
+                .unwrap();
+        let segment_ids: Vec<&str> = data.segments.iter().map(|s| s.id.as_str()).collect();
+        assert_eq!(segment_ids, vec!["s1", "s2"]);
+        let connector_ids: Vec<&str> = data.connectors.iter().map(|c| c.id.as_str()).collect();
+        assert_eq!(connector_ids, vec!["c1", "c2"]);
+        assert_eq!(data.connectors[1].coordinate.lat, 47.61);
+
+        write_mixed_parquet(&segment_path, &[("p1", "place", point_wkb(-122.3, 47.6))]);
+        let err = import_overture_data(&segment_path, &input_dir.path().join("connector.parquet")).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert!(err.to_string().contains("unexpected feature type 'place'"));
+    }
+
+    #[test]
+    fn test_connector_without_geometry_is_an_error() {
+        let input_dir = tempdir().unwrap();
+        let segment_path = input_dir.path().join("segment.parquet");
+        let ids: ArrayRef = Arc::new(StringArray::from(vec!["c1"]));
+        let types: ArrayRef = Arc::new(StringArray::from(vec!["connector"]));
+        let geometry: ArrayRef = Arc::new(BinaryArray::from(vec![None::<&[u8]>]));
+        let batch = RecordBatch::try_from_iter([("id", ids), ("type", types), ("geometry", geometry)]).unwrap();
+        let mut writer = ArrowWriter::try_new(File::create(&segment_path).unwrap(), batch.schema(), None).unwrap();
+        writer.write(&batch).unwrap();
+        writer.close().unwrap();
+
+        let err = import_overture_data(&segment_path, &input_dir.path().join("connector.parquet")).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert!(err.to_string().contains("connector 'c1' has no point geometry"), "{}", err);
+    }
+
+    fn write_empty_parquet(path: &Path, message_type: &str) {
+        let schema = Arc::new(parse_message_type(message_type).unwrap());
+        let file = File::create(path).unwrap();
+        let writer = SerializedFileWriter::new(file, schema, Default::default()).unwrap();
+        writer.close().unwrap();
+    }
+
+    fn write_empty_input(input_dir: &Path) {
+        write_empty_parquet(
+            &input_dir.join("segment.parquet"),
+            "message segment { optional binary id (STRING); optional binary geometry; }",
+        );
+        write_empty_parquet(
+            &input_dir.join("connector.parquet"),
+            "message connector { optional binary id (STRING); optional binary geometry; }",
+        );
+    }
+
+    #[test]
+    fn test_convert_empty_input_errors() {
+        let input_dir = tempdir().unwrap();
+        let output_dir = tempdir().unwrap();
+        write_empty_input(input_dir.path());
+
+        let err = convert_overture_to_valhalla(
+            input_dir.path(),
+            output_dir.path(),
+            &ConvertOptions::default(),
+        )
+        .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert!(err.to_string().contains("No routable segments"));
+        assert!(!output_dir.path().join("ways.bin").exists());
+    }
+
+    #[test]
+    fn test_convert_empty_input_allowed() {
+        let input_dir = tempdir().unwrap();
+        let output_dir = tempdir().unwrap();
+        write_empty_input(input_dir.path());
+
+        let options = ConvertOptions { allow_empty: true, ..Default::default() };
+        convert_overture_to_valhalla(input_dir.path(), output_dir.path(), &options).unwrap();
+        assert_eq!(std::fs::metadata(output_dir.path().join("ways.bin")).unwrap().len(), 0);
+        assert_eq!(std::fs::metadata(output_dir.path().join("way_nodes.bin")).unwrap().len(), 0);
+    }
+
+    fn point(lon: f64, lat: f64) -> Point {
+        Point { lat, lon }
+    }
+
+    fn connector(id: &str, coordinate: Point) -> Connector {
+        Connector { id: id.to_string(), coordinate, ..Default::default() }
+    }
+
+    fn search(connectors: &[Connector]) -> ConnectorSearch<'_> {
+        ConnectorSearch::new(connectors, &ConvertOptions::default())
+    }
+
+    fn mapped(segment: &Segment) -> MappedSegment {
+        map_segment(segment, &mut MappingContext::new())
+    }
+
+    /// `process_segment` with the default connectorless policy, which exports every segment
+    fn exported_road(segment: &Segment, search: &ConnectorSearch, next_index: &mut usize) -> ExportedRoad {
+        process_segment(segment, search, next_index, mapped(segment), ConnectorlessPolicy::Synthesize).unwrap().unwrap()
+    }
+
+    fn segment(name: &str, points: Vec<Point>) -> Segment {
+        Segment {
+            id: name.to_string(),
+            name: name.to_string(),
+            road_class: Some("residential".to_string()),
+            subtype: Some("road".to_string()),
+            points,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_geometry_problem() {
+        assert_eq!(geometry_problem(&[point(-122.3, 47.6), point(-122.31, 47.61)]), None);
+        assert!(geometry_problem(&[point(0.0, 0.0), point(-122.31, 47.61)])
+            .unwrap()
+            .contains("(0, 0)"));
+        assert!(geometry_problem(&[point(47.6, -122.3)]).unwrap().contains("swapped"));
+        assert!(geometry_problem(&[point(-122.3, 47.6), point(-110.0, 47.6)])
+            .unwrap()
+            .contains("vertex jump"));
+    }
+
+    #[test]
+    fn test_remove_implausible_geometry() {
+        let mut data = Data {
+            segments: vec![
+                segment("good", vec![point(-122.3, 47.6), point(-122.31, 47.61)]),
+                segment("null island", vec![point(0.0, 0.0), point(-122.31, 47.61)]),
+            ],
+            connectors: vec![
+                connector("good", point(-122.3, 47.6)),
+                connector("swapped", point(47.6, -122.3)),
+            ],
+        };
+
+        remove_implausible_geometry(&mut data);
+        assert_eq!(data.segments.len(), 1);
+        assert_eq!(data.segments[0].name, "good");
+        assert_eq!(data.connectors.len(), 1);
+        assert_eq!(data.connectors[0].id, "good");
+    }
+
+    #[test]
+    fn test_import_inline_connector_coordinates_without_connector_file() {
+        let input_dir = tempdir().unwrap();
+        let segment_path = input_dir.path().join("segment.parquet");
+        write_segment_parquet(
+            &segment_path,
+            &[TestSegment {
+                id: "s1",
+                name: "Main St",
+                class: "residential",
+                points: vec![(-122.3, 47.6), (-122.31, 47.61), (-122.32, 47.62)],
+                connectors: vec![
+                    TestConnectorRef { id: "c1", at: 0.0, lon_lat: Some((-122.3, 47.6)) },
+                    TestConnectorRef { id: "c2", at: 1.0, lon_lat: Some((-122.32, 47.62)) },
+                ],
+            }],
+        );
+
+        let data =
+            import_overture_data(&segment_path, &input_dir.path().join("connector.parquet"))
+                .unwrap();
+        assert_eq!(data.segments.len(), 1);
+        assert_eq!(data.segments[0].id, "s1");
+        assert_eq!(data.segments[0].name, "Main St");
+        assert_eq!(data.connectors.len(), 2);
+        assert_eq!(data.connectors[1].id, "c2");
+        assert_eq!(data.connectors[1].coordinate.lon, -122.32);
+        assert_eq!(data.connectors[1].coordinate.lat, 47.62);
+
+        let mut next_index = data.connectors.len();
+        let road = exported_road(&data.segments[0], &search(&data.connectors), &mut next_index);
+        let indices: Vec<usize> = road.points.iter().map(|p| p.index).collect();
+        assert_eq!(indices, vec![0, 2, 1]);
+        assert_eq!(road.points[2].point.lat, 47.62);
+        assert_eq!(road.points[2].point.lon, -122.32);
+    }
+
+    #[test]
+    fn test_import_falls_back_to_connector_file() {
+        let input_dir = tempdir().unwrap();
+        let segment_path = input_dir.path().join("segment.parquet");
+        write_segment_parquet(
+            &segment_path,
+            &[TestSegment {
+                id: "s1",
+                name: "Main St",
+                class: "residential",
+                points: vec![(-122.3, 47.6), (-122.32, 47.62)],
+                connectors: vec![
+                    TestConnectorRef { id: "c1", at: 0.0, lon_lat: Some((-122.3, 47.6)) },
+                    TestConnectorRef { id: "c2", at: 1.0, lon_lat: None },
+                ],
+            }],
+        );
+        write_empty_parquet(
+            &input_dir.path().join("connector.parquet"),
+            "message connector { optional binary id (STRING); optional binary geometry; }",
+        );
+
+        let data =
+            import_overture_data(&segment_path, &input_dir.path().join("connector.parquet"))
+                .unwrap();
+        // Only the inline connector is known, c2 has neither inline nor file coordinates
+        assert_eq!(data.connectors.len(), 1);
+        assert_eq!(data.connectors[0].id, "c1");
+        assert!(data.segments[0].connectors[1].coordinate.is_none());
+    }
+
+    #[test]
+    fn test_match_precision_recovers_drifted_connector() {
+        let vertex = point(-122.3000001, 47.6000001);
+        let connectors = vec![connector("c1", point(-122.3000012, 47.6000012))];
+        let connector_refs = vec![ConnectorRef { id: "c1".to_string(), at: AlongFraction::START, coordinate: None }];
+
+        let precise = search(&connectors);
+        let rounded = ConnectorSearch::new(&connectors, &ConvertOptions { match_precision: Some(5), ..Default::default() });
+        assert!(!precise.is_at(&vertex, &connector_refs[0]));
+        assert!(rounded.is_at(&vertex, &connector_refs[0]));
+
+        let mut drifted = segment("drifted", vec![vertex.clone(), point(-122.31, 47.61)]);
+        drifted.connectors = connector_refs;
+        let mut next_index = connectors.len();
+        let road = exported_road(&drifted, &rounded, &mut next_index);
+        assert_eq!(road.points[0].index, 0);
+        // Output keeps the unrounded vertex
+        assert_eq!(road.points[0].point.lat, 47.6000001);
+        assert_eq!(road.points[0].point.lon, -122.3000001);
+    }
+
+    #[test]
+    fn test_id_match_beyond_coordinate_tolerance() {
+        // Both connectors sit just beyond CONNECTOR_MATCH_TOLERANCE from their vertices
+        let offset = 2.0 * CONNECTOR_MATCH_TOLERANCE;
+        let connectors = vec![
+            connector("c1", point(-122.3 + offset, 47.6)),
+            connector("c2", point(-122.31, 47.61 - offset)),
+        ];
+        let mut road = segment("road", vec![point(-122.3, 47.6), point(-122.305, 47.605), point(-122.31, 47.61)]);
+        road.connectors = vec![
+            ConnectorRef { id: "c1".to_string(), at: AlongFraction::START, coordinate: None },
+            ConnectorRef { id: "c2".to_string(), at: AlongFraction::END, coordinate: None },
+        ];
+
+        let search = search(&connectors);
+        assert!(!search.is_at(&road.points[0], &road.connectors[0]));
+        assert_eq!(search.match_vertices(&road), vec![Some(0), None, Some(1)]);
+
+        let mut next_index = connectors.len();
+        let exported = exported_road(&road, &search, &mut next_index);
+        let indices: Vec<usize> = exported.points.iter().map(|p| p.index).collect();
+        assert_eq!(indices, vec![0, 2, 1]);
+
+        // Beyond the snap tolerance the id match is rejected and the coordinates decide
+        let strict = ConnectorSearch::new(&connectors, &ConvertOptions { connector_snap_tolerance: Some(offset / 2.0), ..Default::default() });
+        assert_eq!(strict.match_vertices(&road), vec![None, None, None]);
+    }
+
+    #[test]
+    fn test_connector_between_vertices_inserts_node() {
+        let connectors = vec![
+            connector("c1", point(-122.3, 47.6)),
+            connector("c2", point(-122.303, 47.603)),
+            connector("c3", point(-122.31, 47.61)),
+        ];
+        let mut road = segment("road", vec![point(-122.3, 47.6), point(-122.31, 47.61)]);
+        road.connectors = vec![
+            ConnectorRef { id: "c1".to_string(), at: AlongFraction::START, coordinate: None },
+            ConnectorRef { id: "c2".to_string(), at: AlongFraction::new(0.3).unwrap(), coordinate: None },
+            ConnectorRef { id: "c3".to_string(), at: AlongFraction::END, coordinate: None },
+        ];
+
+        let mut next_index = connectors.len();
+        let exported = exported_road(&road, &search(&connectors), &mut next_index);
+        let indices: Vec<usize> = exported.points.iter().map(|p| p.index).collect();
+        assert_eq!(indices, vec![0, 1, 2]);
+        assert!((exported.points[1].point.lon - -122.303).abs() < 1e-12);
+        assert!((exported.points[1].point.lat - 47.603).abs() < 1e-12);
+        assert_eq!(next_index, connectors.len());
+
+        let output = road_output(0, &exported, true);
+        assert!(output.ways.iter().all(|way| way.node_count() == 3));
+        assert_eq!(output.way_nodes.len(), 6);
+    }
+
+    #[test]
+    fn test_connector_order() {
+        assert_eq!(connector_order(&[]), ConnectorOrder::Ascending);
+        assert_eq!(connector_order(&[0.0, 0.5, 1.0]), ConnectorOrder::Ascending);
+        assert_eq!(connector_order(&[1.0, 0.0]), ConnectorOrder::Descending);
+        assert_eq!(connector_order(&[0.0, 1.0, 0.5]), ConnectorOrder::Inconsistent);
+    }
+
+    #[test]
+    fn test_reverse_digitized_segment() {
+        let connectors = vec![
+            connector("c1", point(-122.3, 47.6)),
+            connector("c2", point(-122.32, 47.62)),
+        ];
+        // Geometry runs from c2 to c1 while `at` says c1 is the start
+        let mut reversed = segment(
+            "reversed",
+            vec![point(-122.32, 47.62), point(-122.31, 47.61), point(-122.3, 47.6)],
+        );
+        reversed.connectors = vec![
+            ConnectorRef { id: "c1".to_string(), at: AlongFraction::START, coordinate: None },
+            ConnectorRef { id: "c2".to_string(), at: AlongFraction::END, coordinate: None },
+        ];
+
+        let mut next_index = connectors.len();
+        let road = exported_road(&reversed, &search(&connectors), &mut next_index);
+        let indices: Vec<usize> = road.points.iter().map(|p| p.index).collect();
+        assert_eq!(indices, vec![0, 2, 1]);
+        assert_eq!(road.points[0].point.lon, -122.3);
+        assert_eq!(road.points[2].point.lon, -122.32);
+    }
+
+    #[test]
+    fn test_traffic_signal_connector_node_kind() {
+        let input_dir = tempdir().unwrap();
+        let segment_path = input_dir.path().join("segment.parquet");
+        let connector_path = input_dir.path().join("connector.parquet");
+        write_segment_parquet(
+            &segment_path,
+            &[TestSegment {
+                id: "s1",
+                name: "Main St",
+                class: "residential",
+                points: vec![(-122.3, 47.6), (-122.31, 47.61)],
+                connectors: vec![
+                    TestConnectorRef { id: "c1", at: 0.0, lon_lat: None },
+                    TestConnectorRef { id: "c2", at: 1.0, lon_lat: None },
+                ],
+            }],
+        );
+        write_connector_parquet(
+            &connector_path,
+            &[("c1", (-122.3, 47.6), Some("traffic_signal")), ("c2", (-122.31, 47.61), None)],
+        );
+
+        let data = import_overture_data(&segment_path, &connector_path).unwrap();
+        assert_eq!(data.connectors[0].node_kind, ValhallaNodeKind::TrafficSignal);
+        assert_eq!(data.connectors[1].node_kind, ValhallaNodeKind::Intersection);
+
+        let mut next_index = data.connectors.len();
+        let road = exported_road(&data.segments[0], &search(&data.connectors), &mut next_index);
+        assert_eq!(road.points[0].node_kind, ValhallaNodeKind::TrafficSignal);
+        assert_eq!(road.points[1].node_kind, ValhallaNodeKind::Intersection);
+
+        // The flag reaches the written way nodes
+        let output_dir = tempdir().unwrap();
+        let options = ConvertOptions { emit_reverse_ways: Some(false), ..Default::default() };
+        convert_overture_to_valhalla(input_dir.path(), output_dir.path(), &options).unwrap();
+        let way_nodes = OsmWayNode::from_bytes(&std::fs::read(output_dir.path().join("way_nodes.bin")).unwrap()).unwrap();
+        let signals: Vec<bool> = way_nodes.iter().map(OsmWayNode::traffic_signal).collect();
+        assert_eq!(signals, vec![true, false]);
+    }
+
+    #[test]
+    fn test_converted_output_passes_verification() {
+        let input_dir = tempdir().unwrap();
+        let output_dir = tempdir().unwrap();
+        write_segment_parquet(
+            &input_dir.path().join("segment.parquet"),
+            &[TestSegment {
+                id: "s1",
+                name: "Main St",
+                class: "residential",
+                points: vec![(-122.3, 47.6), (-122.31, 47.61), (-122.32, 47.62)],
+                connectors: vec![
+                    TestConnectorRef { id: "c1", at: 0.0, lon_lat: Some((-122.3, 47.6)) },
+                    TestConnectorRef { id: "c2", at: 1.0, lon_lat: Some((-122.32, 47.62)) },
+                ],
+            }],
+        );
+
+        convert_overture_to_valhalla(input_dir.path(), output_dir.path(), &ConvertOptions::default()).unwrap();
+        assert_eq!(crate::verify::verify_bin_files(output_dir.path()).unwrap(), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_convert_mapping_metrics() {
+        let input_dir = tempdir().unwrap();
+        let output_dir = tempdir().unwrap();
+        let test_segment = |id, class, lon| TestSegment {
+            id,
+            name: "Road",
+            class,
+            points: vec![(lon, 47.6), (lon, 47.61)],
+            connectors: Vec::new(),
+        };
+        write_segment_parquet(
+            &input_dir.path().join("segment.parquet"),
+            &[
+                test_segment("s1", "motorway", -122.3),
+                test_segment("s2", "residential", -122.31),
+                test_segment("s3", "residential", -122.32),
+                test_segment("s4", "footway", -122.33),
+                test_segment("s5", "cycleway", -122.34),
+            ],
+        );
+
+        let metrics = convert_overture_to_valhalla(input_dir.path(), output_dir.path(), &ConvertOptions::default()).unwrap();
+        assert_eq!(metrics.segments, 5);
+        assert_eq!(
+            metrics.road_classes,
+            BTreeMap::from([
+                (ValhallaRoadClass::Motorway, 1),
+                (ValhallaRoadClass::Residential, 2),
+                (ValhallaRoadClass::ServiceOther, 2),
+            ])
+        );
+        // No segment carries a road surface, so all get their class default
+        assert_eq!(metrics.surfaces, BTreeMap::from([(ValhallaSurface::Paved, 4), (ValhallaSurface::Path, 1)]));
+        assert_eq!(metrics.access_denied.auto, 2);
+        assert_eq!(metrics.access_denied.pedestrian, 2);
+        assert_eq!(metrics.access_denied.bicycle, 2);
+    }
+
+    #[test]
+    fn test_connectorless_policies() {
+        let connectors = vec![connector("c1", point(-122.3, 47.6))];
+        let lonely = segment("lonely", vec![point(-122.5, 47.7), point(-122.51, 47.71)]);
+        let search = search(&connectors);
+        let process = |policy| process_segment(&lonely, &search, &mut connectors.len(), mapped(&lonely), policy);
+
+        let road = process(ConnectorlessPolicy::Synthesize).unwrap().unwrap();
+        assert!(road.connectorless);
+        let indices: Vec<usize> = road.points.iter().map(|p| p.index).collect();
+        assert_eq!(indices, vec![1, 2]);
+
+        assert!(process(ConnectorlessPolicy::Skip).unwrap().is_none());
+
+        let err = process(ConnectorlessPolicy::Error).unwrap_err();
+        assert!(matches!(&err, WriterError::ConnectorlessSegment(id) if id == "lonely"));
+        assert!(Error::from(err).to_string().contains("'lonely' has no connectors"));
+
+        assert_eq!("skip".parse(), Ok(ConnectorlessPolicy::Skip));
+        assert!("drop".parse::<ConnectorlessPolicy>().is_err());
+    }
+
+    #[test]
+    fn test_way_name_indices_point_into_strings_file() {
+        let input_dir = tempdir().unwrap();
+        let segment = |id, name, lon| TestSegment {
+            id,
+            name,
+            class: "residential",
+            points: vec![(lon, 47.6), (lon - 0.01, 47.61)],
+            connectors: Vec::new(),
+        };
+        write_segment_parquet(
+            &input_dir.path().join("segment.parquet"),
+            &[segment("s1", "Main St", -122.3), segment("s2", " ", -122.4), segment("s3", "Pine St", -122.5), segment("s4", "Main St", -122.6)],
+        );
+        let output_dir = tempdir().unwrap();
+        let options = ConvertOptions { emit_reverse_ways: Some(false), ..Default::default() };
+        convert_overture_to_valhalla(input_dir.path(), output_dir.path(), &options).unwrap();
+
+        let strings = std::fs::read(output_dir.path().join(STRINGS_FILE)).unwrap();
+        let names: Vec<&str> = strings.split(|byte| *byte == 0).map(|name| std::str::from_utf8(name).unwrap()).collect();
+        // Every name is terminated, so splitting leaves an empty remainder
+        assert_eq!(names, ["", "Main St", "Pine St", ""]);
+
+        let ways = OsmWay::from_bytes(&std::fs::read(output_dir.path().join("ways.bin")).unwrap()).unwrap();
+        let way_names: Vec<&str> = ways.iter().map(|way| names[way.name_index() as usize]).collect();
+        assert_eq!(way_names, ["Main St", "", "Pine St", "Main St"]);
+    }
+
+    #[test]
+    fn test_connectorless_policy_counts() {
+        let input_dir = tempdir().unwrap();
+        write_segment_parquet(
+            &input_dir.path().join("segment.parquet"),
+            &[
+                TestSegment {
+                    id: "s1",
+                    name: "A",
+                    class: "residential",
+                    points: vec![(-122.3, 47.6), (-122.31, 47.61)],
+                    connectors: vec![
+                        TestConnectorRef { id: "c1", at: 0.0, lon_lat: Some((-122.3, 47.6)) },
+                        TestConnectorRef { id: "c2", at: 1.0, lon_lat: Some((-122.31, 47.61)) },
+                    ],
+                },
+                TestSegment { id: "s2", name: "B", class: "residential", points: vec![(-122.4, 47.6), (-122.41, 47.61)], connectors: Vec::new() },
+            ],
+        );
+
+        let convert = |connectorless_policy| {
+            let output_dir = tempdir().unwrap();
+            let options = ConvertOptions { connectorless_policy, emit_reverse_ways: Some(false), ..Default::default() };
+            let metrics = convert_overture_to_valhalla(input_dir.path(), output_dir.path(), &options)?;
+            let ways = OsmWay::from_bytes(&std::fs::read(output_dir.path().join("ways.bin")).unwrap()).unwrap();
+            Ok::<_, Error>((metrics.connectorless, ways.len()))
+        };
+
+        let (outcomes, ways) = convert(ConnectorlessPolicy::default()).unwrap();
+        assert_eq!(outcomes, ConnectorlessOutcomes { synthesized: 1, skipped: 0 });
+        assert_eq!(ways, 2);
+
+        let (outcomes, ways) = convert(ConnectorlessPolicy::Skip).unwrap();
+        assert_eq!(outcomes, ConnectorlessOutcomes { synthesized: 0, skipped: 1 });
+        assert_eq!(ways, 1);
+
+        let err = convert(ConnectorlessPolicy::Error).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert!(err.to_string().contains("'s2'"), "{}", err);
+    }
+
+    #[test]
+    fn test_emit_reverse_ways() {
+        let input_dir = tempdir().unwrap();
+        write_segment_parquet(
+            &input_dir.path().join("segment.parquet"),
+            &[
+                TestSegment { id: "s1", name: "A", class: "residential", points: vec![(-122.3, 47.6), (-122.31, 47.61)], connectors: Vec::new() },
+                TestSegment { id: "s2", name: "B", class: "primary", points: vec![(-122.4, 47.6), (-122.41, 47.61), (-122.42, 47.62)], connectors: Vec::new() },
+            ],
+        );
+
+        for (emit_reverse_ways, expected_ways, expected_way_nodes) in [(None, 4, 10), (Some(true), 4, 10), (Some(false), 2, 5)] {
+            let output_dir = tempdir().unwrap();
+            let options = ConvertOptions { emit_reverse_ways, ..Default::default() };
+            convert_overture_to_valhalla(input_dir.path(), output_dir.path(), &options).unwrap();
+
+            let ways = OsmWay::from_bytes(&std::fs::read(output_dir.path().join("ways.bin")).unwrap()).unwrap();
+            let way_nodes = OsmWayNode::from_bytes(&std::fs::read(output_dir.path().join("way_nodes.bin")).unwrap()).unwrap();
+            assert_eq!(ways.len(), expected_ways, "{:?}", emit_reverse_ways);
+            assert_eq!(way_nodes.len(), expected_way_nodes, "{:?}", emit_reverse_ways);
+            assert_eq!(crate::verify::verify_bin_files(output_dir.path()).unwrap(), Vec::<String>::new());
+        }
+    }
+
+    #[test]
+    fn test_conversion_stage_timings() {
+        let input_dir = tempdir().unwrap();
+        write_segment_parquet(
+            &input_dir.path().join("segment.parquet"),
+            &[TestSegment { id: "s1", name: "A", class: "residential", points: vec![(-122.3, 47.6), (-122.31, 47.61)], connectors: Vec::new() }],
+        );
+        let output_dir = tempdir().unwrap();
+        let mut timings = StageTimings::new();
+        convert_overture_to_valhalla_with_timings(input_dir.path(), output_dir.path(), &ConvertOptions::default(), &mut timings)
+            .unwrap();
+
+        let stages: Vec<&str> = timings.stages().iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(stages, vec!["import", "mapping", "export"]);
+        let summary = timings.summary();
+        for stage in ["import", "mapping", "export", "total"] {
+            assert!(summary.contains(stage), "{}", summary);
+        }
+    }
+
+    #[test]
+    fn test_split_by_mode() {
+        let input_dir = tempdir().unwrap();
+        write_segment_parquet(
+            &input_dir.path().join("segment.parquet"),
+            &[
+                TestSegment { id: "s1", name: "A", class: "residential", points: vec![(-122.3, 47.6), (-122.31, 47.61)], connectors: Vec::new() },
+                TestSegment { id: "s2", name: "B", class: "footway", points: vec![(-122.4, 47.6), (-122.41, 47.61)], connectors: Vec::new() },
+                TestSegment { id: "s3", name: "C", class: "cycleway", points: vec![(-122.5, 47.6), (-122.51, 47.61)], connectors: Vec::new() },
+            ],
+        );
+        let output_dir = tempdir().unwrap();
+        let options = ConvertOptions { split_by_mode: true, emit_reverse_ways: Some(false), ..Default::default() };
+        convert_overture_to_valhalla(input_dir.path(), output_dir.path(), &options).unwrap();
+
+        let way_ids = |mode: &str| -> Vec<u64> {
+            let bytes = std::fs::read(output_dir.path().join(format!("{}_ways.bin", mode))).unwrap();
+            OsmWay::from_bytes(&bytes).unwrap().iter().map(OsmWay::osmid).collect()
+        };
+        // The footway keeps the way id it has in ways.bin
+        assert_eq!(way_ids("pedestrian"), vec![1, 3]);
+        assert_eq!(way_ids("auto"), vec![1]);
+        // The cycleway is closed to cars and pedestrians and only in the bicycle graph
+        assert_eq!(way_ids("bicycle"), vec![1, 5]);
+
+        let way_node_count = |mode: &str| -> usize {
+            let bytes = std::fs::read(output_dir.path().join(format!("{}_way_nodes.bin", mode))).unwrap();
+            OsmWayNode::from_bytes(&bytes).unwrap().len()
+        };
+        assert_eq!(way_node_count("pedestrian"), 4);
+        assert_eq!(way_node_count("auto"), 2);
+        assert_eq!(way_node_count("bicycle"), 4);
+        assert_eq!(std::fs::read(output_dir.path().join("ways.bin")).unwrap().len(), 3 * size_of::<OsmWay>());
+    }
+
+    /// List column splitting `values` into lists of the given `lengths`
+    fn list_array(values: ArrayRef, lengths: &[usize]) -> ArrayRef {
+        let item = Arc::new(ArrowField::new("item", values.data_type().clone(), true));
+        Arc::new(ListArray::new(item, OffsetBuffer::from_lengths(lengths.iter().copied()), values, None))
+    }
+
+    #[test]
+    fn test_cycleway_bicycle_attributes_written() {
+        let input_dir = tempdir().unwrap();
+        let ids: ArrayRef = Arc::new(StringArray::from(vec!["s1", "s2"]));
+        let classes: ArrayRef = Arc::new(StringArray::from(vec!["cycleway", "path"]));
+        let geometry: ArrayRef = Arc::new(BinaryArray::from_iter_values(
+            [-122.3, -122.4].map(|lon| linestring_wkb(&[(lon, 47.6), (lon, 47.61)])),
+        ));
+        let surfaces: ArrayRef = Arc::new(StructArray::from(vec![(
+            Arc::new(ArrowField::new("value", DataType::Utf8, true)),
+            Arc::new(StringArray::from(vec!["paved", "gravel"])) as ArrayRef,
+        )]));
+        let road_surface = list_array(surfaces, &[1, 1]);
+        let batch =
+            RecordBatch::try_from_iter([("id", ids), ("class", classes), ("geometry", geometry), ("road_surface", road_surface)])
+                .unwrap();
+        let mut writer = ArrowWriter::try_new(File::create(input_dir.path().join("segment.parquet")).unwrap(), batch.schema(), None).unwrap();
+        writer.write(&batch).unwrap();
+        writer.close().unwrap();
+
+        let output_dir = tempdir().unwrap();
+        let options = ConvertOptions { split_by_mode: true, emit_reverse_ways: Some(false), ..Default::default() };
+        convert_overture_to_valhalla(input_dir.path(), output_dir.path(), &options).unwrap();
+
+        let ways = OsmWay::from_bytes(&std::fs::read(output_dir.path().join("bicycle_ways.bin")).unwrap()).unwrap();
+        let [cycleway, gravel_path] = ways.as_slice() else { panic!("expected two bicycle ways, got {}", ways.len()) };
+        assert!(cycleway.bicycle_allowed() && !cycleway.auto_allowed() && !cycleway.pedestrian_allowed());
+        assert_eq!(cycleway.cycle_lane(), ValhallaCycleLane::Separated as u16);
+        assert_eq!(cycleway.surface(), ValhallaSurface::Paved as u32);
+        assert!(!cycleway.bicycle_dismount());
+        assert!(gravel_path.bicycle_allowed());
+        assert_eq!(gravel_path.cycle_lane(), ValhallaCycleLane::None as u16);
+        assert_eq!(gravel_path.surface(), ValhallaSurface::Gravel as u32);
+    }
+
+    #[test]
+    fn test_access_restrictions_clear_auto_access() {
+        let input_dir = tempdir().unwrap();
+        let ids: ArrayRef = Arc::new(StringArray::from(vec!["s1", "s2", "s3"]));
+        let classes: ArrayRef = Arc::new(StringArray::from(vec!["residential"; 3]));
+        let geometry: ArrayRef = Arc::new(BinaryArray::from_iter_values(
+            [-122.3, -122.4, -122.5].map(|lon| linestring_wkb(&[(lon, 47.6), (lon, 47.61)])),
+        ));
+        // s1 is closed to cars, s3 only during rush hour which is not mapped
+        let modes = list_array(Arc::new(StringArray::from(vec!["car", "car"])), &[1, 1]);
+        let during: ArrayRef = Arc::new(StringArray::from(vec![None, Some("Mo-Fr 07:00-09:00")]));
+        let when: ArrayRef = Arc::new(StructArray::from(vec![
+            (Arc::new(ArrowField::new("mode", modes.data_type().clone(), true)), modes),
+            (Arc::new(ArrowField::new("during", DataType::Utf8, true)), during),
+        ]));
+        let rules: ArrayRef = Arc::new(StructArray::from(vec![
+            (
+                Arc::new(ArrowField::new("access_type", DataType::Utf8, true)),
+                Arc::new(StringArray::from(vec!["denied", "denied"])) as ArrayRef,
+            ),
+            (Arc::new(ArrowField::new("when", when.data_type().clone(), true)), when),
+        ]));
+        let access_restrictions = list_array(rules, &[1, 0, 1]);
+
+        let batch = RecordBatch::try_from_iter([
+            ("id", ids),
+            ("class", classes),
+            ("geometry", geometry),
+            ("access_restrictions", access_restrictions),
+        ])
+        .unwrap();
+        let file = File::create(input_dir.path().join("segment.parquet")).unwrap();
+        let mut writer = ArrowWriter::try_new(file, batch.schema(), None).unwrap();
+        writer.write(&batch).unwrap();
+        writer.close().unwrap();
+
+        let output_dir = tempdir().unwrap();
+        let options = ConvertOptions { emit_reverse_ways: Some(false), ..Default::default() };
+        convert_overture_to_valhalla(input_dir.path(), output_dir.path(), &options).unwrap();
+
+        let ways = OsmWay::from_bytes(&std::fs::read(output_dir.path().join("ways.bin")).unwrap()).unwrap();
+        let auto: Vec<bool> = ways.iter().map(OsmWay::auto_allowed).collect();
+        assert_eq!(auto, vec![false, true, true]);
+        assert!(ways.iter().all(OsmWay::pedestrian_allowed));
+    }
+
+    #[test]
+    fn test_clip_rejects_on_bbox_before_decoding_geometry() {
+        let input_dir = tempdir().unwrap();
+        let segment_path = input_dir.path().join("segment.parquet");
+        let connector_path = input_dir.path().join("connector.parquet");
+        let ids: ArrayRef = Arc::new(StringArray::from(vec!["inside", "outside"]));
+        // The geometry of the outside segment is not valid WKB, decoding it fails
+        let geometry: ArrayRef = Arc::new(BinaryArray::from_iter_values([
+            linestring_wkb(&[(-122.35, 47.55), (-122.36, 47.56)]),
+            vec![0xff, 0xff, 0xff],
+        ]));
+        let bbox_column = |values: [f64; 2]| Arc::new(Float64Array::from(values.to_vec())) as ArrayRef;
+        let bbox: ArrayRef = Arc::new(StructArray::from(vec![
+            (Arc::new(ArrowField::new("xmin", DataType::Float64, true)), bbox_column([-122.36, 10.0])),
+            (Arc::new(ArrowField::new("xmax", DataType::Float64, true)), bbox_column([-122.35, 10.1])),
+            (Arc::new(ArrowField::new("ymin", DataType::Float64, true)), bbox_column([47.55, 20.0])),
+            (Arc::new(ArrowField::new("ymax", DataType::Float64, true)), bbox_column([47.56, 20.1])),
+        ]));
+        let batch = RecordBatch::try_from_iter([("id", ids), ("geometry", geometry), ("bbox", bbox)]).unwrap();
+        let mut writer = ArrowWriter::try_new(File::create(&segment_path).unwrap(), batch.schema(), None).unwrap();
+        writer.write(&batch).unwrap();
+        writer.close().unwrap();
+
+        assert!(import_overture_data(&segment_path, &connector_path).is_err());
+
+        let options = ConvertOptions { clip: Some(ClipBox::new(-122.4, 47.5, -122.3, 47.6)), ..Default::default() };
+        let data = import_overture_data_with_options(&segment_path, &connector_path, &options).unwrap();
+        let segment_ids: Vec<&str> = data.segments.iter().map(|s| s.id.as_str()).collect();
+        assert_eq!(segment_ids, vec!["inside"]);
+    }
+
+    #[test]
+    fn test_clip_without_bbox_uses_geometry() {
+        let input_dir = tempdir().unwrap();
+        let segment_path = input_dir.path().join("segment.parquet");
+        write_segment_parquet(
+            &segment_path,
+            &[
+                TestSegment { id: "s1", name: "A", class: "residential", points: vec![(-122.35, 47.55), (-122.36, 47.56)], connectors: Vec::new() },
+                TestSegment { id: "s2", name: "B", class: "residential", points: vec![(-122.5, 47.55), (-122.2, 47.55)], connectors: Vec::new() },
+                TestSegment { id: "s3", name: "C", class: "residential", points: vec![(-121.0, 47.0), (-121.1, 47.1)], connectors: Vec::new() },
+            ],
+        );
+
+        let options = ConvertOptions { clip: Some(ClipBox::new(-122.4, 47.5, -122.3, 47.6)), ..Default::default() };
+        let data = import_overture_data_with_options(&segment_path, &input_dir.path().join("connector.parquet"), &options).unwrap();
+        // s2 has no vertex in the box but passes through it
+        let segment_ids: Vec<&str> = data.segments.iter().map(|s| s.id.as_str()).collect();
+        assert_eq!(segment_ids, vec!["s1", "s2"]);
+    }
+
+    #[test]
+    fn test_segment_sources() {
+        let input_dir = tempdir().unwrap();
+        let ids: ArrayRef = Arc::new(StringArray::from(vec!["s1", "s2"]));
+        let classes: ArrayRef = Arc::new(StringArray::from(vec!["residential"; 2]));
+        let geometry: ArrayRef = Arc::new(BinaryArray::from_iter_values(
+            [-122.3, -122.4].map(|lon| linestring_wkb(&[(lon, 47.6), (lon, 47.61)])),
+        ));
+        // s1 has a source for the whole feature and one for its name, s2 has none
+        let entries: ArrayRef = Arc::new(StructArray::from(vec![
+            (
+                Arc::new(ArrowField::new("property", DataType::Utf8, true)),
+                Arc::new(StringArray::from(vec![None, Some("/names/primary")])) as ArrayRef,
+            ),
+            (
+                Arc::new(ArrowField::new("dataset", DataType::Utf8, true)),
+                Arc::new(StringArray::from(vec!["OpenStreetMap", "TomTom"])) as ArrayRef,
+            ),
+            (
+                Arc::new(ArrowField::new("record_id", DataType::Utf8, true)),
+                Arc::new(StringArray::from(vec![Some("w123"), None])) as ArrayRef,
+            ),
+            (
+                Arc::new(ArrowField::new("confidence", DataType::Float64, true)),
+                Arc::new(Float64Array::from(vec![None, Some(0.8)])) as ArrayRef,
+            ),
+        ]));
+        let sources = list_array(entries, &[2, 0]);
+
+        let batch = RecordBatch::try_from_iter([
+            ("id", ids),
+            ("class", classes),
+            ("geometry", geometry),
+            ("sources", sources),
+        ])
+        .unwrap();
+        let file = File::create(input_dir.path().join("segment.parquet")).unwrap();
+        let mut writer = ArrowWriter::try_new(file, batch.schema(), None).unwrap();
+        writer.write(&batch).unwrap();
+        writer.close().unwrap();
+
+        let data = import_overture_data(&input_dir.path().join("segment.parquet"), &input_dir.path().join("connector.parquet")).unwrap();
+        let datasets: Vec<&str> = data.segments[0].sources.iter().map(|source| source.dataset.as_str()).collect();
+        assert_eq!(datasets, vec!["OpenStreetMap", "TomTom"]);
+        assert_eq!(data.segments[0].sources[0].record_id.as_deref(), Some("w123"));
+        assert_eq!(data.segments[0].sources[1].property.as_deref(), Some("/names/primary"));
+        assert_eq!(data.segments[0].sources[1].confidence, Some(0.8));
+        assert!(data.segments[1].sources.is_empty());
+
+        let output_dir = tempdir().unwrap();
+        let options = ConvertOptions { preserve_sources: true, ..Default::default() };
+        convert_overture_to_valhalla(input_dir.path(), output_dir.path(), &options).unwrap();
+
+        let records: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(output_dir.path().join(SOURCES_FILE)).unwrap()).unwrap();
+        let ways = &records["ways"];
+        assert_eq!(ways[0]["way_ids"], serde_json::json!([1, 2]));
+        assert_eq!(ways[0]["segment_id"], "s1");
+        assert_eq!(ways[0]["sources"][0]["dataset"], "OpenStreetMap");
+        assert_eq!(ways[1]["way_ids"], serde_json::json!([3, 4]));
+        assert_eq!(ways[1]["sources"], serde_json::json!([]));
+        assert_eq!(records["nodes"], serde_json::json!([]));
+
+        // Without the option no sidecar is written
+        let output_dir = tempdir().unwrap();
+        convert_overture_to_valhalla(input_dir.path(), output_dir.path(), &ConvertOptions::default()).unwrap();
+        assert!(!output_dir.path().join(SOURCES_FILE).exists());
+    }
+
+    #[test]
+    fn test_way_capacity_guard() {
+        let max_roads = max_roads() as usize;
+        assert!(check_way_capacity(max_roads).is_ok());
+        // The last road that fits still gets ids and indices within 32 bits
+        let [way_id, reverse_way_id] = road_way_ids(max_roads - 1);
+        assert!(u32::try_from(way_id).is_ok() && u32::try_from(reverse_way_id).is_ok());
+
+        // One more would wrap the way index of its reversed duplicate
+        assert!(u32::try_from(road_way_ids(max_roads)[1]).is_err());
+        let err = check_way_capacity(max_roads + 1).unwrap_err();
+        assert!(matches!(err, WriterError::TooManyWays { roads, .. } if roads == max_roads as u64 + 1));
+        let err = Error::from(err);
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert!(err.to_string().contains("split the input"), "{}", err);
+    }
+
+    #[test]
+    fn test_directional_speed_limits() {
+        let connectors = vec![
+            connector("c1", point(-122.3, 47.6)),
+            connector("c2", point(-122.31, 47.61)),
+        ];
+        let mut road = segment("directional", vec![point(-122.3, 47.6), point(-122.31, 47.61)]);
+        road.connectors = vec![
+            ConnectorRef { id: "c1".to_string(), at: AlongFraction::START, coordinate: None },
+            ConnectorRef { id: "c2".to_string(), at: AlongFraction::END, coordinate: None },
+        ];
+        road.speed_limits = vec![
+            SpeedLimit {
+                max_speed: Some(Speed { value: 50.0, unit: "km/h".to_string() }),
+                min_speed: None,
+                when: Some(SpeedLimitWhen { heading: Some("forward".to_string()) }),
+            },
+            SpeedLimit {
+                max_speed: Some(Speed { value: 30.0, unit: "km/h".to_string() }),
+                min_speed: None,
+                when: Some(SpeedLimitWhen { heading: Some("backward".to_string()) }),
+            },
+        ];
+
+        let mut next_index = connectors.len();
+        let exported = exported_road(&road, &search(&connectors), &mut next_index);
+        let output = road_output(0, &exported, true);
+        let forward_way = output.ways[0].speed();
+        let reverse_way = output.ways[1].speed();
+        assert_eq!(forward_way, DirectionalSpeed { forward: Some(50), backward: Some(30) });
+        // The second way runs against the geometry
+        assert_eq!(reverse_way, DirectionalSpeed { forward: Some(30), backward: Some(50) });
+    }
+
+    fn segment_ids(count: usize) -> Vec<String> {
+        (0..count).map(|i| format!("s{}", i)).collect()
+    }
+
+    fn numbered_segments(ids: &[String]) -> Vec<TestSegment<'_>> {
+        ids.iter()
+            .enumerate()
+            .map(|(i, id)| {
+                let lon = -122.3 - i as f64 * 0.001;
+                TestSegment {
+                    id,
+                    name: "Main St",
+                    class: "residential",
+                    points: vec![(lon, 47.6), (lon, 47.61)],
+                    connectors: Vec::new(),
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_import_is_independent_of_chunk_size() {
+        let input_dir = tempdir().unwrap();
+        let segment_path = input_dir.path().join("segment.parquet");
+        let connector_path = input_dir.path().join("connector.parquet");
+        let expected = segment_ids(25);
+        write_segment_parquet(&segment_path, &numbered_segments(&expected));
+
+        for chunk_size in [1, 3, 7, 25, 1000] {
+            let options = ConvertOptions { threads: Some(4), chunk_size: Some(chunk_size), ..Default::default() };
+            let data = import_overture_data_with_options(&segment_path, &connector_path, &options).unwrap();
+            let ids: Vec<String> = data.segments.iter().map(|segment| segment.id.clone()).collect();
+            assert_eq!(ids, expected, "chunk size {}", chunk_size);
+        }
+    }
+
+    #[test]
+    fn test_row_groups_read_in_parallel_match_sequential_read() {
+        let input_dir = tempdir().unwrap();
+        let segment_path = input_dir.path().join("segment.parquet");
+        let connector_path = input_dir.path().join("connector.parquet");
+        let expected = segment_ids(50);
+        write_segment_row_groups(&segment_path, &numbered_segments(&expected), 7);
+        let reader = SerializedFileReader::new(File::open(&segment_path).unwrap()).unwrap();
+        assert_eq!(reader.num_row_groups(), 8);
+
+        let sequential = ConvertOptions { threads: Some(1), ..Default::default() };
+        let sequential = import_overture_data_with_options(&segment_path, &connector_path, &sequential).unwrap();
+        let ids: Vec<String> = sequential.segments.iter().map(|segment| segment.id.clone()).collect();
+        assert_eq!(ids, expected);
+
+        for threads in [2, 3, 16] {
+            let data = import_row_groups(&segment_path, 8, threads, None).unwrap();
+            assert_eq!(format!("{:?}", data.segments), format!("{:?}", sequential.segments), "{} threads", threads);
+            let options = ConvertOptions { threads: Some(threads), ..Default::default() };
+            let data = import_overture_data_with_options(&segment_path, &connector_path, &options).unwrap();
+            assert_eq!(format!("{:?}", data), format!("{:?}", sequential), "{} threads", threads);
+        }
+    }
+
+    /// Run with `cargo test -p overture-valhalla-writer --release -- --ignored --nocapture`
+    #[test]
+    #[ignore = "benchmark"]
+    fn bench_import_chunk_sizes() {
+        let input_dir = tempdir().unwrap();
+        let segment_path = input_dir.path().join("segment.parquet");
+        let connector_path = input_dir.path().join("connector.parquet");
+        write_segment_parquet(&segment_path, &numbered_segments(&segment_ids(200_000)));
+
+        for chunk_size in [1, 16, 256, DEFAULT_CHUNK_SIZE, 16_384] {
+            let options = ConvertOptions { chunk_size: Some(chunk_size), ..Default::default() };
+            let start = std::time::Instant::now();
+            let data = import_overture_data_with_options(&segment_path, &connector_path, &options).unwrap();
+            println!("chunk size {:>6}: {} segments in {:?}", chunk_size, data.segments.len(), start.elapsed());
+        }
+    }
+
+    #[test]
+    fn test_sample_segments_is_seeded() {
+        let ids = segment_ids(1000);
+        let sample = |seed: u64| -> Vec<String> {
+            let mut data = Data {
+                segments: ids.iter().map(|id| segment(id, vec![point(-122.3, 47.6), point(-122.31, 47.61)])).collect(),
+                connectors: vec![connector("c1", point(-122.3, 47.6))],
+            };
+            sample_segments(&mut data, 0.1, seed);
+            assert_eq!(data.connectors.len(), 1);
+            data.segments.into_iter().map(|segment| segment.id).collect()
+        };
+
+        let first = sample(42);
+        assert_eq!(first, sample(42));
+        assert_ne!(first, sample(7));
+        assert!((50..150).contains(&first.len()), "sampled {} of 1000", first.len());
+
+        assert!(ids.iter().all(|id| segment_in_sample(id, 1.0, 42)));
+    }
 }
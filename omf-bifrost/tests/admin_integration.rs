@@ -1,4 +1,6 @@
-use omf_bifrost::admin::{AdminConfig, build_admins_from_geo_parquet};
+use omf_bifrost::admin::{
+    AdminConfig, AdminPass, DurabilityProfile, build_admins_from_geo_parquet, merge_admin_dbs,
+};
 use tempfile::tempdir;
 
 #[derive(Debug)]
@@ -49,6 +51,8 @@ fn test_build_admins_from_geo_parquet_wa_example_data() {
         area_parquet,
         &sqlite_path.to_string_lossy(),
         &admin_config,
+        DurabilityProfile::Fast,
+        &AdminPass::default(),
     );
     assert!(result.is_ok(), "admin building failed: {:?}", result);
 
@@ -165,6 +169,8 @@ fn test_build_admins_from_geo_parquet_japan_example_data() {
         area_parquet,
         &sqlite_path.to_string_lossy(),
         &admin_config,
+        DurabilityProfile::Fast,
+        &AdminPass::default(),
     );
     assert!(result.is_ok(), "admin building failed: {:?}", result);
 
@@ -214,3 +220,213 @@ fn test_build_admins_from_geo_parquet_japan_example_data() {
         .unwrap();
     assert_eq!(count, 0, "Expected no admin_access rows, but found {count}",);
 }
+
+#[test]
+fn test_merge_admin_dbs_wa_and_japan() {
+    let tmp_dir = tempdir().expect("failed to make tempdir");
+    let wa_path = tmp_dir.path().join("wa_admin.sqlite");
+    let japan_path = tmp_dir.path().join("japan_admin.sqlite");
+    let merged_path = tmp_dir.path().join("merged_admin.sqlite");
+
+    let admin_config = AdminConfig::default();
+    for (division_parquet, area_parquet, sqlite_path) in [
+        (
+            "tests/data/wa-divisions.parquet",
+            "tests/data/wa-division-areas.parquet",
+            &wa_path,
+        ),
+        (
+            "tests/data/tokio-divisions.parquet",
+            "tests/data/tokio-division-areas.parquet",
+            &japan_path,
+        ),
+    ] {
+        build_admins_from_geo_parquet(
+            division_parquet,
+            area_parquet,
+            &sqlite_path.to_string_lossy(),
+            &admin_config,
+            DurabilityProfile::Fast,
+            &AdminPass::default(),
+        )
+        .expect("admin building failed");
+    }
+
+    // Merging WA twice must not duplicate the US or Washington rows
+    let result = merge_admin_dbs(
+        &[wa_path.as_path(), japan_path.as_path(), wa_path.as_path()],
+        &merged_path,
+    );
+    assert!(result.is_ok(), "admin merge failed: {:?}", result);
+
+    let conn = rusqlite::Connection::open(&merged_path).unwrap();
+    let mut stmt = conn
+        .prepare(
+            "SELECT rowid, admin_level, iso_code, parent_admin, name, name_en,
+           drive_on_right, allow_intersection_names, default_language, supported_languages, geom
+         FROM admins ORDER BY rowid",
+        )
+        .unwrap();
+    let admins: Vec<AdminRow> = stmt
+        .query_map([], AdminRow::from_row)
+        .unwrap()
+        .map(Result::unwrap)
+        .collect();
+
+    assert_eq!(admins.len(), 4, "Expected 4 features, got {:?}", admins);
+    let find = |iso_code: &str| {
+        admins
+            .iter()
+            .find(|admin| admin.iso_code.as_deref() == Some(iso_code))
+            .unwrap_or_else(|| panic!("missing admin {iso_code}"))
+    };
+
+    let us = find("US");
+    let wa = find("WA");
+    let jp = find("JP");
+    let tokyo = find("13");
+    assert_eq!(us.parent_admin, None);
+    assert_eq!(wa.parent_admin, Some(us.rowid));
+    assert_eq!(wa.name_en.as_deref(), Some("Washington"));
+    assert_eq!(jp.parent_admin, None);
+    assert_eq!(tokyo.parent_admin, Some(jp.rowid));
+    assert_eq!(tokyo.drive_on_right, Some(0));
+    assert!(admins.iter().all(|admin| admin.geom.is_some()));
+
+    let admin_access: Vec<(i64, String)> = conn
+        .prepare("SELECT admin_id, iso_code FROM admin_access")
+        .unwrap()
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .unwrap()
+        .map(Result::unwrap)
+        .collect();
+    assert_eq!(admin_access, vec![(us.rowid, "US".to_string())]);
+
+    let spatial_index: i64 = conn
+        .query_row(
+            "SELECT spatial_index_enabled FROM geometry_columns WHERE f_table_name = 'admins'",
+            [],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert_eq!(spatial_index, 1);
+}
+
+#[test]
+fn test_build_admins_includes_maritime_areas_when_configured() {
+    let tmp_dir = tempdir().expect("failed to make tempdir");
+    let area_parquet = tmp_dir.path().join("maritime-division-areas.parquet");
+
+    // Turn the Washington area into a maritime one
+    let duck_con = duckdb::Connection::open_in_memory().unwrap();
+    duck_con
+        .execute_batch(&format!(
+            "INSTALL spatial; LOAD spatial;
+             COPY (
+                SELECT * REPLACE (
+                    CASE WHEN subtype = 'region' THEN FALSE ELSE is_land END AS is_land,
+                    CASE WHEN subtype = 'region' THEN 'maritime' ELSE class END AS class
+                )
+                FROM read_parquet('tests/data/wa-division-areas.parquet')
+             ) TO '{}' (FORMAT 'parquet');",
+            area_parquet.display()
+        ))
+        .unwrap();
+
+    let admin_iso_codes = |admin_config: &AdminConfig| {
+        let sqlite_path = tmp_dir.path().join("test_admin.sqlite");
+        build_admins_from_geo_parquet(
+            "tests/data/wa-divisions.parquet",
+            &area_parquet.to_string_lossy(),
+            &sqlite_path.to_string_lossy(),
+            admin_config,
+            DurabilityProfile::Fast,
+            &AdminPass::default(),
+        )
+        .expect("admin building failed");
+
+        let conn = rusqlite::Connection::open(&sqlite_path).unwrap();
+        let iso_codes = conn
+            .prepare("SELECT iso_code FROM admins ORDER BY rowid")
+            .unwrap()
+            .query_map([], |row| row.get::<_, String>(0))
+            .unwrap()
+            .map(Result::unwrap)
+            .collect::<Vec<_>>();
+        iso_codes
+    };
+
+    assert_eq!(admin_iso_codes(&AdminConfig::default()), vec!["US"]);
+
+    let admin_config = AdminConfig {
+        include_maritime: true,
+        ..AdminConfig::default()
+    };
+    assert_eq!(admin_iso_codes(&admin_config), vec!["US", "WA"]);
+}
+
+#[test]
+fn test_build_admins_level_by_level() {
+    let tmp_dir = tempdir().expect("failed to make tempdir");
+    let sqlite_path = tmp_dir.path().join("test_admin.sqlite");
+    let build_levels = |levels: Vec<i64>, append: bool| {
+        build_admins_from_geo_parquet(
+            "tests/data/wa-divisions.parquet",
+            "tests/data/wa-division-areas.parquet",
+            &sqlite_path.to_string_lossy(),
+            &AdminConfig::default(),
+            DurabilityProfile::Fast,
+            &AdminPass { levels, append },
+        )
+    };
+    let admins = || {
+        let conn = rusqlite::Connection::open(&sqlite_path).unwrap();
+        let admins = conn
+            .prepare("SELECT rowid, admin_level, iso_code, parent_admin FROM admins ORDER BY rowid")
+            .unwrap()
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, i64>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, Option<i64>>(3)?,
+                ))
+            })
+            .unwrap()
+            .map(Result::unwrap)
+            .collect::<Vec<_>>();
+        admins
+    };
+
+    build_levels(vec![2], false).expect("level 2 pass failed");
+    let countries = admins();
+    assert_eq!(countries.len(), 1, "{countries:?}");
+    let (us_rowid, _, _, _) = countries[0];
+    assert_eq!(countries[0], (us_rowid, 2, "US".to_string(), None));
+
+    build_levels(vec![4], true).expect("level 4 pass failed");
+    assert_eq!(
+        admins(),
+        vec![
+            (us_rowid, 2, "US".to_string(), None),
+            (us_rowid + 1, 4, "WA".to_string(), Some(us_rowid)),
+        ]
+    );
+
+    let conn = rusqlite::Connection::open(&sqlite_path).unwrap();
+    let (drive_on_right, access_rows): (i64, i64) = conn
+        .query_row(
+            "SELECT (SELECT drive_on_right FROM admins WHERE iso_code = 'WA'),
+                    (SELECT COUNT(*) FROM admin_access WHERE admin_id = ?)",
+            [us_rowid],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .unwrap();
+    assert_eq!(drive_on_right, 1);
+    assert_eq!(access_rows, 1);
+
+    // A database built with every level has no division ids left to link appended admins to
+    build_levels(Vec::new(), false).expect("full build failed");
+    assert!(build_levels(vec![4], true).is_err());
+    assert!(build_levels(vec![6], false).is_err());
+}